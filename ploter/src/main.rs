@@ -1,16 +1,21 @@
 use prototype::{
-    stages::binary_compile::generate_function,
-    components::auxilary_functions::parse_plot_input_file
+    stages::compiler::Compiler,
+    components::{
+        auxilary_functions::parse_plot_input_file,
+        compilation_options::CompilationOptions
+    }
 };
 use csv;
 
 fn main() {
     println!("Started ploting");
     let plot_conf = parse_plot_input_file("./test_config.toml");
-    let fja = generate_function(
+    let compiler = Compiler::default();
+    let (fja, _) = compiler.compile_batch_function(
         &plot_conf.function,
         plot_conf.precision_center, 
-        plot_conf.poly_power
+        plot_conf.poly_power,
+        &CompilationOptions::default()
     );
 
     let mut wtr = csv::Writer::from_path(plot_conf.path).unwrap();    
@@ -19,10 +24,17 @@ fn main() {
     let mut cp = plot_conf.precision_center-plot_conf.epsilon;
     let step = 2.0*plot_conf.epsilon/(plot_conf.samples as f64);
 
+    let mut xs = Vec::<f64>::with_capacity(plot_conf.samples);
     for _ in 0..plot_conf.samples {
-        wtr.write_record(&[format!("{}", cp), format!("{}", fja(cp))]).unwrap();
+        xs.push(cp);
         cp = cp + step;
     }
+    let mut ys = vec![0.0; xs.len()];
+    fja.evaluate(&xs, &mut ys);
+
+    for (x, y) in xs.iter().zip(ys.iter()) {
+        wtr.write_record(&[format!("{}", x), format!("{}", y)]).unwrap();
+    }
 
     wtr.flush().unwrap();
     println!("Finnished ploting");
@@ -8,33 +8,35 @@ use prototype::{
         auxilary_functions::parse_input_file
     },
     stages::{
-        ir_compile::generate_ir,
-        linking::link_buffer
+        compiler::Compiler,
+        linking::BatchFunction
     }
 };
-use std::{
-    env::args, ffi::{CStr, CString}, process::exit, ptr::{self, NonNull}
-};
-use llvm_sys::{
-    core::*,
-    prelude::*,
-    ir_reader::LLVMParseIRInContext,
-    target::*,
-    target_machine::*
-};
+use std::env::args;
 
-extern "C" {
-    static __code_buffer: u8;  // Start of the reserved block, size is 16KB
-}
+// Number of points evaluated by a single call of the batch function
+const CHUNK_SIZE: usize = 4096;
 
-fn calculate_integral(fja: fn (f64) -> f64, r_start: f64, r_end: f64, samples: u64) -> f64 {
+fn calculate_integral(fja: BatchFunction, r_start: f64, r_end: f64, samples: u64) -> f64 {
     let mut x = r_start;
     let dx = (r_end-r_start)/(samples as f64);
-    let mut sum = fja(r_start);
+    let mut sum = 0.0;
+
+    let mut xs = Vec::<f64>::with_capacity(CHUNK_SIZE);
+    let mut ys = vec![0.0; CHUNK_SIZE];
+    let mut remaining = samples + 1;
+
+    while remaining > 0 {
+        let chunk = (remaining as usize).min(CHUNK_SIZE);
+        xs.clear();
+        for _ in 0..chunk {
+            xs.push(x);
+            x += dx;
+        }
 
-    for _ in 0..samples{
-        x += dx;
-        sum += fja(x);
+        fja.evaluate(&xs, &mut ys[..chunk]);
+        sum += ys[..chunk].iter().sum::<f64>();
+        remaining -= chunk as u64;
     }
 
     sum*dx
@@ -49,84 +51,8 @@ fn main(){
 
     let parameters = parse_input_file(&args[1]);
 
-    let llvm_ir = generate_ir(&parameters.function);
-    let llvm_ir_len = llvm_ir.len();
-    let ir_c_string = CString::new(llvm_ir).unwrap();
-    let fja;
-
-    unsafe {
-        let context = LLVMContextCreate();
-        let buffer = LLVMCreateMemoryBufferWithMemoryRangeCopy(
-            ir_c_string.as_ptr(),
-            llvm_ir_len, 
-            CString::new("LLVM IR").unwrap().as_ptr()
-        );
-
-        let mut module: LLVMModuleRef = ptr::null_mut();
-        let mut error: *mut i8 = ptr::null_mut();
-        if LLVMParseIRInContext(context, buffer, &mut module, &mut error) != 0 {
-            unrecoverable_error!("LLVM Error | Error occured while lexing and parsing the IR string", *error);
-        }
-
-        let result = LLVM_InitializeNativeTarget();
-        if result != 0 {
-            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native target.");
-        }
-
-        let result = LLVM_InitializeNativeAsmPrinter();
-        if result != 0 {
-            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native assembler printer.");
-        }
-
-        let triple = LLVMGetDefaultTargetTriple();
-        let mut target: LLVMTargetRef = ptr::null_mut();
-
-        if LLVMGetTargetFromTriple(triple, &mut target, &mut error) != 0 {
-            unrecoverable_error!("LLVM Error | Error getting target information", *error);
-        }
-
-        let target_machine = LLVMCreateTargetMachine(
-            target,
-            triple,
-            CString::new("generic").unwrap().as_ptr(),
-            CString::new("").unwrap().as_ptr(),
-            LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
-            LLVMRelocMode::LLVMRelocDefault,
-            LLVMCodeModel::LLVMCodeModelDefault
-        );
-
-        let mut memory_buffer: LLVMMemoryBufferRef = ptr::null_mut();
-        if LLVMTargetMachineEmitToMemoryBuffer(
-            target_machine,
-            module,
-            LLVMCodeGenFileType::LLVMObjectFile,
-            &mut error,
-            &mut memory_buffer
-        ) != 0{
-            let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
-            unrecoverable_error!("LLVM Error | Error emitting machine code to buffer", error_message);
-        }
-
-        let buffer_start = LLVMGetBufferStart(memory_buffer) as *mut u8;
-        let buffer_size = LLVMGetBufferSize(memory_buffer);
-
-        let buffer_data = std::slice::from_raw_parts_mut(buffer_start, buffer_size as usize);
-
-        let object_space: *const u8 = &__code_buffer;
-
-        fja= link_buffer(
-            buffer_data,
-            NonNull::new_unchecked(object_space as *mut u8)
-        );
-
-        std::ptr::copy_nonoverlapping(buffer_data.as_ptr(), object_space as *mut u8, buffer_size);
-
-        LLVMDisposeMemoryBuffer(memory_buffer);
-        LLVMDisposeModule(module);
-        LLVMDisposeTargetMachine(target_machine);
-        LLVMContextDispose(context);
-        LLVMDisposeMessage(triple);
-    }
+    let compiler = Compiler::default();
+    let fja = compiler.compile_exact_batch_function(&parameters.function);
 
     let result = calculate_integral(fja, parameters.range_start, parameters.range_end, parameters.samples);
 
@@ -1,5 +1,13 @@
+use std::process::Command;
+
 fn main() {
-    println!("cargo:rustc-link-arg=-Tprototype/linker.ld");
     println!("cargo:rustc-env=RUSTFLAGS=-C target-feature=+avx,-avx512f");
-}
 
+    // version of the LLVM build llvm-sys links, part of the compilation cache keys
+    let llvm_version = std::env::var("DEP_LLVM_16_CONFIG_PATH").ok()
+        .and_then(|llvm_config| Command::new(llvm_config).arg("--version").output().ok())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=FJA_LLVM_VERSION={}", llvm_version);
+}
@@ -0,0 +1,104 @@
+use super::polynomials::TsPoly;
+
+/// Number of rows of every table, covers all powers a `TsPoly` can hold.
+pub const TABLE_SIZE: usize = TsPoly::DEFAULT_MAX_POW;
+
+// 34! is the largest factorial that fits into u128, so the exact tables are valid up to that size
+const _: () = assert!(TABLE_SIZE <= 35, "exact factorial table would overflow u128");
+
+const fn build_factorials() -> [u128; TABLE_SIZE] {
+    let mut table = [1u128; TABLE_SIZE];
+    let mut n = 1;
+    while n < TABLE_SIZE {
+        table[n] = table[n-1]*n as u128;
+        n += 1;
+    }
+    table
+}
+
+/// Pascal's triangle, BINOMIALS[n][k] = n choose k, 0 for k > n.
+const fn build_binomials() -> [[u128; TABLE_SIZE]; TABLE_SIZE] {
+    let mut table = [[0u128; TABLE_SIZE]; TABLE_SIZE];
+    let mut n = 0;
+    while n < TABLE_SIZE {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n-1][k-1] + table[n-1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+const fn build_factorials_f64() -> [f64; TABLE_SIZE] {
+    let mut table = [0.0; TABLE_SIZE];
+    let mut n = 0;
+    while n < TABLE_SIZE {
+        table[n] = FACTORIALS[n] as f64;
+        n += 1;
+    }
+    table
+}
+
+const fn build_inverse_factorials() -> [f64; TABLE_SIZE] {
+    let mut table = [0.0; TABLE_SIZE];
+    let mut n = 0;
+    while n < TABLE_SIZE {
+        table[n] = 1.0/FACTORIALS_F64[n];
+        n += 1;
+    }
+    table
+}
+
+const fn build_binomials_f64() -> [[f64; TABLE_SIZE]; TABLE_SIZE] {
+    let mut table = [[0.0; TABLE_SIZE]; TABLE_SIZE];
+    let mut n = 0;
+    while n < TABLE_SIZE {
+        let mut k = 0;
+        while k <= n {
+            table[n][k] = BINOMIALS[n][k] as f64;
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+pub const FACTORIALS: [u128; TABLE_SIZE] = build_factorials();
+/// Correctly rounded n!, unlike a running product which accumulates rounding errors past 22!.
+pub const FACTORIALS_F64: [f64; TABLE_SIZE] = build_factorials_f64();
+pub const INVERSE_FACTORIALS: [f64; TABLE_SIZE] = build_inverse_factorials();
+pub const BINOMIALS: [[u128; TABLE_SIZE]; TABLE_SIZE] = build_binomials();
+pub const BINOMIALS_F64: [[f64; TABLE_SIZE]; TABLE_SIZE] = build_binomials_f64();
+
+/// n!, computed in f64 outside of the table.
+#[inline(always)]
+pub fn factorial(n: usize) -> f64 {
+    if n < TABLE_SIZE {
+        FACTORIALS_F64[n]
+    }else{
+        (TABLE_SIZE..=n).fold(FACTORIALS_F64[TABLE_SIZE-1], |acc, i| acc*i as f64)
+    }
+}
+
+/// 1/n!, computed in f64 outside of the table.
+#[inline(always)]
+pub fn inverse_factorial(n: usize) -> f64 {
+    if n < TABLE_SIZE { INVERSE_FACTORIALS[n] } else { 1.0/factorial(n) }
+}
+
+/// n choose k, computed multiplicatively in f64 outside of the table, so it can't overflow.
+#[inline(always)]
+pub fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    if n < TABLE_SIZE {
+        return BINOMIALS_F64[n][k];
+    }
+
+    let k = k.min(n-k);
+    (0..k).fold(1.0, |acc, i| acc*(n-i) as f64/(i+1) as f64)
+}
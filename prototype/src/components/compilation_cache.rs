@@ -0,0 +1,127 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::{
+    terminal_decoration::Color,
+    compilation_options::CompilationOptions,
+    elf_object::ElfObject
+};
+use crate::stages::binary_compile::TargetDescription;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+    sync::atomic::{AtomicUsize, Ordering}
+};
+
+/// Directory of the cache used by `generate_function` and friends, the cache is off while it isn't set.
+pub const CACHE_DIRECTORY_VARIABLE: &str = "FJA_CACHE_DIR";
+
+/// Version of the generated code, bump it with every change to what a formula compiles to (evaluation schemes, range reduction,
+/// relocations, ...), so entries written by older code generation are never loaded.
+pub const CODEGEN_VERSION: u32 = 1;
+
+/// Start of every cache entry, followed by the length of the key description, the description and the object file.
+const ENTRY_MAGIC: &[u8; 8] = b"FJACACHE";
+
+/// Numbers the temporary files of the entries, threads of one process may store the same key at once.
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF29CE484222325;
+const FNV_PRIME: u64 = 0x100000001B3;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Everything the emitted object file depends on, written out as text and hashed.
+/// The description is stored with the entry, so a hash collision reads as a miss instead of the wrong function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheKey {
+    description: String,
+    hash: u64,
+}
+
+impl CacheKey {
+    /// Whitespace of the formula is ignored, `target` is the resolved CPU and features rather than `options.target_cpu`.
+    pub fn new(function: &str, precision_center: f64, max_power: usize, target: &TargetDescription, options: &CompilationOptions) -> Self {
+        let formula: String = function.chars().filter(|ch| !ch.is_whitespace()).collect();
+        let description = format!(
+            "fja {}\ncodegen {}\nllvm {}\nformula {}\ncenter {:016x}\ndegree {}\ncpu {}\nfeatures {}\nfloat {:?}\napproximation {:?}\nscheme {:?}\nfma {}\nbasis {:?}\nrange reduction {}\npipeline {:?}\nderivatives {}\n",
+            env!("CARGO_PKG_VERSION"), CODEGEN_VERSION, env!("FJA_LLVM_VERSION"), formula, precision_center.to_bits(), max_power, target.cpu, target.features, options.float_mode,
+            options.approximation, options.evaluation_scheme, options.fused_multiply_add, options.basis, options.range_reduction,
+            options.pass_pipeline, options.derivatives
+        );
+        let hash = fnv1a(description.as_bytes());
+        CacheKey { description, hash }
+    }
+
+    /// 64 bit FNV-1a hash of the description, names the entry file.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// Object files emitted for Taylor approximated formulas, one file per `CacheKey` in `directory`.
+/// Entries are written to a temporary file and renamed, so processes sharing the directory never read a partial entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompilationCache {
+    directory: PathBuf,
+}
+
+impl CompilationCache {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let directory = directory.into();
+        if let Err(error) = fs::create_dir_all(&directory) {
+            unrecoverable_error!("Compilation cache error | Failed to create the cache directory", format!("{}: {}", directory.display(), error));
+        }
+        CompilationCache { directory }
+    }
+
+    /// Cache in the directory named by `FJA_CACHE_DIR`, None if the variable isn't set.
+    pub fn from_environment() -> Option<Self> {
+        std::env::var_os(CACHE_DIRECTORY_VARIABLE).filter(|directory| !directory.is_empty()).map(Self::new)
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.directory.join(format!("{:016x}.o", key.hash))
+    }
+
+    /// Object file stored under the key. Missing, unreadable, colliding and truncated or malformed entries are all misses.
+    pub fn lookup(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        let entry = fs::read(self.entry_path(key)).ok()?;
+        let description = key.description.as_bytes();
+        let header_size = ENTRY_MAGIC.len() + 8;
+        if entry.len() < header_size || entry[..ENTRY_MAGIC.len()] != ENTRY_MAGIC[..] {
+            return None;
+        }
+        let description_size = u64::from_le_bytes(entry[ENTRY_MAGIC.len()..header_size].try_into().unwrap()) as usize;
+        if description_size != description.len() || entry.get(header_size..header_size + description_size) != Some(description) {
+            return None;
+        }
+        let object_file = &entry[header_size + description_size..];
+        ElfObject::try_parse(object_file).ok()?.defined_symbol("fja")?;
+        Some(object_file.to_vec())
+    }
+
+    /// Stores the object file, replacing the entry of a colliding key. A failed write only costs the next lookup a miss.
+    pub fn store(&self, key: &CacheKey, object_file: &[u8]) {
+        let mut entry = ENTRY_MAGIC.to_vec();
+        entry.extend((key.description.len() as u64).to_le_bytes());
+        entry.extend(key.description.as_bytes());
+        entry.extend(object_file);
+
+        let path = self.entry_path(key);
+        let temporary = path.with_extension(format!("{}.{}.tmp", std::process::id(), TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)));
+        if fs::write(&temporary, entry).and_then(|_| fs::rename(&temporary, &path)).is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+}
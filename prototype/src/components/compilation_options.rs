@@ -0,0 +1,130 @@
+/// Selects what kind of approximant replaces the elementary functions of the compiled formula.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApproximationMode {
+    /// Truncated Taylor polynomial of degree `max_power` (default).
+    Taylor,
+    /// Padé [max_power-denominator_degree / denominator_degree] rational approximant built from the
+    /// same Taylor coefficients. Converges much better than the polynomial near poles and singularities.
+    Pade { denominator_degree: usize },
+}
+
+/// Selects the order of operations used when a polynomial is evaluated in the generated IR.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EvaluationScheme {
+    /// Powers of x are accumulated explicitly and shared between all polynomials of x (default).
+    SharedPowers,
+    /// Nested multiply-adds, least operations but one long dependency chain.
+    Horner,
+    /// Pairwise multiply-adds joined with x^2, x^4, ..., shorter dependency chains for instruction level parallelism.
+    Estrin,
+}
+
+/// Selects the variable in which polynomials of x are expressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolynomialBasis {
+    /// Series are expanded into powers of x (default).
+    Monomial,
+    /// Series are kept in powers of (x - precision_center), the generated code subtracts the center once.
+    /// Avoids the cancellation of large alternating coefitients when the center is far from 0.
+    Shifted,
+}
+
+/// Floating point semantics the generated code is allowed to assume.
+/// The matching fast-math flags are put on every floating point instruction and call, see `IrModule::with_fast_math_flags`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatMode {
+    /// Every operation is rounded as written, results are bit-reproducible (default).
+    Strict,
+    /// Multiply-adds may be fused into a single FMA when the target has one.
+    Contract,
+    /// Contraction plus reassociation, no NaNs/infinities/signed zeros and approximate functions.
+    Fast,
+}
+
+impl FloatMode {
+    /// Fast-math flags of the instructions, None if they are left without flags.
+    pub fn instruction_flags(&self) -> Option<&'static str> {
+        match self {
+            FloatMode::Strict => None,
+            FloatMode::Contract => Some("contract"),
+            FloatMode::Fast => Some("fast"),
+        }
+    }
+}
+
+/// Mid-level LLVM optimization pipeline run on the generated module before code emission.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PassPipeline {
+    /// Only the backend optimizations of the target machine are applied (default).
+    Disabled,
+    /// "default<O{level}>" pipeline of the new pass manager, level is 0 to 3.
+    Default(u8),
+    /// Any pipeline description accepted by `opt -passes=...`, eg. "instcombine,reassociate,gvn".
+    Custom(String),
+}
+
+impl PassPipeline {
+    /// Pipeline description passed to LLVMRunPasses, None if no passes should run.
+    pub fn description(&self) -> Option<String> {
+        match self {
+            PassPipeline::Disabled => None,
+            PassPipeline::Default(level) => Some(format!("default<O{}>", level)),
+            PassPipeline::Custom(passes) => Some(passes.clone()),
+        }
+    }
+}
+
+/// How the emitted object file is turned into callable code, selected per `Compiler`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecutionBackend {
+    /// Our own linker, `link_buffer` into an executable region (default).
+    Linker,
+    /// LLVM's ORC LLJIT links the same object file, a reference for the correctness and latency of the linker.
+    OrcJit,
+}
+
+/// CPU the machine code is generated for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TargetCpu {
+    /// Baseline of the target architecture, runs everywhere (default).
+    Generic,
+    /// CPU and features of the machine doing the compilation, eg. AVX2 and FMA where available.
+    Host,
+    /// Any CPU name and feature string known to LLVM, eg. cpu "haswell" with features "+avx2,+fma".
+    Explicit { cpu: String, features: String },
+}
+
+/// Options that control how a formula is turned into machine code by `generate_function_with_options`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompilationOptions {
+    pub approximation: ApproximationMode,
+    pub evaluation_scheme: EvaluationScheme,
+    /// Emit every multiply-add of polynomial evaluation as a call to the llvm.fma.f64 intrinsic.
+    pub fused_multiply_add: bool,
+    pub basis: PolynomialBasis,
+    /// Evaluate sin, cos, exp and ln through argument range reduction, so they stay accurate far from the precision center.
+    pub range_reduction: bool,
+    pub pass_pipeline: PassPipeline,
+    pub float_mode: FloatMode,
+    pub target_cpu: TargetCpu,
+    /// Highest derivative written by `fja_jet(x, out)`, out[k] = f^(k)(x). 0 doesn't emit the jet function at all.
+    /// Exactly evaluated subterms are differentiated symbolically, which only knows the lexer's functions: formulas calling
+    /// resolver functions (`bessel_j0`, `tan`, `sinh`, ...) can't be compiled with derivatives.
+    pub derivatives: usize,
+}
+
+impl Default for CompilationOptions {
+    fn default() -> Self {
+        CompilationOptions {
+            approximation: ApproximationMode::Taylor,
+            evaluation_scheme: EvaluationScheme::SharedPowers,
+            fused_multiply_add: false,
+            basis: PolynomialBasis::Monomial,
+            range_reduction: false,
+            pass_pipeline: PassPipeline::Disabled,
+            float_mode: FloatMode::Strict,
+            target_cpu: TargetCpu::Generic,
+            derivatives: 0,
+        }
+    }
+}
@@ -0,0 +1,302 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::terminal_decoration::Color;
+use std::process::exit;
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_NOBITS: u32 = 8;
+
+pub const SHF_WRITE: u64 = 0x1;
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_EXECINSTR: u64 = 0x4;
+
+pub const SHN_UNDEF: usize = 0;
+pub const SHN_LORESERVE: usize = 0xFF00;
+pub const SHN_ABS: usize = 0xFFF1;
+pub const SHN_COMMON: usize = 0xFFF2;
+
+pub const STT_SECTION: u8 = 3;
+
+pub const R_X86_64_64: u32 = 1;
+pub const R_X86_64_PC32: u32 = 2;
+pub const R_X86_64_PLT32: u32 = 4;
+pub const R_X86_64_GLOB_DAT: u32 = 6;
+pub const R_X86_64_RELATIVE: u32 = 8;
+pub const R_X86_64_GOTPCREL: u32 = 9;
+pub const R_X86_64_32S: u32 = 11;
+pub const R_X86_64_GOTPCRELX: u32 = 41;
+pub const R_X86_64_REX_GOTPCRELX: u32 = 42;
+
+const ELF_HEADER_SIZE: usize = 0x40;
+const SECTION_HEADER_SIZE: usize = 0x40;
+const SYMBOL_SIZE: usize = 24;
+const RELA_SIZE: usize = 24;
+
+/// Entry of the section header table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section<'a> {
+    pub name: &'a str,
+    pub kind: u32,
+    pub flags: u64,
+    /// Offset of the section in the file, meaningless for SHT_NOBITS sections.
+    pub offset: usize,
+    pub size: usize,
+    pub link: usize,
+    pub info: usize,
+    pub alignment: usize,
+}
+
+impl Section<'_> {
+    /// Occupies memory while the code runs, eg. .text.*, .rodata.*, .data.
+    pub fn is_allocated(&self) -> bool {
+        self.flags & SHF_ALLOC != 0
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.flags & SHF_EXECINSTR != 0
+    }
+}
+
+/// Entry of the symbol table, `section` is SHN_UNDEF for symbols defined outside of the object file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub value: usize,
+    pub size: usize,
+    pub section: usize,
+    pub kind: u8,
+    pub binding: u8,
+}
+
+impl Symbol<'_> {
+    /// Defined in one of the sections of the object file.
+    pub fn is_defined(&self) -> bool {
+        self.section != SHN_UNDEF && self.section < SHN_LORESERVE
+    }
+}
+
+/// Entry of a SHT_RELA section, `offset` is relative to the start of the section it patches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    pub offset: usize,
+    pub symbol: usize,
+    pub kind: u32,
+    pub addend: i64,
+}
+
+/// Relocations of the section with index `target`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelocationSection {
+    pub target: usize,
+    pub relocations: Vec<Relocation>,
+}
+
+/// Parsed ELF64 little endian x86-64 relocatable object, as emitted by LLVM.
+/// Every read is bounds checked, a malformed object ends in an unrecoverable error (or an Err of `try_parse`) instead of a wrong link.
+#[derive(Debug)]
+pub struct ElfObject<'a> {
+    bytes: &'a [u8],
+    section_table_end: usize,
+    pub sections: Vec<Section<'a>>,
+    pub symbols: Vec<Symbol<'a>>,
+    pub relocation_sections: Vec<RelocationSection>,
+}
+
+fn malformed(cause: String) -> ! {
+    unrecoverable_error!("Linker Error | Malformed object file", cause);
+}
+
+fn slice<'a>(bytes: &'a [u8], offset: usize, len: usize, what: &str) -> Result<&'a [u8], String> {
+    match offset.checked_add(len) {
+        Some(end) if end <= bytes.len() => Ok(&bytes[offset..end]),
+        _ => Err(format!("{} ({} bytes at offset {}) lies outside of the {} bytes of the file", what, len, offset, bytes.len()))
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize, what: &str) -> Result<u16, String> {
+    Ok(u16::from_le_bytes(slice(bytes, offset, 2, what)?.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], offset: usize, what: &str) -> Result<u32, String> {
+    Ok(u32::from_le_bytes(slice(bytes, offset, 4, what)?.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize, what: &str) -> Result<u64, String> {
+    Ok(u64::from_le_bytes(slice(bytes, offset, 8, what)?.try_into().unwrap()))
+}
+
+/// Null terminated string at `offset` of a string table.
+fn read_string<'a>(table: &'a [u8], offset: usize, what: &str) -> Result<&'a str, String> {
+    let tail = table.get(offset..)
+        .ok_or_else(|| format!("Name of {} starts at {}, past the end of its string table ({} bytes)", what, offset, table.len()))?;
+    let end = tail.iter().position(|&b| b == 0).ok_or_else(|| format!("Name of {} isn't null terminated", what))?;
+    std::str::from_utf8(&tail[..end]).map_err(|_| format!("Name of {} isn't valid UTF-8", what))
+}
+
+impl<'a> ElfObject<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Self {
+        Self::try_parse(bytes).unwrap_or_else(|cause| malformed(cause))
+    }
+
+    /// Same as `parse`, returns what is wrong with a malformed object instead of stopping, eg. for entries of the compilation cache.
+    pub fn try_parse(bytes: &'a [u8]) -> Result<Self, String> {
+        let ident = slice(bytes, 0, 16, "ELF identification")?;
+        if ident[..4] != [0x7F, b'E', b'L', b'F'] {
+            return Err(String::from("The buffer doesn't start with the ELF magic number"));
+        }
+        if ident[4] != 2 || ident[5] != 1 {
+            return Err(String::from("Only 64 bit little endian objects are supported"));
+        }
+        slice(bytes, 0, ELF_HEADER_SIZE, "ELF header")?;
+        if read_u16(bytes, 0x10, "e_type")? != 1 {
+            return Err(String::from("The object isn't relocatable (ET_REL)"));
+        }
+        if read_u16(bytes, 0x12, "e_machine")? != 62 {
+            return Err(String::from("The object isn't compiled for x86-64"));
+        }
+
+        let section_table_offset = read_u64(bytes, 0x28, "e_shoff")? as usize;
+        let section_header_size = read_u16(bytes, 0x3A, "e_shentsize")? as usize;
+        let section_count = read_u16(bytes, 0x3C, "e_shnum")? as usize;
+        let names_index = read_u16(bytes, 0x3E, "e_shstrndx")? as usize;
+        if section_header_size != SECTION_HEADER_SIZE {
+            return Err(format!("Section headers have {} bytes instead of {}", section_header_size, SECTION_HEADER_SIZE));
+        }
+        if names_index >= section_count {
+            return Err(format!("Section name table index {} is out of the {} sections", names_index, section_count));
+        }
+
+        // e_shoff comes from the file, so the table is checked once and every header lies inside it
+        let section_table_end = section_count.checked_mul(SECTION_HEADER_SIZE).and_then(|size| section_table_offset.checked_add(size))
+            .ok_or_else(|| format!("Section header table at {:#x} with {} sections overflows", section_table_offset, section_count))?;
+        let section_table = slice(bytes, section_table_offset, section_table_end - section_table_offset, "Section header table")?;
+
+        let header_at = |index: usize| -> Result<_, String> {
+            let header = slice(section_table, index*SECTION_HEADER_SIZE, SECTION_HEADER_SIZE, "Section header")?;
+            Ok((
+                read_u32(header, 0, "sh_name")? as usize,
+                read_u32(header, 4, "sh_type")?,
+                read_u64(header, 8, "sh_flags")?,
+                read_u64(header, 0x18, "sh_offset")? as usize,
+                read_u64(header, 0x20, "sh_size")? as usize,
+                read_u32(header, 0x28, "sh_link")? as usize,
+                read_u32(header, 0x2C, "sh_info")? as usize,
+                read_u64(header, 0x30, "sh_addralign")? as usize
+            ))
+        };
+
+        let (_, _, _, names_offset, names_size, _, _, _) = header_at(names_index)?;
+        let names = slice(bytes, names_offset, names_size, "Section name table")?;
+
+        let sections = (0..section_count).map(|index| {
+            let (name, kind, flags, offset, size, link, info, alignment) = header_at(index)?;
+            let section = Section { name: read_string(names, name, &format!("section {}", index))?, kind, flags, offset, size, link, info, alignment };
+            if kind != SHT_NOBITS {
+                slice(bytes, offset, size, &format!("Section '{}'", section.name))?;
+            }
+            Ok(section)
+        }).collect::<Result<Vec<Section>, String>>()?;
+
+        let mut object = ElfObject { bytes, section_table_end, sections, symbols: Vec::new(), relocation_sections: Vec::new() };
+        object.parse_symbols()?;
+        object.parse_relocations()?;
+        Ok(object)
+    }
+
+    fn parse_symbols(&mut self) -> Result<(), String> {
+        let symbol_table = self.sections.iter().position(|section| section.kind == SHT_SYMTAB)
+            .ok_or_else(|| String::from("Symbol table wasn't found"))?;
+        let string_table = self.sections[symbol_table].link;
+        if self.sections.get(string_table).is_none_or(|section| section.kind != SHT_STRTAB) {
+            return Err(format!("Symbol table links to section {}, which isn't a string table", string_table));
+        }
+
+        let table = self.section_bytes(symbol_table);
+        let names = self.section_bytes(string_table);
+        self.symbols = table.chunks(SYMBOL_SIZE).enumerate().map(|(index, entry)| {
+            if entry.len() != SYMBOL_SIZE {
+                return Err(format!("Symbol table size isn't a multiple of {} bytes", SYMBOL_SIZE));
+            }
+            let info = entry[4];
+            let symbol = Symbol {
+                name: read_string(names, read_u32(entry, 0, "st_name")? as usize, &format!("symbol {}", index))?,
+                value: read_u64(entry, 8, "st_value")? as usize,
+                size: read_u64(entry, 16, "st_size")? as usize,
+                section: read_u16(entry, 6, "st_shndx")? as usize,
+                kind: info & 0xF,
+                binding: info >> 4
+            };
+            if symbol.is_defined() && symbol.section >= self.sections.len() {
+                return Err(format!("Symbol '{}' is defined in section {}, which doesn't exist", symbol.name, symbol.section));
+            }
+            Ok(symbol)
+        }).collect::<Result<_, String>>()?;
+        Ok(())
+    }
+
+    fn parse_relocations(&mut self) -> Result<(), String> {
+        for index in 0..self.sections.len() {
+            let section = &self.sections[index];
+            if section.kind != SHT_RELA {
+                continue;
+            }
+            if section.info >= self.sections.len() {
+                return Err(format!("Relocation section '{}' patches section {}, which doesn't exist", section.name, section.info));
+            }
+
+            let target = section.info;
+            let target_size = self.sections[target].size;
+            let relocations = self.section_bytes(index).chunks(RELA_SIZE).map(|entry| {
+                if entry.len() != RELA_SIZE {
+                    return Err(format!("Relocation section '{}' size isn't a multiple of {} bytes", self.sections[index].name, RELA_SIZE));
+                }
+                let info = read_u64(entry, 8, "r_info")?;
+                let relocation = Relocation {
+                    offset: read_u64(entry, 0, "r_offset")? as usize,
+                    symbol: (info >> 32) as usize,
+                    kind: info as u32,
+                    addend: read_u64(entry, 16, "r_addend")? as i64
+                };
+                if relocation.symbol >= self.symbols.len() {
+                    return Err(format!("Relocation refers to symbol {}, the symbol table has {} entries", relocation.symbol, self.symbols.len()));
+                }
+                if relocation.offset >= target_size {
+                    return Err(format!("Relocation at {} lies outside of section '{}' ({} bytes)", relocation.offset, self.sections[target].name, target_size));
+                }
+                Ok(relocation)
+            }).collect::<Result<_, String>>()?;
+            self.relocation_sections.push(RelocationSection { target, relocations });
+        }
+        Ok(())
+    }
+
+    /// Contents of a section, empty for SHT_NOBITS sections.
+    pub fn section_bytes(&self, index: usize) -> &'a [u8] {
+        let section = &self.sections[index];
+        if section.kind == SHT_NOBITS {
+            return &[];
+        }
+        // checked by `parse`
+        &self.bytes[section.offset..section.offset + section.size]
+    }
+
+    /// Bytes of the file that hold headers or section contents, anything after them can be used by the linker.
+    pub fn file_size(&self) -> usize {
+        self.sections.iter()
+            .filter(|section| section.kind != SHT_NOBITS)
+            .map(|section| section.offset + section.size)
+            .fold(self.section_table_end.max(ELF_HEADER_SIZE), usize::max)
+    }
+
+    pub fn section_index(&self, name: &str) -> Option<usize> {
+        self.sections.iter().position(|section| section.name == name)
+    }
+
+    /// Symbol with the name defined in the object, not one it only references.
+    pub fn defined_symbol(&self, name: &str) -> Option<&Symbol<'a>> {
+        self.symbols.iter().find(|symbol| symbol.name == name && symbol.is_defined())
+    }
+}
@@ -0,0 +1,139 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::{
+    symbol_resolver::global_resolver,
+    terminal_decoration::Color
+};
+use std::{
+    ptr::{self, NonNull},
+    process::exit
+};
+
+/// Generated code calls the external functions through 32 bit PC relative offsets,
+/// so its memory is preferably mapped at most this far away from libm.
+const CALL_REACH: usize = 1 << 30;
+/// Distance between two addresses tried while looking for free memory close to the program.
+const SEARCH_STEP: usize = 64 << 20;
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Anonymous memory for the machine code of a single function, mapped RW while it is written and RX afterwards, never both.
+/// The mapping is released when the region is dropped, so functions pointing into it must not outlive it.
+pub struct ExecutableRegion {
+    ptr: NonNull<u8>,
+    len: usize,
+    executable: bool
+}
+
+impl ExecutableRegion {
+    /// Maps at least `size` writable bytes, preferably within reach of the libm functions.
+    /// If there's no free memory close to them the kernel picks the address, calls then go through trampolines.
+    pub fn allocate(size: usize) -> Self {
+        let anchor = global_resolver().address("sin").unwrap_or(Self::allocate as *const () as usize);
+        Self::allocate_near(size, anchor).unwrap_or_else(|| Self::allocate_anywhere(size))
+    }
+
+    /// Maps at least `size` writable bytes within reach of `address`, None if no free memory was found.
+    pub fn allocate_near(size: usize, address: usize) -> Option<Self> {
+        let page = page_size();
+        let len = size.max(1).div_ceil(page)*page;
+        let anchor = address & !(page-1);
+
+        let lower = (1..CALL_REACH/SEARCH_STEP).filter_map(|step| anchor.checked_sub(step*SEARCH_STEP));
+        let upper = (1..CALL_REACH/SEARCH_STEP).filter_map(|step| anchor.checked_add(step*SEARCH_STEP));
+        for hint in lower.chain(upper) {
+            let mapped = unsafe {
+                libc::mmap(
+                    hint as *mut libc::c_void,
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED_NOREPLACE,
+                    -1,
+                    0
+                )
+            };
+            if mapped == libc::MAP_FAILED {
+                continue;
+            }
+
+            // Kernels older than 4.17 treat the address only as a hint
+            if (mapped as usize).abs_diff(anchor) + len > CALL_REACH {
+                unsafe { libc::munmap(mapped, len); }
+                continue;
+            }
+
+            return Some(ExecutableRegion { ptr: NonNull::new(mapped as *mut u8).unwrap(), len, executable: false });
+        }
+        None
+    }
+
+    /// Maps at least `size` writable bytes wherever the kernel places them.
+    pub fn allocate_anywhere(size: usize) -> Self {
+        let page = page_size();
+        let len = size.max(1).div_ceil(page)*page;
+        let mapped = unsafe {
+            libc::mmap(ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0)
+        };
+        if mapped == libc::MAP_FAILED {
+            unrecoverable_error!("Memory error | Failed to map memory for the generated code", std::io::Error::last_os_error());
+        }
+        ExecutableRegion { ptr: NonNull::new(mapped as *mut u8).unwrap(), len, executable: false }
+    }
+
+    /// Maps a region holding `code`, which is executable right away.
+    pub fn with_code(code: &[u8]) -> Self {
+        let mut region = Self::allocate(code.len());
+        region.write(0, code);
+        region.make_executable();
+        region
+    }
+
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.executable
+    }
+
+    pub fn write(&mut self, offset: usize, bytes: &[u8]) {
+        if self.executable {
+            unrecoverable_error!("Memory error | Write to generated code", "The region was already made executable, it can't be written anymore");
+        }
+        if offset + bytes.len() > self.len {
+            unrecoverable_error!("Memory error | Write to generated code", format!("{} bytes at offset {} don't fit into a region of {} bytes", bytes.len(), offset, self.len));
+        }
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), self.ptr.as_ptr().add(offset), bytes.len()); }
+    }
+
+    /// Switches the region from RW to RX.
+    pub fn make_executable(&mut self) {
+        if unsafe { libc::mprotect(self.ptr.as_ptr() as *mut libc::c_void, self.len, libc::PROT_READ | libc::PROT_EXEC) } != 0 {
+            unrecoverable_error!("Memory error | Failed to make the generated code executable", std::io::Error::last_os_error());
+        }
+        self.executable = true;
+    }
+
+    /// Keeps the region mapped for the rest of the program, eg. for a function whose compiler is dropped.
+    pub fn leak(self) -> NonNull<u8> {
+        let ptr = self.ptr;
+        std::mem::forget(self);
+        ptr
+    }
+}
+
+impl Drop for ExecutableRegion {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len); }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::components::{
+    object_type_definitions::{Func, Node},
+    symbol_resolver::global_resolver,
+    symbolic_differentiation::{postfix_to_tree, tree_to_postfix}
+};
+use std::f64::consts::FRAC_PI_2;
+
+/// Simplifies a postfix sequence through its expression tree, see `Node::simplified`.
+pub fn simplify_postfix(postfix: &mut Vec<Func>) {
+    *postfix = tree_to_postfix(&postfix_to_tree(postfix).simplified());
+}
+
+impl Node {
+    /// Bottom up constant folding, 0/1 elimination and a few identities (u-u, u/u, exp(ln(u)), sin(u)^2+cos(u)^2, ...).
+    /// Identities are applied as in real arithmetic, eg. x*0 is 0 even though x could be infinite.
+    pub fn simplified(&self) -> Node {
+        let left = self.left.as_ref().map(|left| left.simplified());
+        let right = self.right.as_ref().map(|right| right.simplified());
+        let (a, b) = (left.as_ref().and_then(constant_value), right.as_ref().and_then(constant_value));
+
+        if let Some(folded) = fold_constant(&self.op, a, b) {
+            return Node::from_value(folded);
+        }
+
+        let (left, right) = match (left, right) {
+            (Some(left), Some(right)) => (left, right),
+            (Some(argument), None) => return simplified_unary(&self.op, argument),
+            (left, right) => return Node { left: left.map(Box::new), right: right.map(Box::new), op: self.op.clone() }
+        };
+
+        match (&self.op, a, b) {
+            (Func::Add, Some(0.0), _) => right,
+            (Func::Add | Func::Sub, _, Some(0.0)) => left,
+            (Func::Mul, Some(0.0), _) | (Func::Mul, _, Some(0.0)) | (Func::Div, Some(0.0), _) => Node::from_value(0.0),
+            (Func::Mul, Some(1.0), _) => right,
+            (Func::Mul | Func::Div | Func::Pow, _, Some(1.0)) => left,
+            (Func::Pow, _, Some(0.0)) | (Func::Pow, Some(1.0), _) => Node::from_value(1.0),
+            (Func::Sub, _, _) if left == right => Node::from_value(0.0),
+            (Func::Div, _, _) if left == right => Node::from_value(1.0),
+            (Func::Add, _, _) if left == right => Node::binary(Func::Mul, Node::from_value(2.0), left),
+            (Func::Add, _, _) if is_pythagorean_identity(&left, &right) || is_pythagorean_identity(&right, &left) => Node::from_value(1.0),
+            // 0-(0-u) = u
+            (Func::Sub, Some(0.0), _) if right.op == Func::Sub && right.left.as_deref().and_then(constant_value) == Some(0.0) => *right.right.unwrap(),
+            // c1*(c2*u) = (c1*c2)*u
+            (Func::Mul, Some(outer), _) if right.op == Func::Mul && right.left.as_deref().and_then(constant_value).is_some() => {
+                let inner = right.left.as_deref().and_then(constant_value).unwrap();
+                Node::binary(Func::Mul, Node::from_value(outer*inner), *right.right.unwrap())
+            },
+            // u*c = c*u, so constants end up in front
+            (Func::Mul, None, Some(_)) => Node::binary(Func::Mul, right, left),
+            _ => Node::binary(self.op.clone(), left, right)
+        }
+    }
+}
+
+fn simplified_unary(op: &Func, argument: Node) -> Node {
+    match (op, &argument.op) {
+        (Func::Exp, Func::Ln) | (Func::Ln, Func::Exp) => *argument.left.unwrap(),
+        _ => Node::unary(op.clone(), argument)
+    }
+}
+
+/// u for u*u and u^2.
+fn squared_base(node: &Node) -> Option<&Node> {
+    let (left, right) = (node.left.as_deref()?, node.right.as_deref()?);
+    match node.op {
+        Func::Mul if left == right => Some(left),
+        Func::Pow if constant_value(right) == Some(2.0) => Some(left),
+        _ => None
+    }
+}
+
+/// True for sin(u)^2 and cos(u)^2 of the same u.
+fn is_pythagorean_identity(sine: &Node, cosine: &Node) -> bool {
+    match (squared_base(sine), squared_base(cosine)) {
+        (Some(sine), Some(cosine)) => sine.op == Func::Sin && cosine.op == Func::Cos && sine.left == cosine.left,
+        _ => false
+    }
+}
+
+fn constant_value(node: &Node) -> Option<f64> {
+    match node.op {
+        Func::Const(value) => Some(value),
+        _ => None
+    }
+}
+
+/// Value of an operation whose operands are all constants, None if it isn't constant or the result isn't finite.
+fn fold_constant(op: &Func, a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    let result = match (op, a, b) {
+        (Func::Add | Func::Sub | Func::Mul | Func::Div | Func::Pow, Some(a), Some(b)) => apply(op, a, b)?,
+        (Func::Add | Func::Sub | Func::Mul | Func::Div | Func::Pow, _, _) => return None,
+        (_, Some(a), None) => apply(op, a, 0.0)?,
+        _ => return None
+    };
+    result.is_finite().then_some(result)
+}
+
+/// op(a) for unary functions, op(a, b) for binary operators.
+pub(crate) fn apply(op: &Func, a: f64, b: f64) -> Option<f64> {
+    Some(match op {
+        Func::Add => a + b,
+        Func::Sub => a - b,
+        Func::Mul => a * b,
+        Func::Div => a / b,
+        Func::Pow => a.powf(b),
+        Func::Sin => a.sin(),
+        Func::Cos => a.cos(),
+        Func::Tg => a.tan(),
+        Func::Ctg => 1.0 / a.tan(),
+        Func::Asin => a.asin(),
+        Func::Acos => a.acos(),
+        Func::Atg => a.atan(),
+        Func::Actg => FRAC_PI_2 - a.atan(),
+        Func::Ln => a.ln(),
+        Func::Exp => a.exp(),
+        Func::Sqrt => a.sqrt(),
+        Func::External(name) => global_resolver().unary_function(name)?(a),
+        _ => return None
+    })
+}
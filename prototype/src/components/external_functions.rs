@@ -1,37 +0,0 @@
-//TODO Optimize this to just have f64::[function name] so it doesnt have to have so many jumps
-
-pub fn sin(x: f64) -> f64{
-    f64::sin(x)
-}
-
-pub fn cos(x: f64) -> f64{
-    f64::cos(x)
-}
-
-pub fn exp(x: f64) -> f64{
-    f64::exp(x)
-}
-
-pub fn tan(x: f64) -> f64{
-    f64::tan(x)
-}
-
-pub fn ln(x: f64) -> f64{
-    f64::ln(x)
-}
-
-pub fn asin(x: f64) -> f64{
-    f64::asin(x)
-}
-
-pub fn acos(x: f64) -> f64{
-    f64::acos(x)
-}
-
-pub fn atan(x: f64) -> f64{
-    f64::atan(x)
-}
-
-pub fn sqrt(x: f64) -> f64{
-    f64::sqrt(x)
-}
@@ -0,0 +1,587 @@
+#![allow(unused_imports)]
+use crate::{
+    unrecoverable_error,
+    components::{
+        terminal_decoration::Color,
+        compilation_options::FloatMode
+    }
+};
+use std::{
+    ffi::{CStr, CString},
+    process::exit,
+    ptr,
+    rc::Rc
+};
+use llvm_sys::{
+    analysis::{LLVMVerifyModule, LLVMVerifierFailureAction},
+    core::*,
+    prelude::*,
+    ir_reader::LLVMParseIRInContext,
+    LLVMIntPredicate,
+    LLVMRealPredicate,
+    LLVMAttributeFunctionIndex
+};
+
+/// Owned LLVM context, shared by every module created in it and disposed together with the last of them.
+pub struct IrContext(LLVMContextRef);
+
+impl IrContext {
+    pub fn new() -> Rc<Self> {
+        unsafe { Rc::new(IrContext(LLVMContextCreate())) }
+    }
+
+    pub fn as_raw(&self) -> LLVMContextRef {
+        self.0
+    }
+}
+
+impl Drop for IrContext {
+    fn drop(&mut self) {
+        unsafe { LLVMContextDispose(self.0); }
+    }
+}
+
+/// A single LLVM module, keeps the context it was created in alive.
+pub struct IrModule {
+    context: Rc<IrContext>,
+    module: LLVMModuleRef,
+}
+
+impl IrModule {
+    /// Creates the module in a context of its own.
+    pub fn new(name: &str) -> Self {
+        Self::new_in(&IrContext::new(), name)
+    }
+
+    pub fn new_in(context: &Rc<IrContext>, name: &str) -> Self {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            let module = LLVMModuleCreateWithNameInContext(name.as_ptr(), context.as_raw());
+            IrModule { context: Rc::clone(context), module }
+        }
+    }
+
+    /// Parses textual IR, used for hand written IR. Generated code is built directly with `FunctionBuilder`.
+    pub fn parse(llvm_ir: &str) -> Self {
+        Self::parse_in(&IrContext::new(), llvm_ir)
+    }
+
+    pub fn parse_in(context: &Rc<IrContext>, llvm_ir: &str) -> Self {
+        let ir_c_string = CString::new(llvm_ir).unwrap();
+        let buffer_name = CString::new("LLVM IR").unwrap();
+        unsafe {
+            let buffer = LLVMCreateMemoryBufferWithMemoryRangeCopy(ir_c_string.as_ptr(), llvm_ir.len(), buffer_name.as_ptr());
+
+            let mut module: LLVMModuleRef = ptr::null_mut();
+            let mut error: *mut i8 = ptr::null_mut();
+            if LLVMParseIRInContext(context.as_raw(), buffer, &mut module, &mut error) != 0 {
+                let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
+                LLVMDisposeMessage(error);
+                unrecoverable_error!("LLVM Error | Error occured while lexing and parsing the IR string", error_message);
+            }
+
+            IrModule { context: Rc::clone(context), module }
+        }
+    }
+
+    pub fn as_raw(&self) -> LLVMModuleRef {
+        self.module
+    }
+
+    pub fn context(&self) -> LLVMContextRef {
+        self.context.as_raw()
+    }
+
+    /// Runs the LLVM verifier, returns its report if the module is malformed.
+    pub fn verify(&self) -> Result<(), String> {
+        unsafe {
+            let mut error: *mut i8 = ptr::null_mut();
+            let failed = LLVMVerifyModule(self.module, LLVMVerifierFailureAction::LLVMReturnStatusAction, &mut error) != 0;
+            let message = if error.is_null() { String::new() } else { CStr::from_ptr(error).to_string_lossy().into_owned() };
+            if !error.is_null() { LLVMDisposeMessage(error); }
+
+            if failed { Err(message) } else { Ok(()) }
+        }
+    }
+
+    /// Records the CPU and features as "target-cpu"/"target-features" attributes of every function defined in the module.
+    pub fn set_target_attributes(&self, cpu: &str, features: &str) {
+        unsafe {
+            let mut function = LLVMGetFirstFunction(self.module);
+            while !function.is_null() {
+                if LLVMIsDeclaration(function) == 0 {
+                    for (key, value) in [("target-cpu", cpu), ("target-features", features)] {
+                        if value.is_empty() { continue; }
+                        let attribute = LLVMCreateStringAttribute(
+                            self.context.as_raw(),
+                            key.as_ptr() as *const i8, key.len() as u32,
+                            value.as_ptr() as *const i8, value.len() as u32
+                        );
+                        LLVMAddAttributeAtIndex(function, LLVMAttributeFunctionIndex, attribute);
+                    }
+                }
+                function = LLVMGetNextFunction(function);
+            }
+        }
+    }
+
+    /// Adds `void batch_name(const double* xs, double* ys, size_t n)` which stores scalar_name(xs[i]) to ys[i].
+    /// The scalar function is marked always inline, so once the loop is inlined the loop vectorizer can turn it into vector code.
+    /// The string attributes of the scalar function (target CPU and features, fast-math) are copied to the batch function.
+    pub fn add_batch_function(&self, scalar_name: &str, batch_name: &str) {
+        unsafe {
+            let scalar = LLVMGetNamedFunction(self.module, c_name(scalar_name).as_ptr());
+            if scalar.is_null() {
+                unrecoverable_error!("LLVM Error | Batch function needs an existing scalar function", scalar_name);
+            }
+            let always_inline = LLVMGetEnumAttributeKindForName(c"alwaysinline".as_ptr(), 12);
+            LLVMAddAttributeAtIndex(scalar, LLVMAttributeFunctionIndex, LLVMCreateEnumAttribute(self.context(), always_inline, 0));
+
+            let f64_type = LLVMDoubleTypeInContext(self.context());
+            let i64_type = LLVMInt64TypeInContext(self.context());
+            let pointer_type = LLVMPointerType(f64_type, 0);
+            let mut parameters = [pointer_type, pointer_type, i64_type];
+            let batch_type = LLVMFunctionType(LLVMVoidTypeInContext(self.context()), parameters.as_mut_ptr(), 3, 0);
+            let batch = LLVMAddFunction(self.module, c_name(batch_name).as_ptr(), batch_type);
+            for attribute in function_attributes(scalar) {
+                if LLVMIsStringAttribute(attribute) != 0 {
+                    LLVMAddAttributeAtIndex(batch, LLVMAttributeFunctionIndex, attribute);
+                }
+            }
+            let (xs, ys, n) = (LLVMGetParam(batch, 0), LLVMGetParam(batch, 1), LLVMGetParam(batch, 2));
+            LLVMSetValueName2(xs, c"xs".as_ptr(), 2);
+            LLVMSetValueName2(ys, c"ys".as_ptr(), 2);
+            LLVMSetValueName2(n, c"n".as_ptr(), 1);
+
+            // xs and ys never overlap (Rust slices, `restrict` in exported C headers), so the vectorizer doesn't need runtime alias checks
+            let no_alias = LLVMGetEnumAttributeKindForName(c"noalias".as_ptr(), 7);
+            for index in [1, 2] {
+                LLVMAddAttributeAtIndex(batch, index, LLVMCreateEnumAttribute(self.context(), no_alias, 0));
+            }
+
+            let builder = LLVMCreateBuilderInContext(self.context());
+            let entry = LLVMAppendBasicBlockInContext(self.context(), batch, c"entry".as_ptr());
+            let body = LLVMAppendBasicBlockInContext(self.context(), batch, c"loop".as_ptr());
+            let exit = LLVMAppendBasicBlockInContext(self.context(), batch, c"exit".as_ptr());
+            let zero = LLVMConstInt(i64_type, 0, 0);
+
+            LLVMPositionBuilderAtEnd(builder, entry);
+            let empty = LLVMBuildICmp(builder, LLVMIntPredicate::LLVMIntEQ, n, zero, c"empty".as_ptr());
+            LLVMBuildCondBr(builder, empty, exit, body);
+
+            LLVMPositionBuilderAtEnd(builder, body);
+            let index = LLVMBuildPhi(builder, i64_type, c"i".as_ptr());
+            let mut indices = [index];
+            let x_pointer = LLVMBuildGEP2(builder, f64_type, xs, indices.as_mut_ptr(), 1, c"xp".as_ptr());
+            let x = LLVMBuildLoad2(builder, f64_type, x_pointer, c"x".as_ptr());
+            let mut arguments = [x];
+            let mut scalar_parameters = [f64_type];
+            let scalar_type = LLVMFunctionType(f64_type, scalar_parameters.as_mut_ptr(), 1, 0);
+            let y = LLVMBuildCall2(builder, scalar_type, scalar, arguments.as_mut_ptr(), 1, c"y".as_ptr());
+            let y_pointer = LLVMBuildGEP2(builder, f64_type, ys, indices.as_mut_ptr(), 1, c"yp".as_ptr());
+            LLVMBuildStore(builder, y, y_pointer);
+            let next = LLVMBuildNUWAdd(builder, index, LLVMConstInt(i64_type, 1, 0), c"next".as_ptr());
+            let done = LLVMBuildICmp(builder, LLVMIntPredicate::LLVMIntEQ, next, n, c"done".as_ptr());
+            LLVMBuildCondBr(builder, done, exit, body);
+
+            let mut incoming_values = [zero, next];
+            let mut incoming_blocks = [entry, body];
+            LLVMAddIncoming(index, incoming_values.as_mut_ptr(), incoming_blocks.as_mut_ptr(), 2);
+
+            LLVMPositionBuilderAtEnd(builder, exit);
+            LLVMBuildRetVoid(builder);
+            LLVMDisposeBuilder(builder);
+        }
+    }
+
+    /// Number of instructions in all function bodies of the module.
+    pub fn instruction_count(&self) -> usize {
+        let mut count: usize = 0;
+        unsafe {
+            let mut function = LLVMGetFirstFunction(self.module);
+            while !function.is_null() {
+                let mut block = LLVMGetFirstBasicBlock(function);
+                while !block.is_null() {
+                    let mut instruction = LLVMGetFirstInstruction(block);
+                    while !instruction.is_null() {
+                        count += 1;
+                        instruction = LLVMGetNextInstruction(instruction);
+                    }
+                    block = LLVMGetNextBasicBlock(block);
+                }
+                function = LLVMGetNextFunction(function);
+            }
+        }
+        count
+    }
+
+    /// Value of the string attribute `key` of function `name`, None if either is missing.
+    pub fn function_attribute(&self, name: &str, key: &str) -> Option<String> {
+        unsafe {
+            let function = LLVMGetNamedFunction(self.module, c_name(name).as_ptr());
+            if function.is_null() {
+                return None;
+            }
+            let attribute = LLVMGetStringAttributeAtIndex(function, LLVMAttributeFunctionIndex, key.as_ptr() as *const i8, key.len() as u32);
+            if attribute.is_null() {
+                return None;
+            }
+            let mut len = 0;
+            let value = LLVMGetStringAttributeValue(attribute, &mut len);
+            Some(String::from_utf8_lossy(std::slice::from_raw_parts(value as *const u8, len as usize)).into_owned())
+        }
+    }
+
+    pub fn has_function(&self, name: &str) -> bool {
+        unsafe { !LLVMGetNamedFunction(self.module, c_name(name).as_ptr()).is_null() }
+    }
+
+    /// Names of the functions the module calls without defining them, intrinsics excluded.
+    pub fn declared_functions(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        unsafe {
+            let mut function = LLVMGetFirstFunction(self.module);
+            while !function.is_null() {
+                if LLVMIsDeclaration(function) != 0 && LLVMGetIntrinsicID(function) == 0 {
+                    let mut len = 0;
+                    let name = LLVMGetValueName2(function, &mut len);
+                    names.push(String::from_utf8_lossy(std::slice::from_raw_parts(name as *const u8, len)).into_owned());
+                }
+                function = LLVMGetNextFunction(function);
+            }
+        }
+        names
+    }
+
+    /// Renames function `from` to `to`, returns false if the module has no function `from`.
+    /// If `to` already exists, the calls of `from` are redirected to it and `from` is deleted.
+    pub fn rename_function(&self, from: &str, to: &str) -> bool {
+        unsafe {
+            let function = LLVMGetNamedFunction(self.module, c_name(from).as_ptr());
+            if function.is_null() {
+                return false;
+            }
+            let existing = LLVMGetNamedFunction(self.module, c_name(to).as_ptr());
+            if existing.is_null() {
+                LLVMSetValueName2(function, to.as_ptr() as *const i8, to.len());
+            } else if existing != function {
+                if LLVMIsDeclaration(function) == 0 {
+                    unrecoverable_error!("LLVM Error | Function can't be renamed", format!("'{}' is defined and '{}' already exists", from, to));
+                }
+                LLVMReplaceAllUsesWith(function, existing);
+                LLVMDeleteFunction(function);
+            }
+            true
+        }
+    }
+
+    /// Copy of the module in the same context, with fast-math `flags` (eg. "contract" or "fast") on every floating point
+    /// instruction and every call, phi and select of doubles. The C API of LLVM 16 can't set the flags of an instruction,
+    /// so the module is printed, the flags are added to the text and the result is parsed again.
+    pub fn with_fast_math_flags(&self, flags: &str) -> IrModule {
+        let llvm_ir: Vec<String> = self.dump().lines().map(|line| add_fast_math_flags(line, flags)).collect();
+        IrModule::parse_in(&self.context, &llvm_ir.join("\n"))
+    }
+
+    /// Textual IR of the module, only meant for debugging.
+    pub fn dump(&self) -> String {
+        unsafe {
+            let c_string = LLVMPrintModuleToString(self.module);
+            let temp = CStr::from_ptr(c_string).to_string_lossy().into_owned();
+            LLVMDisposeMessage(c_string);
+            temp
+        }
+    }
+}
+
+impl Drop for IrModule {
+    fn drop(&mut self) {
+        unsafe {
+            LLVMDisposeModule(self.module);
+        }
+    }
+}
+
+/// Builds a single `double name(double)` function, straight line code apart from the blocks of `branch`.
+/// Every method appends one instruction and returns its value, `name` is only used to label the value in the dump.
+pub struct FunctionBuilder<'m> {
+    module: &'m IrModule,
+    builder: LLVMBuilderRef,
+    function: LLVMValueRef,
+    f64_type: LLVMTypeRef,
+    i64_type: LLVMTypeRef,
+    float_mode: FloatMode,
+}
+
+/// Function attributes of `FloatMode::Fast`, read by the backend. The optimization passes only look at the flags
+/// of the instructions, which `IrModule::with_fast_math_flags` adds once the functions are built.
+const FAST_MATH_ATTRIBUTES: [&str; 6] = [
+    "unsafe-fp-math", "no-nans-fp-math", "no-infs-fp-math", "no-signed-zeros-fp-math", "approx-func-fp-math", "no-trapping-math"
+];
+
+/// Attributes of the function itself, without those of the return value and parameters.
+unsafe fn function_attributes(function: LLVMValueRef) -> Vec<LLVMAttributeRef> {
+    let count = LLVMGetAttributeCountAtIndex(function, LLVMAttributeFunctionIndex) as usize;
+    let mut attributes = vec![ptr::null_mut(); count];
+    LLVMGetAttributesAtIndex(function, LLVMAttributeFunctionIndex, attributes.as_mut_ptr());
+    attributes
+}
+
+/// `%v = fadd double %a, %b` -> `%v = fadd fast double %a, %b`, lines of other instructions are returned unchanged.
+fn add_fast_math_flags(line: &str, flags: &str) -> String {
+    let Some(position) = line.find(" = ") else { return line.to_string() };
+    let (result, instruction) = line.split_at(position + 3);
+    let (prefix, instruction) = match instruction.strip_prefix("tail ") {
+        Some(call) => ("tail ", call),
+        None => ("", instruction)
+    };
+    let Some((opcode, operands)) = instruction.split_once(' ') else { return line.to_string() };
+    let floating = match opcode {
+        "fadd" | "fsub" | "fmul" | "fdiv" | "frem" | "fneg" | "fcmp" => true,
+        "call" | "phi" => operands.starts_with("double "),
+        "select" => operands.split(", ").nth(1).is_some_and(|operand| operand.starts_with("double ")),
+        _ => false
+    };
+    if floating {
+        format!("{}{}{} {} {}", result, prefix, opcode, flags, operands)
+    }else{
+        line.to_string()
+    }
+}
+
+#[inline(always)]
+fn c_name(name: &str) -> CString {
+    CString::new(name).unwrap()
+}
+
+impl<'m> FunctionBuilder<'m> {
+    pub(crate) fn new(module: &'m IrModule, function_name: &str, float_mode: FloatMode) -> Self {
+        Self::create(module, function_name, float_mode, false)
+    }
+
+    /// Builds `void name(double x, double* out)` instead, results are written with `store_output` and returned with `ret_void`.
+    pub(crate) fn with_output(module: &'m IrModule, function_name: &str, float_mode: FloatMode) -> Self {
+        Self::create(module, function_name, float_mode, true)
+    }
+
+    fn create(module: &'m IrModule, function_name: &str, float_mode: FloatMode, output: bool) -> Self {
+        unsafe {
+            let f64_type = LLVMDoubleTypeInContext(module.context());
+            let i64_type = LLVMInt64TypeInContext(module.context());
+            let function_type = if output {
+                let mut parameters = [f64_type, LLVMPointerType(f64_type, 0)];
+                LLVMFunctionType(LLVMVoidTypeInContext(module.context()), parameters.as_mut_ptr(), 2, 0)
+            }else{
+                let mut parameters = [f64_type];
+                LLVMFunctionType(f64_type, parameters.as_mut_ptr(), 1, 0)
+            };
+            let function = LLVMAddFunction(module.module, c_name(function_name).as_ptr(), function_type);
+            if output {
+                LLVMSetValueName2(LLVMGetParam(function, 1), c"out".as_ptr(), 3);
+            }
+            let argument = LLVMGetParam(function, 0);
+            LLVMSetValueName2(argument, c"x".as_ptr(), 1);
+
+            if float_mode == FloatMode::Fast {
+                for attribute_name in FAST_MATH_ATTRIBUTES {
+                    let attribute = LLVMCreateStringAttribute(
+                        module.context(),
+                        attribute_name.as_ptr() as *const i8, attribute_name.len() as u32,
+                        c"true".as_ptr(), 4
+                    );
+                    LLVMAddAttributeAtIndex(function, LLVMAttributeFunctionIndex, attribute);
+                }
+            }
+
+            let builder = LLVMCreateBuilderInContext(module.context());
+            let entry = LLVMAppendBasicBlockInContext(module.context(), function, c"entry".as_ptr());
+            LLVMPositionBuilderAtEnd(builder, entry);
+
+            FunctionBuilder { module, builder, function, f64_type, i64_type, float_mode }
+        }
+    }
+
+    pub(crate) fn argument(&self) -> LLVMValueRef {
+        unsafe { LLVMGetParam(self.function, 0) }
+    }
+
+    pub(crate) fn constant(&self, value: f64) -> LLVMValueRef {
+        unsafe { LLVMConstReal(self.f64_type, value) }
+    }
+
+    pub(crate) fn constant_i64(&self, value: i64) -> LLVMValueRef {
+        unsafe { LLVMConstInt(self.i64_type, value as u64, 1) }
+    }
+
+    pub(crate) fn fadd(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildFAdd(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn fsub(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildFSub(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn fmul(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildFMul(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn fdiv(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildFDiv(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn fneg(&self, value: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildFNeg(self.builder, value, c_name(name).as_ptr()) }
+    }
+
+    /// a*b + c. With `fma` it is always a single llvm.fma.f64, otherwise a separate fmul/fadd pair in strict mode
+    /// or llvm.fmuladd.f64 which the backend may contract into an FMA.
+    pub(crate) fn multiply_add(&self, a: LLVMValueRef, b: LLVMValueRef, c: LLVMValueRef, fma: bool, name: &str) -> LLVMValueRef {
+        if fma {
+            self.call("llvm.fma.f64", &[a, b, c], name)
+        }else if self.float_mode != FloatMode::Strict {
+            self.call("llvm.fmuladd.f64", &[a, b, c], name)
+        }else{
+            let product = self.fmul(a, b, &format!("{}_m", name));
+            self.fadd(product, c, name)
+        }
+    }
+
+    /// Calls `double callee(double, ...)`, the callee (intrinsic or external function) is declared on first use.
+    pub(crate) fn call(&self, callee: &str, arguments: &[LLVMValueRef], name: &str) -> LLVMValueRef {
+        unsafe {
+            let mut parameters = vec![self.f64_type; arguments.len()];
+            let function_type = LLVMFunctionType(self.f64_type, parameters.as_mut_ptr(), parameters.len() as u32, 0);
+            let callee_name = c_name(callee);
+            let mut function = LLVMGetNamedFunction(self.module.module, callee_name.as_ptr());
+            if function.is_null() {
+                function = LLVMAddFunction(self.module.module, callee_name.as_ptr(), function_type);
+            }
+
+            let mut arguments = arguments.to_vec();
+            LLVMBuildCall2(self.builder, function_type, function, arguments.as_mut_ptr(), arguments.len() as u32, c_name(name).as_ptr())
+        }
+    }
+
+    pub(crate) fn bitcast_to_i64(&self, value: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildBitCast(self.builder, value, self.i64_type, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn bitcast_to_f64(&self, value: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildBitCast(self.builder, value, self.f64_type, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn add(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildAdd(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn sub(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildSub(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn and(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildAnd(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn or(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildOr(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn shl(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildShl(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn ashr(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildAShr(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn lshr(&self, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildLShr(self.builder, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn fptosi(&self, value: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildFPToSI(self.builder, value, self.i64_type, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn sitofp(&self, value: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildSIToFP(self.builder, value, self.f64_type, c_name(name).as_ptr()) }
+    }
+
+    /// Zero extends an i1 into i64.
+    pub(crate) fn zext(&self, value: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildZExt(self.builder, value, self.i64_type, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn icmp(&self, predicate: LLVMIntPredicate, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildICmp(self.builder, predicate, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn fcmp(&self, predicate: LLVMRealPredicate, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildFCmp(self.builder, predicate, lhs, rhs, c_name(name).as_ptr()) }
+    }
+
+    pub(crate) fn select(&self, condition: LLVMValueRef, then_value: LLVMValueRef, else_value: LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe { LLVMBuildSelect(self.builder, condition, then_value, else_value, c_name(name).as_ptr()) }
+    }
+
+    /// `condition ? then_value() : else_value()`, only the side that is taken runs, eg. a libm call on a rare slow path.
+    /// Code built afterwards continues in the block joining the two sides.
+    pub(crate) fn branch(&self, condition: LLVMValueRef, then_value: impl FnOnce() -> LLVMValueRef, else_value: impl FnOnce() -> LLVMValueRef, name: &str) -> LLVMValueRef {
+        unsafe {
+            let context = self.module.context();
+            let then_block = LLVMAppendBasicBlockInContext(context, self.function, c_name(&format!("{}_then", name)).as_ptr());
+            let else_block = LLVMAppendBasicBlockInContext(context, self.function, c_name(&format!("{}_else", name)).as_ptr());
+            let join_block = LLVMAppendBasicBlockInContext(context, self.function, c_name(&format!("{}_join", name)).as_ptr());
+            LLVMBuildCondBr(self.builder, condition, then_block, else_block);
+
+            LLVMPositionBuilderAtEnd(self.builder, then_block);
+            let mut values = [then_value(), ptr::null_mut()];
+            let mut blocks = [LLVMGetInsertBlock(self.builder), else_block];
+            LLVMBuildBr(self.builder, join_block);
+
+            LLVMPositionBuilderAtEnd(self.builder, else_block);
+            values[1] = else_value();
+            blocks[1] = LLVMGetInsertBlock(self.builder);
+            LLVMBuildBr(self.builder, join_block);
+
+            LLVMPositionBuilderAtEnd(self.builder, join_block);
+            let phi = LLVMBuildPhi(self.builder, self.f64_type, c_name(name).as_ptr());
+            LLVMAddIncoming(phi, values.as_mut_ptr(), blocks.as_mut_ptr(), 2);
+            phi
+        }
+    }
+
+    pub(crate) fn ret(&self, value: LLVMValueRef) {
+        unsafe { LLVMBuildRet(self.builder, value); }
+    }
+
+    /// out[index] = value, only for functions created by `with_output`.
+    pub(crate) fn store_output(&self, value: LLVMValueRef, index: usize) {
+        unsafe {
+            let mut indices = [LLVMConstInt(self.i64_type, index as u64, 0)];
+            let name = c_name(&format!("out{}", index));
+            let pointer = LLVMBuildGEP2(self.builder, self.f64_type, LLVMGetParam(self.function, 1), indices.as_mut_ptr(), 1, name.as_ptr());
+            LLVMBuildStore(self.builder, value, pointer);
+        }
+    }
+
+    pub(crate) fn ret_void(&self) {
+        unsafe { LLVMBuildRetVoid(self.builder); }
+    }
+
+    /// Value of a floating point constant, None for values only known at runtime.
+    pub(crate) fn known_constant(&self, value: LLVMValueRef) -> Option<f64> {
+        unsafe {
+            if LLVMIsAConstantFP(value).is_null() {
+                return None;
+            }
+            let mut loses_info: LLVMBool = 0;
+            Some(LLVMConstRealGetDouble(value, &mut loses_info))
+        }
+    }
+}
+
+impl Drop for FunctionBuilder<'_> {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeBuilder(self.builder); }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::components::{
+    ir_builder::FunctionBuilder,
+    coefficient_tables::factorial
+};
+use llvm_sys::prelude::LLVMValueRef;
+
+/// Truncated Taylor series of an intermediate value around the current x, terms[n] = f^(n)(x)/n!.
+/// Terms known to be zero at compile time are None, so constants and polynomials of x don't generate useless arithmetic.
+#[derive(Clone)]
+pub(crate) struct Jet {
+    pub(crate) terms: Vec<Option<LLVMValueRef>>,
+}
+
+impl Jet {
+    pub(crate) fn constant(value: LLVMValueRef, order: usize) -> Self {
+        let mut terms = vec![None; order+1];
+        terms[0] = Some(value);
+        Jet { terms }
+    }
+
+    /// Jet of x itself, x + 1*t.
+    pub(crate) fn variable(builder: &FunctionBuilder, order: usize) -> Self {
+        let mut terms = vec![None; order+1];
+        terms[0] = Some(builder.argument());
+        if order > 0 {
+            terms[1] = Some(builder.constant(1.0));
+        }
+        Jet { terms }
+    }
+
+    pub(crate) fn order(&self) -> usize {
+        self.terms.len()-1
+    }
+
+    pub(crate) fn value(&self, builder: &FunctionBuilder) -> LLVMValueRef {
+        self.terms[0].unwrap_or_else(|| builder.constant(0.0))
+    }
+
+    /// True if the value doesn't depend on x.
+    pub(crate) fn is_constant(&self) -> bool {
+        self.terms[1..].iter().all(Option::is_none)
+    }
+
+    pub(crate) fn add(&self, builder: &FunctionBuilder, rhs: &Jet, name: &str) -> Jet {
+        let terms = self.terms.iter().zip(rhs.terms.iter()).enumerate().map(|(n, (lhs, rhs))| {
+            match (lhs, rhs) {
+                (Some(lhs), Some(rhs)) => Some(builder.fadd(*lhs, *rhs, &format!("{}_{}", name, n))),
+                (Some(value), None) | (None, Some(value)) => Some(*value),
+                (None, None) => None,
+            }
+        }).collect();
+        Jet { terms }
+    }
+
+    pub(crate) fn sub(&self, builder: &FunctionBuilder, rhs: &Jet, name: &str) -> Jet {
+        let terms = self.terms.iter().zip(rhs.terms.iter()).enumerate().map(|(n, (lhs, rhs))| {
+            match (lhs, rhs) {
+                (Some(lhs), Some(rhs)) => Some(builder.fsub(*lhs, *rhs, &format!("{}_{}", name, n))),
+                (Some(lhs), None) => Some(*lhs),
+                (None, Some(rhs)) => Some(builder.fneg(*rhs, &format!("{}_{}", name, n))),
+                (None, None) => None,
+            }
+        }).collect();
+        Jet { terms }
+    }
+
+    /// Cauchy product, h_n = sum(i=0..n) f_i*g_(n-i).
+    pub(crate) fn mul(&self, builder: &FunctionBuilder, rhs: &Jet, fma: bool, name: &str) -> Jet {
+        let terms = (0..=self.order()).map(|n| {
+            let mut temp: Option<LLVMValueRef> = None;
+            for i in 0..=n {
+                if let (Some(lhs), Some(rhs)) = (self.terms[i], rhs.terms[n-i]) {
+                    temp = Some(multiply_add_term(builder, lhs, rhs, temp, fma, &format!("{}_{}_{}", name, n, i)));
+                }
+            }
+            temp
+        }).collect();
+        Jet { terms }
+    }
+
+    /// q_n = (f_n - sum(i=1..n) g_i*q_(n-i))/g_0.
+    pub(crate) fn div(&self, builder: &FunctionBuilder, rhs: &Jet, name: &str) -> Jet {
+        let denominator = rhs.value(builder);
+        let mut terms: Vec<Option<LLVMValueRef>> = Vec::with_capacity(self.terms.len());
+        for n in 0..=self.order() {
+            let mut numerator = self.terms[n];
+            for i in 1..=n {
+                if let (Some(rhs_term), Some(quotient)) = (rhs.terms[i], terms[n-i]) {
+                    let product = multiply_term(builder, rhs_term, quotient, &format!("{}_{}_{}", name, n, i));
+                    numerator = Some(match numerator {
+                        Some(numerator) => builder.fsub(numerator, product, &format!("{}_{}_s{}", name, n, i)),
+                        None => builder.fneg(product, &format!("{}_{}_s{}", name, n, i)),
+                    });
+                }
+            }
+            terms.push(numerator.map(|numerator| builder.fdiv(numerator, denominator, &format!("{}_{}", name, n))));
+        }
+        Jet { terms }
+    }
+
+    /// Jet of outer(self), where outer_terms[j] = outer^(j)(u)/j! at u = value of self.
+    /// h = sum(j) outer_j*d^j, with d = self - u, so only the terms of d^j up to the order are built.
+    pub(crate) fn compose(&self, builder: &FunctionBuilder, outer_terms: &[Option<LLVMValueRef>], fma: bool, name: &str) -> Jet {
+        let mut terms = vec![None; self.terms.len()];
+        terms[0] = outer_terms[0];
+
+        let mut delta = self.clone();
+        delta.terms[0] = None;
+        let mut delta_power = delta.clone();
+        for (j, outer_term) in outer_terms.iter().enumerate().take(self.terms.len()).skip(1) {
+            if j > 1 {
+                delta_power = delta_power.mul(builder, &delta, fma, &format!("{}_d{}", name, j));
+            }
+            if let Some(outer_term) = outer_term {
+                for n in j..self.terms.len() {
+                    if let Some(power_term) = delta_power.terms[n] {
+                        terms[n] = Some(multiply_add_term(builder, *outer_term, power_term, terms[n], fma, &format!("{}_{}_{}", name, n, j)));
+                    }
+                }
+            }
+        }
+        Jet { terms }
+    }
+
+    /// Writes f, f', f'', ... to out[0..=order].
+    pub(crate) fn store_derivatives(&self, builder: &FunctionBuilder) {
+        for (n, term) in self.terms.iter().enumerate() {
+            let derivative = match term {
+                Some(term) if n > 1 => builder.fmul(*term, builder.constant(factorial(n)), &format!("der{}", n)),
+                Some(term) => *term,
+                None => builder.constant(0.0),
+            };
+            builder.store_output(derivative, n);
+        }
+    }
+}
+
+/// lhs*rhs, multiplications by 1 (eg. the derivative of x) are skipped.
+fn multiply_term(builder: &FunctionBuilder, lhs: LLVMValueRef, rhs: LLVMValueRef, name: &str) -> LLVMValueRef {
+    match (builder.known_constant(lhs), builder.known_constant(rhs)) {
+        (Some(1.0), _) => rhs,
+        (_, Some(1.0)) => lhs,
+        _ => builder.fmul(lhs, rhs, name),
+    }
+}
+
+/// lhs*rhs + accumulator, a plain product for the first term of a sum.
+fn multiply_add_term(builder: &FunctionBuilder, lhs: LLVMValueRef, rhs: LLVMValueRef, accumulator: Option<LLVMValueRef>, fma: bool, name: &str) -> LLVMValueRef {
+    let one_operand = builder.known_constant(lhs) == Some(1.0) || builder.known_constant(rhs) == Some(1.0);
+    match accumulator {
+        Some(accumulator) if one_operand => builder.fadd(multiply_term(builder, lhs, rhs, name), accumulator, &format!("{}_a", name)),
+        Some(accumulator) => builder.multiply_add(lhs, rhs, accumulator, fma, name),
+        None => multiply_term(builder, lhs, rhs, name),
+    }
+}
@@ -2,8 +2,22 @@ pub mod object_type_definitions;
 pub mod auxilary_functions;
 pub mod terminal_decoration;
 pub mod error_types;
-pub mod external_functions;
+pub mod coefficient_tables;
+pub mod ir_builder;
 pub mod polynomials;
+pub mod polynomial_evaluation;
+pub mod jets;
 pub mod taylor_generation;
 pub mod polynomial_operators;
-pub mod taylor_optimizer;
\ No newline at end of file
+pub mod taylor_optimizer;
+pub mod pade_approximants;
+pub mod range_reduction;
+pub mod compilation_options;
+pub mod symbolic_differentiation;
+pub mod expression_simplifier;
+pub mod executable_memory;
+pub mod elf_object;
+pub mod symbol_resolver;
+pub mod orc_jit;
+pub mod shared_object;
+pub mod compilation_cache;
@@ -5,12 +5,19 @@ use std::{
     process::exit
 };
 
-use super::polynomials::TsPoly;
+use super::{
+    symbolic_differentiation::postfix_to_tree,
+    polynomials::TsPoly,
+    pade_approximants::RationalPoly,
+    range_reduction::ReducedFunc
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Func {
     //Values used in subsequent optimization passes
     Poly(TsPoly),
+    Rational(RationalPoly),
+    Reduced(ReducedFunc),
 
     //Trigonometry functions
     Sin,    // sin(f(x))
@@ -49,6 +56,9 @@ pub enum Func {
     Cb, //closed
 
     //auxilary
+    Diff,   // d/dx f(x), expanded before compilation
+    Exact(Vec<Func>),   // exact(f(x)), postfix of a subterm which is compiled with calls instead of polynomials, empty while it's still an operator
+    External(String),   // name(f(x)), call to a function of the symbol resolver
     X,      //function variable
     Const(f64),  // C, C e R
     None,   // end of the tree
@@ -72,6 +82,7 @@ impl Func {
             Func::Atg | Func::Actg => String::from("atan"),
             Func::Asin => String::from("asin"),
             Func::Acos => String::from("acos"),
+            Func::External(name) => name.clone(),
             Func::Sinh => todo!("sinh"),
             Func::Cosh => todo!("cosh"),
             Func::Tgh => todo!("tgh"),
@@ -80,7 +91,7 @@ impl Func {
             Func::Arcosh => todo!("arcosh"),
             Func::Artgh => todo!("artgh"),
             Func::Arctgh => todo!("arctgh"),
-            Func::Ob | Func::Cb | Func::None | Func::Const(_) | Func::X => {
+            Func::Ob | Func::Cb | Func::None | Func::Const(_) | Func::X | Func::Diff | Func::Exact(_) | Func::Rational(_) | Func::Reduced(_) => {
                 unrecoverable_error!(
                     "Error generating the IR code string",
                     format!("'Func::{:?}' was encountered, which shouldn't be there.", self)
@@ -100,6 +111,9 @@ impl fmt::Display for Func {
             Func::Div => String::from("/"),
             Func::Pow => String::from("^"),
             Func::X => String::from("x"),
+            Func::Diff => String::from("diff"),
+            Func::Exact(subterm) if subterm.is_empty() => String::from("exact"),
+            Func::Exact(subterm) => format!("exact({})", postfix_to_tree(subterm)),
             Func::None => String::from("None"),
             Func::Sin => String::from("sin"),
             Func::Cos => String::from("cos"),
@@ -112,6 +126,7 @@ impl fmt::Display for Func {
             Func::Atg => String::from("arctg"),
             Func::Asin => String::from("arcsin"),
             Func::Acos => String::from("arccos"),
+            Func::External(name) => name.clone(),
             Func::Actg => String::from("arcctg"),
             Func::Ob => String::from("("),
             Func::Cb => String::from(")"),
@@ -124,13 +139,15 @@ impl fmt::Display for Func {
             Func::Artgh => String::from("artgh"),
             Func::Arctgh => String::from("arctgh"),
             Func::Poly(ts_poly) => ts_poly.to_string(),
+            Func::Rational(rational_poly) => rational_poly.to_string(),
+            Func::Reduced(reduced_func) => reduced_func.to_string(),
         };
 
         write!(f, "{}", temp)
     }
 }
 
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
 pub struct Node {
     pub left: Option<Box<Node>>,
     pub right: Option<Box<Node>>,
@@ -161,6 +178,22 @@ impl Node {
             op: operation
         }
     }
+
+    pub fn unary(operation: Func, argument: Node) -> Node {
+        Node {
+            left: Some(Box::new(argument)),
+            right: None,
+            op: operation
+        }
+    }
+
+    pub fn binary(operation: Func, left: Node, right: Node) -> Node {
+        Node {
+            left: Some(Box::new(left)),
+            right: Some(Box::new(right)),
+            op: operation
+        }
+    }
 }
 
 impl Default for Node {
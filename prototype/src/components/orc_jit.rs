@@ -0,0 +1,105 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::{
+    terminal_decoration::Color,
+    elf_object::{ElfObject, SHN_UNDEF},
+    symbol_resolver::global_resolver
+};
+use std::{
+    ffi::{CString, CStr},
+    process::exit,
+    ptr
+};
+use llvm_sys::{
+    core::LLVMCreateMemoryBufferWithMemoryRangeCopy,
+    error::{LLVMErrorRef, LLVMGetErrorMessage, LLVMDisposeErrorMessage},
+    orc2::{
+        LLVMOrcAbsoluteSymbols, LLVMOrcJITDylibDefine, LLVMOrcDisposeMaterializationUnit,
+        LLVMOrcCSymbolMapPair, LLVMJITEvaluatedSymbol, LLVMJITSymbolFlags, LLVMJITSymbolGenericFlags,
+        lljit::*
+    }
+};
+
+fn check(error: LLVMErrorRef, what: &str) {
+    if !error.is_null() {
+        let error_message = unsafe {
+            let message = LLVMGetErrorMessage(error);
+            let error_message = CStr::from_ptr(message).to_string_lossy().into_owned();
+            LLVMDisposeErrorMessage(message);
+            error_message
+        };
+        unrecoverable_error!(format!("LLVM Error | {}", what), error_message);
+    }
+}
+
+/// LLJIT instance holding a single object file, the reference linker to compare `link_buffer` against.
+/// External symbols of the object are resolved through the symbol resolver, the same way the custom linker resolves them.
+/// The code is freed when the instance is dropped.
+pub struct OrcJit {
+    jit: LLVMOrcLLJITRef
+}
+
+impl OrcJit {
+    pub fn load_object(object_file: &[u8]) -> Self {
+        let mut jit: LLVMOrcLLJITRef = ptr::null_mut();
+        check(unsafe { LLVMOrcCreateLLJIT(&mut jit, ptr::null_mut()) }, "Failed to create the ORC LLJIT");
+        let orc_jit = OrcJit { jit };
+
+        let object = ElfObject::parse(object_file);
+        let mut externals: Vec<&str> = object.symbols.iter()
+            .filter(|symbol| symbol.section == SHN_UNDEF && !symbol.name.is_empty())
+            .map(|symbol| symbol.name)
+            .collect();
+        externals.sort();
+        externals.dedup();
+
+        unsafe {
+            let main_library = LLVMOrcLLJITGetMainJITDylib(jit);
+            if !externals.is_empty() {
+                let resolver = global_resolver();
+                let mut symbols: Vec<LLVMOrcCSymbolMapPair> = externals.iter().map(|name| {
+                    let address = match resolver.address(name) {
+                        Some(address) => address,
+                        None => { unrecoverable_error!("Linker Error | Unrecognized symbol in the external functions table", name); }
+                    };
+                    let name = CString::new(*name).unwrap();
+                    LLVMOrcCSymbolMapPair {
+                        Name: LLVMOrcLLJITMangleAndIntern(jit, name.as_ptr()),
+                        Sym: LLVMJITEvaluatedSymbol {
+                            Address: address as u64,
+                            Flags: LLVMJITSymbolFlags {
+                                GenericFlags: LLVMJITSymbolGenericFlags::LLVMJITSymbolGenericFlagsExported as u8 | LLVMJITSymbolGenericFlags::LLVMJITSymbolGenericFlagsCallable as u8,
+                                TargetFlags: 0
+                            }
+                        }
+                    }
+                }).collect();
+
+                let unit = LLVMOrcAbsoluteSymbols(symbols.as_mut_ptr(), symbols.len());
+                let error = LLVMOrcJITDylibDefine(main_library, unit);
+                if !error.is_null() {
+                    LLVMOrcDisposeMaterializationUnit(unit);
+                }
+                check(error, "Failed to define the external functions in the JIT");
+            }
+
+            let buffer = LLVMCreateMemoryBufferWithMemoryRangeCopy(object_file.as_ptr() as *const i8, object_file.len(), c"fja".as_ptr());
+            check(LLVMOrcLLJITAddObjectFile(jit, main_library, buffer), "Failed to add the object file to the JIT");
+        }
+        orc_jit
+    }
+
+    /// Address of a symbol defined by the object, linking happens on the first lookup.
+    pub fn lookup(&self, name: &str) -> *mut u8 {
+        let mut address = 0;
+        let symbol = CString::new(name).unwrap();
+        check(unsafe { LLVMOrcLLJITLookup(self.jit, &mut address, symbol.as_ptr()) }, &format!("Failed to look up '{}' in the JIT", name));
+        address as *mut u8
+    }
+}
+
+impl Drop for OrcJit {
+    fn drop(&mut self) {
+        unsafe { LLVMOrcDisposeLLJIT(self.jit); }
+    }
+}
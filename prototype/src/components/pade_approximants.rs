@@ -0,0 +1,173 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::{
+    object_type_definitions::Func,
+    polynomials::TsPoly,
+    ir_builder::FunctionBuilder,
+    jets::Jet,
+    terminal_decoration::Color
+};
+use std::{
+    fmt,
+    process::exit
+};
+use llvm_sys::prelude::LLVMValueRef;
+
+/// [Padé approximant](https://en.wikipedia.org/wiki/Pad%C3%A9_approximant) numerator(t)/denominator(t), where t = argument - center.
+/// Both polynomials are stored in the shifted basis, so the first coefitient of the denominator is always 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RationalPoly {
+    pub(crate) numerator: TsPoly,
+    pub(crate) denominator: TsPoly,
+    pub(crate) center: f64,
+    pub(crate) from_x: bool
+}
+
+impl TsPoly {
+    /// Builds the Padé [numerator_degree/denominator_degree] approximant around `center` from the Taylor coefitients of self.
+    /// If the linear system for the denominator is singular (eg. self is already a low degree polynomial)
+    /// the denominator degree is lowered until a non-degenerate entry of the Padé table is found.
+    pub fn pade(&self, numerator_degree: usize, mut denominator_degree: usize, center: f64) -> RationalPoly {
+        if numerator_degree + denominator_degree >= Self::DEFAULT_MAX_POW {
+            unrecoverable_error!(
+                "Pade approximation error | Requested degrees exceed the highest supported power",
+                format!("[{}/{}], highest power is {}", numerator_degree, denominator_degree, Self::DEFAULT_MAX_POW-1)
+            );
+        }
+
+        let series = self.recentered(center).coefs;
+        let coef = |index: isize| -> f64 {
+            if index < 0 { 0.0 } else { series[index as usize] }
+        };
+
+        let denominator_coefs = loop {
+            if denominator_degree == 0 {
+                break vec![1.0];
+            }
+
+            // sum(j=1..n) q_j*c_(m+k-j) = -c_(m+k), for k = 1..n
+            let n = denominator_degree;
+            let mut system: Vec<Vec<f64>> = (1..=n).map(|k| {
+                let mut row: Vec<f64> = (1..=n).map(|j| coef((numerator_degree + k) as isize - j as isize)).collect();
+                row.push(-coef((numerator_degree + k) as isize));
+                row
+            }).collect();
+
+            if let Some(solution) = solve_linear_system(&mut system) {
+                let mut temp = vec![1.0];
+                temp.extend(solution);
+                break temp;
+            }
+
+            denominator_degree -= 1;
+        };
+
+        let mut numerator_coefs = vec![0.0; numerator_degree+1];
+        for (i, numerator_coef) in numerator_coefs.iter_mut().enumerate() {
+            for (j, denominator_coef) in denominator_coefs.iter().enumerate().take(i+1) {
+                *numerator_coef += denominator_coef*series[i-j];
+            }
+        }
+
+        RationalPoly {
+            numerator: TsPoly::from_vec(numerator_coefs, self.from_x),
+            denominator: TsPoly::from_vec(denominator_coefs, self.from_x),
+            center,
+            from_x: self.from_x
+        }
+    }
+}
+
+/// Gaussian elimination with partial pivoting on an augmented matrix, returns None if the system is singular.
+fn solve_linear_system(system: &mut [Vec<f64>]) -> Option<Vec<f64>> {
+    let size = system.len();
+    let scale = system.iter().flatten().fold(0.0, |acc: f64, value| acc.max(value.abs()));
+    if scale == 0.0 { return None; }
+
+    for column in 0..size {
+        let pivot_row = (column..size).max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))?;
+        if system[pivot_row][column].abs() <= scale*1e-13 {
+            return None;
+        }
+        system.swap(column, pivot_row);
+
+        let (upper, lower) = system.split_at_mut(column+1);
+        let pivot = &upper[column];
+        for row in lower.iter_mut() {
+            let factor = row[column]/pivot[column];
+            for (value, pivot_value) in row.iter_mut().zip(pivot.iter()).skip(column) {
+                *value -= factor*pivot_value;
+            }
+        }
+    }
+
+    let mut solution = vec![0.0; size];
+    for row in (0..size).rev() {
+        let mut value = system[row][size];
+        for (index, known) in solution.iter().enumerate().skip(row+1) {
+            value -= system[row][index]*known;
+        }
+        solution[row] = value/system[row][row];
+    }
+
+    Some(solution)
+}
+
+impl RationalPoly {
+    pub fn evaluate(&self, argument: f64) -> f64 {
+        let t = argument - self.center;
+        let horner = |poly: &TsPoly| (0..=poly.max_pow).rev().fold(0.0, |acc, power| acc*t + poly.coefs[power]);
+        horner(&self.numerator)/horner(&self.denominator)
+    }
+
+    /// Builds numerator and denominator in Horner form over t = argument - center, followed by a single fdiv.
+    pub(crate) fn build_ir(&self, builder: &FunctionBuilder, argument: LLVMValueRef, name: &str, fma: bool) -> LLVMValueRef {
+        let t = builder.fsub(argument, builder.constant(self.center), &format!("{}_t", name));
+        let numerator = self.numerator.build_horner_ir(builder, t, &format!("{}_n", name), fma);
+        let denominator = self.denominator.build_horner_ir(builder, t, &format!("{}_d", name), fma);
+        builder.fdiv(numerator, denominator, name)
+    }
+
+    /// Taylor coefitients of numerator/denominator around the argument, up to `order`.
+    pub(crate) fn build_jet_ir(&self, builder: &FunctionBuilder, argument: LLVMValueRef, order: usize, name: &str, fma: bool) -> Vec<Option<LLVMValueRef>> {
+        let t = builder.fsub(argument, builder.constant(self.center), &format!("{}_t", name));
+        let poly_jet = |poly: &TsPoly, prefix: &str| Jet {
+            terms: (0..=order).map(|n| {
+                let derivative = poly.normalized_derivative(n);
+                if derivative.max_pow == 0 && derivative.coefs[0] == 0.0 {
+                    None
+                }else{
+                    Some(derivative.build_horner_ir(builder, t, &format!("{}_{}{}", name, prefix, n), fma))
+                }
+            }).collect()
+        };
+
+        poly_jet(&self.numerator, "n").div(builder, &poly_jet(&self.denominator, "d"), &format!("{}_q", name)).terms
+    }
+}
+
+impl fmt::Display for RationalPoly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] / [{}] at t = x-{}", self.numerator, self.denominator, self.center)
+    }
+}
+
+/// Replaces every polynomial of an already Taylor optimized sequence with its Padé approximant around `precision_center`.
+/// Polynomials for which no proper rational approximant exists (denominator degree falls to 0) are left untouched.
+pub fn apply_pade_approximation(sequence: &mut [Func], precision_center: f64, poly_degree: usize, denominator_degree: usize) {
+    if denominator_degree > poly_degree {
+        unrecoverable_error!(
+            "Pade approximation error | Denominator degree can't be higher than the polynomial degree",
+            format!("{} > {}", denominator_degree, poly_degree)
+        );
+    }
+
+    for elem in sequence.iter_mut() {
+        if let Func::Poly(poly) = elem {
+            let rational = poly.pade(poly_degree-denominator_degree, denominator_degree, precision_center);
+            if rational.denominator.max_pow != 0 {
+                *elem = Func::Rational(rational);
+            }
+        }
+    }
+}
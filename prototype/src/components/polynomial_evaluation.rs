@@ -0,0 +1,46 @@
+use super::{
+    polynomials::TsPoly,
+    ir_builder::FunctionBuilder
+};
+use llvm_sys::prelude::LLVMValueRef;
+
+impl TsPoly {
+    /// Horner scheme, p(t) = c0 + t*(c1 + t*(c2 + ...)). One multiply-add per coefitient, but every step depends on the previous one.
+    /// Values are named name_i in the IR dump.
+    pub(crate) fn build_horner_ir(&self, builder: &FunctionBuilder, argument: LLVMValueRef, name: &str, fma: bool) -> LLVMValueRef {
+        let mut temp = builder.constant(self.coefs[self.max_pow]);
+        for power in (0..self.max_pow).rev() {
+            temp = builder.multiply_add(temp, argument, builder.constant(self.coefs[power]), fma, &format!("{}_{}", name, power));
+        }
+
+        temp
+    }
+
+    /// [Estrin scheme](https://en.wikipedia.org/wiki/Estrin%27s_scheme), coefitients are paired into c_2i + c_2i+1*t,
+    /// then pairs of pairs are joined with t^2, t^4, ... Dependency chain is log2(degree) long, so independent
+    /// multiply-adds can execute in parallel.
+    pub(crate) fn build_estrin_ir(&self, builder: &FunctionBuilder, argument: LLVMValueRef, name: &str, fma: bool) -> LLVMValueRef {
+        let mut terms: Vec<LLVMValueRef> = self.coefs[..=self.max_pow].iter().map(|coef| builder.constant(*coef)).collect();
+        let mut power = argument;
+        let mut level: usize = 0;
+
+        while terms.len() > 1 {
+            let mut next_terms = Vec::<LLVMValueRef>::with_capacity(terms.len().div_ceil(2));
+            for (index, pair) in terms.chunks(2).enumerate() {
+                if let [low, high] = pair {
+                    next_terms.push(builder.multiply_add(*high, power, *low, fma, &format!("{}_{}_{}", name, level, index)));
+                }else{
+                    next_terms.push(pair[0]);
+                }
+            }
+
+            terms = next_terms;
+            if terms.len() > 1 {
+                power = builder.fmul(power, power, &format!("{}_pow{}", name, level+1));
+            }
+            level += 1;
+        }
+
+        terms[0]
+    }
+}
@@ -15,7 +15,7 @@ impl Add for TsPoly{
     fn add(self, rhs: Self) -> Self::Output {
         let temp_pow = if self.max_pow >= rhs.max_pow { self.max_pow } else { rhs.max_pow };
 
-        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: temp_pow, from_x: true};
+        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: temp_pow, from_x: true, center: self.common_center(&rhs)};
         for i in 0..=temp_pow {
             temp.coefs[i] = self.coefs[i] + rhs.coefs[i];
         }
@@ -27,6 +27,7 @@ impl Add for TsPoly{
 impl AddAssign for TsPoly{
     fn add_assign(&mut self, rhs: Self) {
         let temp_pow = if self.max_pow >= rhs.max_pow { self.max_pow } else { rhs.max_pow };
+        self.center = self.common_center(&rhs);
         for i in 0..=temp_pow {
             self.coefs[i]+=rhs.coefs[i];
         }
@@ -40,7 +41,7 @@ impl Sub for TsPoly{
     fn sub(self, rhs: Self) -> Self::Output {
         let temp_pow = if self.max_pow >= rhs.max_pow { self.max_pow } else { rhs.max_pow };
 
-        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: temp_pow, from_x: true};
+        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: temp_pow, from_x: true, center: self.common_center(&rhs)};
         for i in 0..=temp_pow{
             temp.coefs[i] = self.coefs[i] - rhs.coefs[i];
         }
@@ -52,6 +53,7 @@ impl Sub for TsPoly{
 impl SubAssign for TsPoly{
     fn sub_assign(&mut self, rhs: Self) {
         let temp_pow = if self.max_pow >= rhs.max_pow { self.max_pow } else { rhs.max_pow };
+        self.center = self.common_center(&rhs);
         for i in 0..=temp_pow {
             self.coefs[i]-=rhs.coefs[i];
         }
@@ -63,7 +65,7 @@ impl Mul for TsPoly{
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output{
-        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow + rhs.max_pow, from_x: true};
+        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow + rhs.max_pow, from_x: true, center: self.common_center(&rhs)};
         for i_lhs in 0..Self::DEFAULT_MAX_POW{
             for i_rhs in 0..Self::DEFAULT_MAX_POW{
                 let end_index = i_lhs + i_rhs;
@@ -80,7 +82,7 @@ impl Mul for TsPoly{
 
 impl MulAssign for TsPoly{
     fn mul_assign(&mut self, rhs: Self) {
-        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow + rhs.max_pow, from_x: true};
+        let mut temp = TsPoly{coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow + rhs.max_pow, from_x: true, center: self.common_center(&rhs)};
         for i_lhs in 0..Self::DEFAULT_MAX_POW{
             for i_rhs in 0..Self::DEFAULT_MAX_POW{
                 let end_index = i_lhs + i_rhs;
@@ -97,6 +99,7 @@ impl MulAssign for TsPoly{
         }
 
         self.max_pow = temp_pow;
+        self.center = temp.center;
         for i in 0..=self.max_pow { self.coefs[i] = temp.coefs[i]; }
     }
 }
@@ -154,11 +157,11 @@ impl Div for TsPoly{
             );
         }
         
-        let mut quotient = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow-rhs.max_pow, from_x: true};
+        let mut quotient = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow-rhs.max_pow, from_x: true, center: self.common_center(&rhs)};
         let mut remainder = self.clone();
 
         while remainder.coefs[0] != 0.0 && remainder.max_pow != 0 && remainder.max_pow >= rhs.max_pow {
-            let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: remainder.max_pow-rhs.max_pow, from_x: true};
+            let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: remainder.max_pow-rhs.max_pow, from_x: true, center: quotient.center};
             temp.coefs[remainder.max_pow-rhs.max_pow] = remainder.lead()/rhs.lead();
             quotient+=temp.clone();
             remainder-=temp*rhs.clone();
@@ -178,10 +181,10 @@ impl DivAssign for TsPoly{
             );
         }
         
-        let mut quotient = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow-rhs.max_pow, from_x: true};
+        let mut quotient = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow-rhs.max_pow, from_x: true, center: self.common_center(&rhs)};
 
         while self.coefs[0] != 0.0 && self.max_pow != 0 && self.max_pow >= rhs.max_pow {
-            let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow-rhs.max_pow, from_x: true};
+            let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: self.max_pow-rhs.max_pow, from_x: true, center: quotient.center};
             temp.coefs[self.max_pow-rhs.max_pow] = self.lead()/rhs.lead();
             quotient+=temp.clone();
             *self-=temp*rhs.clone();
@@ -191,6 +194,7 @@ impl DivAssign for TsPoly{
         for i in 0..Self::DEFAULT_MAX_POW{
             self.coefs[i] = quotient.coefs[i];
         }
+        self.center = quotient.center;
     }
 }
 
@@ -198,6 +202,7 @@ impl fmt::Display for TsPoly{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut temp_str = String::new();
         let mut started = false;
+        let variable = if self.center == 0.0 { String::from("x") } else { format!("(x-{})", self.center) };
 
         for index in (0..=self.max_pow).rev(){
             if self.coefs[index] == 0.0 { continue; }
@@ -212,14 +217,14 @@ impl fmt::Display for TsPoly{
                     if self.coefs[index] != 1.0{
                         temp_str += "*";
                     }
-                    temp_str += "x ";
+                    temp_str += &format!("{} ", variable);
                 },
                 _ => {
                     if self.coefs[index] != 1.0{
                         temp_str += "*";
                     }
 
-                    temp_str += &format!("x^{} ", index);
+                    temp_str += &format!("{}^{} ", variable, index);
                 }
             }
 
@@ -2,7 +2,10 @@
 use crate::unrecoverable_error;
 use crate::components::terminal_decoration::Color;
 use crate::components::object_type_definitions::Func;
+use crate::components::ir_builder::FunctionBuilder;
+use crate::components::coefficient_tables::binomial;
 use std::process::exit;
+use llvm_sys::prelude::LLVMValueRef;
 
 //TODO write description for everything defined for this struct
 
@@ -13,7 +16,9 @@ use std::process::exit;
 pub struct TsPoly {
     pub(crate) coefs: Vec<f64>,
     pub(crate) max_pow: usize,
-    pub(crate) from_x: bool
+    pub(crate) from_x: bool,
+    /// Coefitients are next to powers of (argument - center), 0.0 is the ordinary monomial basis.
+    pub(crate) center: f64
 }
 
 impl TsPoly{
@@ -21,7 +26,14 @@ impl TsPoly{
     pub(crate) const DEFAULT_MAX_POW: usize = 30;
 
     pub fn zero() -> Self{
-        Self { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: 0, from_x: true}
+        Self { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: 0, from_x: true, center: 0.0}
+    }
+
+    /// Polynomial equal to the variable itself, expressed in the basis (x - center).
+    pub fn variable(center: f64) -> Self{
+        let mut temp = Self::from_vec(vec![center, 1.0], true);
+        temp.center = center;
+        temp
     }
 
     pub fn from_vec(mut provided_coefs: Vec<f64>, from_x: bool) -> Self{
@@ -32,25 +44,63 @@ impl TsPoly{
                 temp_pow = i;
             }
         }
-        Self { coefs: provided_coefs, max_pow: temp_pow, from_x}
+        Self { coefs: provided_coefs, max_pow: temp_pow, from_x, center: 0.0}
     }
 
+    /// Expands coefitients next to powers of (x - offset) into the monomial basis.
     pub fn put_offset(&mut self, mut offset: f64){
+        self.center = 0.0;
         if offset == 0.0 {return;}
         offset = -offset;
+
+        let mut offset_powers = vec![1.0; Self::DEFAULT_MAX_POW];
+        for power in 1..Self::DEFAULT_MAX_POW {
+            offset_powers[power] = offset_powers[power-1]*offset;
+        }
+
         for power in 1..Self::DEFAULT_MAX_POW{
             if self.coefs[power] != 0.0 {
                 let current_coef = self.coefs[power];
-                self.coefs[0] += current_coef*offset.powf(power as f64);
-                for index in 1..power{
-                    self.coefs[power-index] += current_coef*Self::binomial_coef(power, index)*offset.powf(index as f64);
+                self.coefs[0] += current_coef*offset_powers[power];
+                for (index, offset_power) in offset_powers.iter().enumerate().take(power).skip(1){
+                    self.coefs[power-index] += current_coef*binomial(power, index)*offset_power;
                 }
             }
         }
     }
 
+    /// Value at `argument` by Horner's scheme in powers of (argument - center).
+    pub fn evaluate(&self, argument: f64) -> f64 {
+        let t = argument - self.center;
+        (0..=self.max_pow).rev().fold(0.0, |acc, power| acc*t + self.coefs[power])
+    }
+
+    /// Converts a polynomial kept in the shifted basis into the monomial basis.
+    pub fn into_monomial_basis(mut self) -> Self {
+        let center = self.center;
+        self.put_offset(center);
+        self
+    }
+
+    /// Returns the same polynomial expressed in powers of (x - center) instead of powers of (x - self.center).
+    /// Uses repeated synthetic division (Taylor shift), so no binomial coefitients or powers of the center are formed.
+    pub fn recentered(&self, center: f64) -> TsPoly {
+        let mut temp = self.clone();
+        temp.center = center;
+        let shift = center - self.center;
+        if shift == 0.0 { return temp; }
+
+        for start in 0..self.max_pow {
+            for power in (start..self.max_pow).rev() {
+                temp.coefs[power] += shift*temp.coefs[power+1];
+            }
+        }
+
+        temp
+    }
+
     pub fn from_const(constant: f64) -> Self {
-        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: 0, from_x: true};
+        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: 0, from_x: true, center: 0.0};
         temp.coefs[0] = constant;
         temp
     }
@@ -61,25 +111,6 @@ impl TsPoly{
         self.coefs[new_max_pow+1..Self::DEFAULT_MAX_POW].fill(0.0);
     }
 
-    //TODO Make this function a table look up for speed
-    pub(crate) fn binomial_coef(n: usize, k: usize) -> f64{
-        if k > n {
-            return 0.0;
-        }
-        if k == 0 || k == n {
-            return 1.0;
-        }
-    
-        let k = if k > n - k { n - k } else { k };
-        let mut result = 1;
-    
-        for i in 0..k {
-            result = result * (n - i) / (i + 1);
-        }
-    
-        result as f64
-    }
-
     //FIXME this operator doesn't work as intended, I don't get any offset improvement, the problem is with high powers of polynomials again
     pub fn of(&mut self, mut argument: TsPoly) {
         let mut per_power: Vec<TsPoly> = Vec::<TsPoly>::new();
@@ -98,68 +129,68 @@ impl TsPoly{
         }
     }
 
+    /// Basis of the result of an operation between two polynomials. Both operands are expected to share the basis,
+    /// except for constants which are the same in every basis.
+    pub(crate) fn common_center(&self, rhs: &TsPoly) -> f64{
+        if self.max_pow == 0 { rhs.center } else { self.center }
+    }
+
     /// Returns the coefitient next to the highest power of x of the polynomial.
     pub(crate) fn lead(&self) -> f64{
         self.coefs[self.max_pow]
     }
 
-    pub fn generate_ir(&self, poly_argument: Option<String>, start_addr: u16) -> (String, String){
-        let mut x: String = String::from("%x");
-        if let Some(temp_argument) = poly_argument { x = temp_argument;}
-
-        let mut temp = format!(
-r"%p0_{} = fadd double 0.0, {:.15e}
-%tmul0_{} = fmul double {:.15e}, {}
-%p1_{} = fadd double %tmul0_{}, %p0_{}
-%tpow1_{} = fmul double %x, %x
-%tmul1_{} = fmul double {:.15e}, %tpow1_{}
-%p2_{} = fadd double %tmul1_{}, %p1_{}
-",
-start_addr, self.coefs[0],
-start_addr, self.coefs[1], x,
-start_addr, start_addr, start_addr,
-start_addr,
-start_addr, self.coefs[2], start_addr,
-start_addr, start_addr, start_addr
-        );
-
-        for i in 2..=self.max_pow-1 {
-            temp += format!(
-r"%tpow{}_{} = fmul double %tpow{}_{}, {}
-%tmul{}_{} = fmul double {:.15e}, %tpow{}_{}
-%p{}_{} = fadd double %tmul{}_{}, %p{}_{}
-",
-i, start_addr, i-1, start_addr, x,
-i, start_addr, self.coefs[i+1], i, start_addr,
-i+1, start_addr, i, start_addr, i, start_addr,
-).as_str();
+    /// p^(order)/order!, in the same basis. Its value at the argument is the order-th Taylor coefitient of p around the argument.
+    pub fn normalized_derivative(&self, order: usize) -> TsPoly {
+        if order > self.max_pow {
+            let mut temp = TsPoly::from_vec(vec![0.0], self.from_x);
+            temp.center = self.center;
+            return temp;
         }
-        let virtual_register = format!("%p{}_{}", self.max_pow, start_addr);
-        (temp, virtual_register)
-    }
-
-    pub fn generate_ir_from_existing_powers(&self, start_addr: u16, existing_pow_start_addr: u16) -> (String, String){
-        let mut temp = format!(
-r"%s0_{} = fadd double 0.0, {:.15e}
-%tmul0_{} = fmul double {:.15e}, %tpow0_{}
-%s1_{} = fadd double %tmul0_{}, %s0_{}
-",
-start_addr, self.coefs[0],
-start_addr, self.coefs[1], existing_pow_start_addr,
-start_addr, start_addr, start_addr,
-        );
-
-        for i in 1..self.max_pow {
-            temp += format!(
-r"%tmul{}_{} = fmul double {:.15e}, %tpow{}_{}
-%s{}_{} = fadd double %tmul{}_{}, %s{}_{}
-",
-i, start_addr, self.coefs[i+1], i, existing_pow_start_addr,
-i+1, start_addr, i, start_addr, i, start_addr,
-).as_str();
+
+        let coefs: Vec<f64> = (order..=self.max_pow).map(|power| binomial(power, order)*self.coefs[power]).collect();
+        let mut temp = TsPoly::from_vec(coefs, self.from_x);
+        temp.center = self.center;
+        temp
+    }
+
+    /// Shared powers scheme, c0 + c1*t + c2*t^2 + ..., powers of the argument are taken from `powers`,
+    /// so every polynomial of the same argument reuses the ones already computed.
+    pub(crate) fn build_ir(&self, builder: &FunctionBuilder, powers: &mut SharedPowers, name: &str, fma: bool) -> LLVMValueRef {
+        let mut temp = builder.constant(self.coefs[0]);
+        for power in 1..=self.max_pow {
+            let argument_power = powers.get(builder, power);
+            temp = builder.multiply_add(
+                builder.constant(self.coefs[power]), argument_power, temp, fma, &format!("{}_p{}", name, power)
+            );
         }
-        let virtual_register = format!("%s{}_{}", self.max_pow, start_addr);
-        (temp, virtual_register)
+        temp
     }
 
 }
+
+/// Powers t, t^2, t^3, ... of one argument, computed on demand by repeated multiplication.
+pub(crate) struct SharedPowers {
+    argument: LLVMValueRef,
+    powers: Vec<LLVMValueRef>,
+    name: String,
+}
+
+impl SharedPowers {
+    pub(crate) fn new(argument: LLVMValueRef, name: &str) -> Self {
+        SharedPowers { argument, powers: vec![argument], name: name.to_owned() }
+    }
+
+    pub(crate) fn argument(&self) -> LLVMValueRef {
+        self.argument
+    }
+
+    /// Returns argument^power, power has to be at least 1.
+    pub(crate) fn get(&mut self, builder: &FunctionBuilder, power: usize) -> LLVMValueRef {
+        while self.powers.len() < power {
+            let next = builder.fmul(*self.powers.last().unwrap(), self.argument, &format!("{}_pow{}", self.name, self.powers.len()+1));
+            self.powers.push(next);
+        }
+        self.powers[power-1]
+    }
+}
@@ -0,0 +1,320 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::{
+    object_type_definitions::Func,
+    polynomials::TsPoly,
+    ir_builder::FunctionBuilder,
+    coefficient_tables::inverse_factorial,
+    terminal_decoration::Color
+};
+use std::{
+    fmt,
+    process::exit,
+    f64::consts::{FRAC_2_PI, LOG2_E, SQRT_2}
+};
+use llvm_sys::{
+    prelude::LLVMValueRef,
+    LLVMIntPredicate,
+    LLVMRealPredicate
+};
+
+// Cody-Waite splits, the high parts have their low bits cleared so k*HI is exact for |k| < 2^20
+const PIO2_HI: f64 = 1.570_796_326_734_125_6;
+const PIO2_MID: f64 = 6.077_100_506_303_966e-11;
+const PIO2_LO: f64 = 2.022_266_248_795_950_6e-21;
+const LN2_HI: f64 = 6.931_471_803_691_238e-1;
+const LN2_LO: f64 = 1.908_214_929_270_587_7e-10;
+
+/// Largest |x| reduced for sin/cos, keeps the quadrant count k below 2^20. Larger arguments are passed to libm.
+pub const TRIG_REDUCTION_LIMIT: f64 = 1.6e6;
+/// exp is 0 below and infinite above, clamping keeps r small and both halves of the scale normal.
+const EXP_LOWER_LIMIT: f64 = -750.0;
+const EXP_UPPER_LIMIT: f64 = 710.0;
+/// Subnormal arguments of ln are scaled by 2^54 before the exponent is extracted.
+const SUBNORMAL_SCALE: f64 = 18_014_398_509_481_984.0;
+const SUBNORMAL_SCALE_EXPONENT: i64 = 54;
+
+/// Elementary function evaluated as reduction of the argument, a low degree polynomial on the reduced interval and reconstruction.
+/// Unlike the Taylor series around the precision center, the result stays accurate far from the center.
+/// * sin/cos - x = k*pi/2 + r, |r| <= pi/4, the quadrant k mod 4 selects between ±sin(r) and ±cos(r).
+///   Only for |x| <= `TRIG_REDUCTION_LIMIT`, larger arguments, infinities and NaN branch to the libm function.
+/// * exp - x = k*ln2 + r, |r| <= ln2/2, exp(x) = 2^(k/2)*2^(k-k/2)*exp(r), saturates to 0 and inf
+/// * ln - x = 2^e*m, m in [sqrt(2)/2, sqrt(2)), ln(x) = e*ln2 + 2*artgh((m-1)/(m+1)), subnormals included.
+///   NaN for negative x, -inf at 0.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReducedFunc {
+    Sin { sin_core: TsPoly, cos_core: TsPoly },
+    Cos { sin_core: TsPoly, cos_core: TsPoly },
+    Exp { core: TsPoly },
+    Ln { core: TsPoly },
+}
+
+impl ReducedFunc {
+    /// Returns None for functions which don't have a range reduction.
+    pub fn new(operation: &Func, poly_degree: usize) -> Option<Self> {
+        let trig_cores = || (TsPoly::generate_sin(0.0, poly_degree, false), TsPoly::generate_cos(0.0, poly_degree, false));
+        match operation {
+            Func::Sin => {
+                let (sin_core, cos_core) = trig_cores();
+                Some(ReducedFunc::Sin { sin_core, cos_core })
+            },
+            Func::Cos => {
+                let (sin_core, cos_core) = trig_cores();
+                Some(ReducedFunc::Cos { sin_core, cos_core })
+            },
+            Func::Exp => Some(ReducedFunc::Exp { core: TsPoly::generate_exp(0.0, poly_degree, false) }),
+            Func::Ln => {
+                // 2*artgh(s) = 2*(s + s^3/3 + s^5/5 + ...)
+                let mut coefs = vec![0.0; poly_degree+1];
+                for (power, coef) in coefs.iter_mut().enumerate().skip(1).step_by(2) {
+                    *coef = 2.0/power as f64;
+                }
+                Some(ReducedFunc::Ln { core: TsPoly::from_vec(coefs, false) })
+            },
+            _ => None,
+        }
+    }
+
+    /// Value used for static const evaluation.
+    pub fn exact(&self, argument: f64) -> f64 {
+        match self {
+            ReducedFunc::Sin { .. } => argument.sin(),
+            ReducedFunc::Cos { .. } => argument.cos(),
+            ReducedFunc::Exp { .. } => argument.exp(),
+            ReducedFunc::Ln { .. } => argument.ln(),
+        }
+    }
+
+    /// Mirrors the generated IR operation by operation.
+    pub fn evaluate(&self, argument: f64) -> f64 {
+        let horner = |poly: &TsPoly, t: f64| (0..=poly.max_pow).rev().fold(0.0, |acc, power| acc*t + poly.coefs[power]);
+        match self {
+            ReducedFunc::Sin { .. } | ReducedFunc::Cos { .. } if argument.is_nan() || argument.abs() > TRIG_REDUCTION_LIMIT => self.exact(argument),
+            ReducedFunc::Sin { sin_core, cos_core } | ReducedFunc::Cos { sin_core, cos_core } => {
+                let k = (argument*FRAC_2_PI).round_ties_even();
+                let r = argument - k*PIO2_HI - k*PIO2_MID - k*PIO2_LO;
+                let mut quadrant = k as i64 & 3;
+                if let ReducedFunc::Cos { .. } = self { quadrant = (quadrant + 1) & 3; }
+
+                let value = if quadrant & 1 == 1 { horner(cos_core, r) } else { horner(sin_core, r) };
+                if quadrant & 2 == 2 { -value } else { value }
+            },
+            ReducedFunc::Exp { .. } if argument.is_nan() => argument,
+            ReducedFunc::Exp { core } => {
+                let clamped = argument.clamp(EXP_LOWER_LIMIT, EXP_UPPER_LIMIT);
+                let k = (clamped*LOG2_E).round_ties_even();
+                let r = clamped - k*LN2_HI - k*LN2_LO;
+                let k_half = k as i64 >> 1;
+                let power_of_two = |exponent: i64| f64::from_bits(((exponent + 1023) as u64) << 52);
+                horner(core, r)*power_of_two(k_half)*power_of_two(k as i64 - k_half)
+            },
+            ReducedFunc::Ln { .. } if argument.is_nan() || argument < 0.0 => f64::NAN,
+            ReducedFunc::Ln { .. } if argument == 0.0 => f64::NEG_INFINITY,
+            ReducedFunc::Ln { .. } if argument == f64::INFINITY => f64::INFINITY,
+            ReducedFunc::Ln { core } => {
+                let subnormal = argument < f64::MIN_POSITIVE;
+                let bits = if subnormal { (argument*SUBNORMAL_SCALE).to_bits() } else { argument.to_bits() };
+                let mut exponent = ((bits >> 52) & 0x7ff) as i64 - 1023 - if subnormal { SUBNORMAL_SCALE_EXPONENT } else { 0 };
+                let mut mantissa = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | 0x3ff0_0000_0000_0000);
+                if mantissa > SQRT_2 {
+                    mantissa *= 0.5;
+                    exponent += 1;
+                }
+                let s = (mantissa - 1.0)/(mantissa + 1.0);
+                let e = exponent as f64;
+                e*LN2_HI + (horner(core, s) + e*LN2_LO)
+            },
+        }
+    }
+
+    /// Builds the reduction prologue, the core polynomial(s) in Horner form and the reconstruction.
+    /// Values are named name_* in the IR dump.
+    pub(crate) fn build_ir(&self, builder: &FunctionBuilder, argument: LLVMValueRef, name: &str, fma: bool) -> LLVMValueRef {
+        let n = |suffix: &str| format!("{}_{}", name, suffix);
+
+        match self {
+            ReducedFunc::Sin { sin_core, cos_core } | ReducedFunc::Cos { sin_core, cos_core } => {
+                // also false for NaN, which takes the libm path together with the infinities
+                let magnitude = builder.call("llvm.fabs.f64", &[argument], &n("abs"));
+                let reducible = builder.fcmp(LLVMRealPredicate::LLVMRealOLE, magnitude, builder.constant(TRIG_REDUCTION_LIMIT), &n("small"));
+                let libm_name = if let ReducedFunc::Cos { .. } = self { "cos" } else { "sin" };
+                builder.branch(
+                    reducible,
+                    || {
+                        let scaled = builder.fmul(argument, builder.constant(FRAC_2_PI), &n("kx"));
+                        let k = builder.call("llvm.rint.f64", &[scaled], &n("k"));
+                        let r = cody_waite_ir(builder, argument, k, &[PIO2_HI, PIO2_MID, PIO2_LO], name);
+                        let mut k_integer = builder.fptosi(k, &n("ki"));
+                        if let ReducedFunc::Cos { .. } = self {
+                            k_integer = builder.add(k_integer, builder.constant_i64(1), &n("kq"));
+                        }
+                        let quadrant = builder.and(k_integer, builder.constant_i64(3), &n("q"));
+
+                        let sin_value = sin_core.build_horner_ir(builder, r, &n("s"), fma);
+                        let cos_value = cos_core.build_horner_ir(builder, r, &n("c"), fma);
+                        let odd_bit = builder.and(quadrant, builder.constant_i64(1), &n("oq"));
+                        let odd = builder.icmp(LLVMIntPredicate::LLVMIntNE, odd_bit, builder.constant_i64(0), &n("odd"));
+                        let value = builder.select(odd, cos_value, sin_value, &n("v"));
+                        let sign_bit = builder.and(quadrant, builder.constant_i64(2), &n("hq"));
+                        let negative = builder.icmp(LLVMIntPredicate::LLVMIntNE, sign_bit, builder.constant_i64(0), &n("neg"));
+                        let negated = builder.fneg(value, &n("nv"));
+                        builder.select(negative, negated, value, &n("reduced"))
+                    },
+                    || builder.call(libm_name, &[argument], &n("libm")),
+                    name
+                )
+            },
+            ReducedFunc::Exp { core } => {
+                let clamped_low = builder.call("llvm.maxnum.f64", &[argument, builder.constant(EXP_LOWER_LIMIT)], &n("xl"));
+                let clamped = builder.call("llvm.minnum.f64", &[clamped_low, builder.constant(EXP_UPPER_LIMIT)], &n("xc"));
+                let scaled = builder.fmul(clamped, builder.constant(LOG2_E), &n("kx"));
+                let k = builder.call("llvm.rint.f64", &[scaled], &n("k"));
+                let r = cody_waite_ir(builder, clamped, k, &[LN2_HI, LN2_LO], name);
+                let core_value = core.build_horner_ir(builder, r, &n("p"), fma);
+
+                // 2^k is applied as two powers of two assembled in the exponent bits, each of them stays normal
+                // for the clamped k, so large results overflow to inf and small ones are rounded once into subnormals
+                let k_integer = builder.fptosi(k, &n("ki"));
+                let k_half = builder.ashr(k_integer, builder.constant_i64(1), &n("kh"));
+                let k_rest = builder.sub(k_integer, k_half, &n("kr"));
+                let mut value = core_value;
+                for (part, suffix) in [(k_half, "1"), (k_rest, "2")] {
+                    let biased = builder.add(part, builder.constant_i64(1023), &n(&format!("kb{}", suffix)));
+                    let scale_bits = builder.shl(biased, builder.constant_i64(52), &n(&format!("sb{}", suffix)));
+                    let scale = builder.bitcast_to_f64(scale_bits, &n(&format!("scale{}", suffix)));
+                    value = builder.fmul(value, scale, &n(&format!("v{}", suffix)));
+                }
+                // maxnum/minnum drop NaN, so it's passed through here
+                let is_nan = builder.fcmp(LLVMRealPredicate::LLVMRealUNO, argument, argument, &n("nan"));
+                builder.select(is_nan, argument, value, name)
+            },
+            ReducedFunc::Ln { core } => {
+                let subnormal = builder.fcmp(LLVMRealPredicate::LLVMRealOLT, argument, builder.constant(f64::MIN_POSITIVE), &n("sub"));
+                let normalized_argument = builder.fmul(argument, builder.constant(SUBNORMAL_SCALE), &n("xs"));
+                let normalized_argument = builder.select(subnormal, normalized_argument, argument, &n("xn"));
+                let bits = builder.bitcast_to_i64(normalized_argument, &n("bits"));
+                let exponent_bits = builder.lshr(bits, builder.constant_i64(52), &n("eb"));
+                let exponent_masked = builder.and(exponent_bits, builder.constant_i64(0x7ff), &n("em"));
+                let exponent_bias = builder.select(subnormal, builder.constant_i64(1023 + SUBNORMAL_SCALE_EXPONENT), builder.constant_i64(1023), &n("bias"));
+                let exponent = builder.sub(exponent_masked, exponent_bias, &n("e0"));
+                let mantissa_bits = builder.and(bits, builder.constant_i64(0x000f_ffff_ffff_ffff), &n("mb"));
+                let mantissa_one = builder.or(mantissa_bits, builder.constant_i64(0x3ff0_0000_0000_0000), &n("mo"));
+                let mantissa_full = builder.bitcast_to_f64(mantissa_one, &n("m0"));
+
+                let big = builder.fcmp(LLVMRealPredicate::LLVMRealOGT, mantissa_full, builder.constant(SQRT_2), &n("big"));
+                let mantissa_half = builder.fmul(mantissa_full, builder.constant(0.5), &n("mh"));
+                let mantissa = builder.select(big, mantissa_half, mantissa_full, &n("m"));
+                let exponent_increment = builder.zext(big, &n("ei"));
+                let exponent = builder.add(exponent, exponent_increment, &n("e1"));
+                let exponent = builder.sitofp(exponent, &n("e"));
+
+                let numerator = builder.fsub(mantissa, builder.constant(1.0), &n("num"));
+                let denominator = builder.fadd(mantissa, builder.constant(1.0), &n("den"));
+                let s = builder.fdiv(numerator, denominator, &n("s"));
+                let core_value = core.build_horner_ir(builder, s, &n("p"), fma);
+
+                let low = builder.fmul(exponent, builder.constant(LN2_LO), &n("el"));
+                let low = builder.fadd(core_value, low, &n("lo"));
+                let high = builder.fmul(exponent, builder.constant(LN2_HI), &n("eh"));
+                let value = builder.fadd(high, low, &n("v"));
+
+                // ln(inf) = inf, ln(x < 0) = ln(NaN) = NaN, ln(±0) = -inf
+                let infinite = builder.fcmp(LLVMRealPredicate::LLVMRealOEQ, argument, builder.constant(f64::INFINITY), &n("inf"));
+                let value = builder.select(infinite, argument, value, &n("vi"));
+                let negative = builder.fcmp(LLVMRealPredicate::LLVMRealULT, argument, builder.constant(0.0), &n("negative"));
+                let value = builder.select(negative, builder.constant(f64::NAN), value, &n("vn"));
+                let zero = builder.fcmp(LLVMRealPredicate::LLVMRealOEQ, argument, builder.constant(0.0), &n("zero"));
+                builder.select(zero, builder.constant(f64::NEG_INFINITY), value, name)
+            },
+        }
+    }
+
+    /// Taylor coefitients f^(j)(argument)/j! up to `order`, every derivative is again a multiple of sin, cos, exp or a power of 1/argument.
+    pub(crate) fn build_jet_ir(&self, builder: &FunctionBuilder, argument: LLVMValueRef, order: usize, name: &str, fma: bool) -> Vec<Option<LLVMValueRef>> {
+        let n = |suffix: String| format!("{}_{}", name, suffix);
+        let value = self.build_ir(builder, argument, name, fma);
+
+        match self {
+            ReducedFunc::Sin { sin_core, cos_core } | ReducedFunc::Cos { sin_core, cos_core } => {
+                // sin -> cos -> -sin -> -cos, cos starts one step later in the cycle
+                let (sin_value, cos_value) = match self {
+                    ReducedFunc::Sin { .. } => {
+                        let cos = ReducedFunc::Cos { sin_core: sin_core.clone(), cos_core: cos_core.clone() };
+                        (value, cos.build_ir(builder, argument, &n(String::from("cos")), fma))
+                    },
+                    _ => {
+                        let sin = ReducedFunc::Sin { sin_core: sin_core.clone(), cos_core: cos_core.clone() };
+                        (sin.build_ir(builder, argument, &n(String::from("sin")), fma), value)
+                    },
+                };
+                let shift = if let ReducedFunc::Cos { .. } = self { 1 } else { 0 };
+                (0..=order).map(|j| {
+                    let (derivative, sign) = match (j + shift) % 4 {
+                        0 => (sin_value, 1.0),
+                        1 => (cos_value, 1.0),
+                        2 => (sin_value, -1.0),
+                        _ => (cos_value, -1.0),
+                    };
+                    if j == 0 { return Some(derivative); }
+                    Some(builder.fmul(derivative, builder.constant(sign*inverse_factorial(j)), &n(format!("j{}", j))))
+                }).collect()
+            },
+            ReducedFunc::Exp { .. } => (0..=order).map(|j| {
+                if j == 0 { return Some(value); }
+                Some(builder.fmul(value, builder.constant(inverse_factorial(j)), &n(format!("j{}", j))))
+            }).collect(),
+            ReducedFunc::Ln { .. } => {
+                // ln^(j)(u)/j! = (-1)^(j-1)/(j*u^j)
+                let inverse = builder.fdiv(builder.constant(1.0), argument, &n(String::from("inv")));
+                let mut inverse_power = inverse;
+                (0..=order).map(|j| {
+                    match j {
+                        0 => Some(value),
+                        1 => Some(inverse),
+                        _ => {
+                            inverse_power = builder.fmul(inverse_power, inverse, &n(format!("ip{}", j)));
+                            let sign = if j % 2 == 0 { -1.0 } else { 1.0 };
+                            Some(builder.fmul(inverse_power, builder.constant(sign/j as f64), &n(format!("j{}", j))))
+                        }
+                    }
+                }).collect()
+            },
+        }
+    }
+}
+
+/// r = argument - k*c0 - k*c1 - ...
+fn cody_waite_ir(builder: &FunctionBuilder, argument: LLVMValueRef, k: LLVMValueRef, constants: &[f64], name: &str) -> LLVMValueRef {
+    let mut temp = argument;
+    for (index, constant) in constants.iter().enumerate() {
+        let product = builder.fmul(k, builder.constant(*constant), &format!("{}_c{}", name, index));
+        temp = builder.fsub(temp, product, &format!("{}_r{}", name, index));
+    }
+    temp
+}
+
+impl fmt::Display for ReducedFunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReducedFunc::Sin { .. } => "sin",
+            ReducedFunc::Cos { .. } => "cos",
+            ReducedFunc::Exp { .. } => "e^",
+            ReducedFunc::Ln { .. } => "ln",
+        };
+        write!(f, "{}[reduced]", name)
+    }
+}
+
+/// Replaces sin, cos, exp and ln of the postfix sequence with their range reduced versions.
+/// Has to run before the Taylor optimization, which would otherwise replace them with series around the precision center.
+pub fn apply_range_reduction(sequence: &mut [Func], poly_degree: usize) {
+    if poly_degree == 0 {
+        unrecoverable_error!("Range reduction error | Core polynomial degree has to be at least 1", poly_degree);
+    }
+
+    for elem in sequence.iter_mut() {
+        if let Some(reduced) = ReducedFunc::new(elem, poly_degree) {
+            *elem = Func::Reduced(reduced);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::{
+    terminal_decoration::Color,
+    elf_object::*
+};
+use std::process::exit;
+
+const SHT_HASH: u32 = 5;
+const SHT_DYNAMIC: u32 = 6;
+const SHT_DYNSYM: u32 = 11;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_GNU_STACK: u32 = 0x6474E551;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_HASH: u64 = 4;
+const DT_STRTAB: u64 = 5;
+const DT_SYMTAB: u64 = 6;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_RELAENT: u64 = 9;
+const DT_STRSZ: u64 = 10;
+const DT_SYMENT: u64 = 11;
+const DT_SONAME: u64 = 14;
+
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+
+const ELF_HEADER_SIZE: usize = 0x40;
+const PROGRAM_HEADER_SIZE: usize = 56;
+const PROGRAM_HEADER_COUNT: usize = 4;
+const ENTRY_SIZE: usize = 24;
+const PAGE_SIZE: usize = 0x1000;
+
+/// `jmp [rip+disp32]` to the GOT entry of the callee, padded with int3.
+const STUB_SIZE: usize = 8;
+
+struct OutputSection {
+    name: String,
+    kind: u32,
+    flags: u64,
+    offset: usize,
+    size: usize,
+    link: usize,
+    info: usize,
+    alignment: usize,
+    entry_size: usize,
+}
+
+/// Contents of the shared object, addresses equal file offsets.
+struct Image {
+    bytes: Vec<u8>,
+    sections: Vec<OutputSection>,
+}
+
+impl Image {
+    /// Appends a zeroed section, returns its index in the section header table.
+    fn reserve(&mut self, name: &str, kind: u32, flags: u64, size: usize, alignment: usize) -> usize {
+        let offset = self.bytes.len().next_multiple_of(alignment.max(1));
+        self.bytes.resize(offset + size, 0);
+        self.sections.push(OutputSection { name: name.to_string(), kind, flags, offset, size, link: 0, info: 0, alignment, entry_size: 0 });
+        self.sections.len()
+    }
+
+    fn offset(&self, index: usize) -> usize {
+        self.sections[index - 1].offset
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) {
+        self.bytes[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    fn set_link(&mut self, index: usize, link: usize, info: usize, entry_size: usize) {
+        let section = &mut self.sections[index - 1];
+        section.link = link;
+        section.info = info;
+        section.entry_size = entry_size;
+    }
+}
+
+/// Target of a GOT entry, an external function resolved by the dynamic loader or a symbol of the object.
+#[derive(PartialEq)]
+enum GotEntry {
+    External(usize),
+    Local(usize),
+}
+
+fn not_position_independent(section: &str, kind: u32) -> ! {
+    unrecoverable_error!(
+        "Linker Error | Object can't be linked into a shared object",
+        format!("Relocation type {} in '{}' needs a load address known at link time, emit position independent code", kind, section)
+    );
+}
+
+fn write_i32(image: &mut Image, place: usize, value: i64, symbol: &str) {
+    match i32::try_from(value) {
+        Ok(value) => image.write(place, &value.to_le_bytes()),
+        Err(_) => {
+            unrecoverable_error!("Linker Error | Relocation out of range", format!("'{}' resolves to {:#x}, which doesn't fit into 32 bits", symbol, value));
+        }
+    }
+}
+
+fn string_table_entry(table: &mut Vec<u8>, name: &str) -> u32 {
+    let offset = table.len() as u32;
+    table.extend_from_slice(name.as_bytes());
+    table.push(0);
+    offset
+}
+
+fn dynamic_symbol(name: u32, info: u8, section: usize, value: usize, size: usize) -> Vec<u8> {
+    [
+        &name.to_le_bytes()[..], &[info, 0], &(section as u16).to_le_bytes(),
+        &(value as u64).to_le_bytes(), &(size as u64).to_le_bytes()
+    ].concat()
+}
+
+fn dynamic_relocation(offset: usize, symbol: usize, kind: u32, addend: i64) -> Vec<u8> {
+    [(offset as u64).to_le_bytes(), (((symbol as u64) << 32) | kind as u64).to_le_bytes(), addend.to_le_bytes()].concat()
+}
+
+/// Links a position independent relocatable object into an ELF64 shared object exporting its global symbols.
+/// Undefined symbols are left to the dynamic loader, which looks them up in `needed` and in the program loading the library.
+/// Used when no system linker is available, it only covers what LLVM emits for generated functions: no TLS, no symbol versions, no lazy binding.
+pub fn write_shared_object(object_file: &[u8], soname: &str, needed: &[&str]) -> Vec<u8> {
+    let object = ElfObject::parse(object_file);
+    let loaded = |index: usize| object.sections[index].is_allocated();
+    let relocations = || object.relocation_sections.iter()
+        .filter(move |relocations| loaded(relocations.target))
+        .flat_map(|relocations| relocations.relocations.iter().map(move |relocation| (relocations.target, relocation)));
+
+    let externals: Vec<usize> = (0..object.symbols.len())
+        .filter(|&index| object.symbols[index].section == SHN_UNDEF && !object.symbols[index].name.is_empty())
+        .collect();
+    let exported: Vec<usize> = (0..object.symbols.len())
+        .filter(|&index| {
+            let symbol = &object.symbols[index];
+            symbol.is_defined() && loaded(symbol.section) && !symbol.name.is_empty()
+                && symbol.kind != STT_SECTION && (symbol.binding == STB_GLOBAL || symbol.binding == STB_WEAK)
+        })
+        .collect();
+    let dynamic_index = |symbol: usize| externals.iter().position(|&external| external == symbol).map(|position| position + 1);
+
+    let mut got = Vec::<GotEntry>::new();
+    let mut stubs = Vec::<usize>::new();
+    let mut absolute_relocations = 0;
+    for (target, relocation) in relocations() {
+        let undefined = object.symbols[relocation.symbol].section == SHN_UNDEF;
+        let entry = if undefined { GotEntry::External(relocation.symbol) } else { GotEntry::Local(relocation.symbol) };
+        match relocation.kind {
+            R_X86_64_PLT32 if undefined => {
+                if !stubs.contains(&relocation.symbol) {
+                    stubs.push(relocation.symbol);
+                }
+                if !got.contains(&entry) {
+                    got.push(entry);
+                }
+            },
+            R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX if !got.contains(&entry) => got.push(entry),
+            R_X86_64_64 if object.sections[target].flags & SHF_WRITE != 0 => absolute_relocations += 1,
+            _ => {}
+        }
+    }
+
+    let mut dynstr = vec![0u8];
+    let soname_offset = string_table_entry(&mut dynstr, soname);
+    let needed_offsets: Vec<u32> = needed.iter().map(|library| string_table_entry(&mut dynstr, library)).collect();
+    let symbol_names: Vec<u32> = externals.iter().chain(&exported).map(|&index| string_table_entry(&mut dynstr, object.symbols[index].name)).collect();
+    let symbol_count = 1 + externals.len() + exported.len();
+
+    let mut image = Image { bytes: vec![0u8; ELF_HEADER_SIZE + PROGRAM_HEADER_COUNT*PROGRAM_HEADER_SIZE], sections: Vec::new() };
+    let hash = image.reserve(".hash", SHT_HASH, SHF_ALLOC, 4*(2 + 1 + symbol_count), 8);
+    let dynsym = image.reserve(".dynsym", SHT_DYNSYM, SHF_ALLOC, ENTRY_SIZE*symbol_count, 8);
+    let dynstr_section = image.reserve(".dynstr", SHT_STRTAB, SHF_ALLOC, dynstr.len(), 1);
+    let rela = image.reserve(".rela.dyn", SHT_RELA, SHF_ALLOC, ENTRY_SIZE*(got.len() + absolute_relocations), 8);
+
+    let mut placed = vec![0usize; object.sections.len()];
+    let mut place_sections = |image: &mut Image, writable: bool| {
+        for (index, section) in object.sections.iter().enumerate() {
+            if section.is_allocated() && (section.flags & SHF_WRITE != 0) == writable {
+                placed[index] = image.reserve(section.name, SHT_PROGBITS, section.flags, section.size, section.alignment);
+                let offset = image.offset(placed[index]);
+                image.write(offset, object.section_bytes(index));
+            }
+        }
+    };
+    place_sections(&mut image, false);
+    let plt = image.reserve(".plt", SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, STUB_SIZE*stubs.len(), 16);
+    let text_end = image.bytes.len();
+
+    image.bytes.resize(text_end.next_multiple_of(PAGE_SIZE), 0);
+    let data_start = image.bytes.len();
+    place_sections(&mut image, true);
+    let got_section = image.reserve(".got", SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, 8*got.len(), 8);
+    let dynamic_entries = needed.len() + 10;
+    let dynamic = image.reserve(".dynamic", SHT_DYNAMIC, SHF_ALLOC | SHF_WRITE, 16*dynamic_entries, 8);
+    let data_end = image.bytes.len();
+
+    let symbol_address = |image: &Image, index: usize| {
+        let symbol = &object.symbols[index];
+        match symbol.section {
+            SHN_ABS => symbol.value,
+            section if symbol.is_defined() && placed[section] != 0 => image.offset(placed[section]) + symbol.value,
+            _ => {
+                unrecoverable_error!("Linker Error | Unsupported symbol", format!("'{}' is defined in section {:#x}, which isn't loaded with the shared object", symbol.name, symbol.section));
+            }
+        }
+    };
+    let got_address = |image: &Image, entry: &GotEntry| image.offset(got_section) + 8*got.iter().position(|slot| slot == entry).unwrap();
+
+    let mut dynamic_relocations = Vec::<u8>::new();
+    for entry in &got {
+        let slot = got_address(&image, entry);
+        match *entry {
+            GotEntry::External(symbol) => dynamic_relocations.extend(dynamic_relocation(slot, dynamic_index(symbol).unwrap(), R_X86_64_GLOB_DAT, 0)),
+            GotEntry::Local(symbol) => {
+                let address = symbol_address(&image, symbol);
+                image.write(slot, &(address as u64).to_le_bytes());
+                dynamic_relocations.extend(dynamic_relocation(slot, 0, R_X86_64_RELATIVE, address as i64));
+            }
+        }
+    }
+    for (position, &symbol) in stubs.iter().enumerate() {
+        let stub = image.offset(plt) + STUB_SIZE*position;
+        let displacement = got_address(&image, &GotEntry::External(symbol)) as i64 - (stub + 6) as i64;
+        let mut code = [0xFF, 0x25, 0, 0, 0, 0, 0xCC, 0xCC];
+        code[2..6].copy_from_slice(&(displacement as i32).to_le_bytes());
+        image.write(stub, &code);
+    }
+
+    for (target, relocation) in relocations() {
+        let place = image.offset(placed[target]) + relocation.offset;
+        let symbol = &object.symbols[relocation.symbol];
+        let undefined = symbol.section == SHN_UNDEF;
+        match relocation.kind {
+            R_X86_64_PC32 | R_X86_64_PLT32 => {
+                let address = if !undefined {
+                    symbol_address(&image, relocation.symbol)
+                } else if relocation.kind == R_X86_64_PLT32 {
+                    image.offset(plt) + STUB_SIZE*stubs.iter().position(|&stub| stub == relocation.symbol).unwrap()
+                } else {
+                    not_position_independent(object.sections[target].name, relocation.kind);
+                };
+                write_i32(&mut image, place, address as i64 + relocation.addend - place as i64, symbol.name);
+            },
+            R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX => {
+                let entry = if undefined { GotEntry::External(relocation.symbol) } else { GotEntry::Local(relocation.symbol) };
+                let slot = got_address(&image, &entry);
+                write_i32(&mut image, place, slot as i64 + relocation.addend - place as i64, symbol.name);
+            },
+            R_X86_64_64 if object.sections[target].flags & SHF_WRITE != 0 => {
+                if undefined {
+                    dynamic_relocations.extend(dynamic_relocation(place, dynamic_index(relocation.symbol).unwrap(), R_X86_64_64, relocation.addend));
+                } else {
+                    let address = symbol_address(&image, relocation.symbol) as i64 + relocation.addend;
+                    image.write(place, &address.to_le_bytes());
+                    dynamic_relocations.extend(dynamic_relocation(place, 0, R_X86_64_RELATIVE, address));
+                }
+            },
+            kind => not_position_independent(object.sections[target].name, kind)
+        }
+    }
+    let rela_offset = image.offset(rela);
+    image.write(rela_offset, &dynamic_relocations);
+
+    // externals first, all symbols are global so sh_info of .dynsym is 1
+    let mut symbols = vec![0u8; ENTRY_SIZE];
+    for (position, &index) in externals.iter().chain(&exported).enumerate() {
+        let symbol = &object.symbols[index];
+        symbols.extend(if symbol.section == SHN_UNDEF {
+            dynamic_symbol(symbol_names[position], symbol.binding << 4 | symbol.kind, SHN_UNDEF, 0, 0)
+        } else {
+            dynamic_symbol(symbol_names[position], symbol.binding << 4 | symbol.kind, placed[symbol.section], symbol_address(&image, index), symbol.size)
+        });
+    }
+    image.write(image.offset(dynsym), &symbols);
+    image.write(image.offset(dynstr_section), &dynstr);
+
+    // a single bucket chaining every symbol, the loader compares the names anyway
+    let mut hash_table = vec![1u32, symbol_count as u32, if symbol_count > 1 { 1 } else { 0 }, 0];
+    hash_table.extend((1..symbol_count).map(|index| if index + 1 < symbol_count { index as u32 + 1 } else { 0 }));
+    image.write(image.offset(hash), &hash_table.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<u8>>());
+
+    let mut entries: Vec<(u64, u64)> = needed_offsets.iter().map(|&offset| (DT_NEEDED, offset as u64)).collect();
+    entries.extend([
+        (DT_SONAME, soname_offset as u64),
+        (DT_HASH, image.offset(hash) as u64),
+        (DT_STRTAB, image.offset(dynstr_section) as u64),
+        (DT_SYMTAB, image.offset(dynsym) as u64),
+        (DT_STRSZ, dynstr.len() as u64),
+        (DT_SYMENT, ENTRY_SIZE as u64),
+        (DT_RELA, image.offset(rela) as u64),
+        (DT_RELASZ, dynamic_relocations.len() as u64),
+        (DT_RELAENT, ENTRY_SIZE as u64),
+        (DT_NULL, 0)
+    ]);
+    image.write(image.offset(dynamic), &entries.iter().flat_map(|(tag, value)| [tag.to_le_bytes(), value.to_le_bytes()].concat()).collect::<Vec<u8>>());
+
+    image.set_link(hash, dynsym, 0, 4);
+    image.set_link(dynsym, dynstr_section, 1, ENTRY_SIZE);
+    image.set_link(rela, dynsym, 0, ENTRY_SIZE);
+    image.set_link(dynamic, dynstr_section, 0, 16);
+
+    let program_headers = [
+        (PT_LOAD, PF_R | PF_X, 0, text_end, PAGE_SIZE),
+        (PT_LOAD, PF_R | PF_W, data_start, data_end - data_start, PAGE_SIZE),
+        (PT_DYNAMIC, PF_R | PF_W, image.offset(dynamic), 16*dynamic_entries, 8),
+        (PT_GNU_STACK, PF_R | PF_W, 0, 0, 16)
+    ];
+    for (position, (kind, flags, offset, size, alignment)) in program_headers.into_iter().enumerate() {
+        let header = [
+            &kind.to_le_bytes()[..], &flags.to_le_bytes(),
+            &(offset as u64).to_le_bytes(), &(offset as u64).to_le_bytes(), &(offset as u64).to_le_bytes(),
+            &(size as u64).to_le_bytes(), &(size as u64).to_le_bytes(), &(alignment as u64).to_le_bytes()
+        ].concat();
+        image.write(ELF_HEADER_SIZE + position*PROGRAM_HEADER_SIZE, &header);
+    }
+
+    let mut names = vec![0u8];
+    let shstrtab_name = string_table_entry(&mut names, ".shstrtab");
+    let section_names: Vec<u32> = image.sections.iter().map(|section| string_table_entry(&mut names, &section.name)).collect();
+    let shstrtab_offset = image.bytes.len();
+    image.bytes.extend_from_slice(&names);
+
+    let section_table = image.bytes.len().next_multiple_of(8);
+    image.bytes.resize(section_table + 0x40, 0);
+    let headers = image.sections.iter().zip(section_names)
+        .map(|(section, name)| (name, section.kind, section.flags, section.offset, section.offset, section.size, section.link, section.info, section.alignment, section.entry_size))
+        .chain([(shstrtab_name, SHT_STRTAB, 0, 0, shstrtab_offset, names.len(), 0, 0, 1, 0)]);
+    for (name, kind, flags, address, offset, size, link, info, alignment, entry_size) in headers {
+        image.bytes.extend([
+            &name.to_le_bytes()[..], &kind.to_le_bytes(), &flags.to_le_bytes(),
+            &(address as u64).to_le_bytes(), &(offset as u64).to_le_bytes(), &(size as u64).to_le_bytes(),
+            &(link as u32).to_le_bytes(), &(info as u32).to_le_bytes(), &(alignment as u64).to_le_bytes(), &(entry_size as u64).to_le_bytes()
+        ].concat());
+    }
+    let section_count = image.sections.len() + 2;
+
+    let mut header = vec![0x7F, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    header.extend([
+        &3u16.to_le_bytes()[..], &62u16.to_le_bytes(), &1u32.to_le_bytes(), &0u64.to_le_bytes(),
+        &(ELF_HEADER_SIZE as u64).to_le_bytes(), &(section_table as u64).to_le_bytes(), &0u32.to_le_bytes(),
+        &(ELF_HEADER_SIZE as u16).to_le_bytes(), &(PROGRAM_HEADER_SIZE as u16).to_le_bytes(), &(PROGRAM_HEADER_COUNT as u16).to_le_bytes(),
+        &0x40u16.to_le_bytes(), &(section_count as u16).to_le_bytes(), &(section_count as u16 - 1).to_le_bytes()
+    ].concat());
+    image.write(0, &header);
+    image.bytes
+}
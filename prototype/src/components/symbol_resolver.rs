@@ -0,0 +1,128 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::terminal_decoration::Color;
+use crate::stages::function_lexing::is_builtin_name;
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    process::exit,
+    sync::{OnceLock, RwLock, RwLockReadGuard}
+};
+
+/// Signature of the functions formulas can call, eg. `bessel_j0(x)`.
+pub type ExternalFunction = extern "C" fn(f64) -> f64;
+
+/// (name used by the generated code, libm symbol, number of arguments)
+const LIBM_FUNCTIONS: [(&str, &str, usize); 31] = [
+    ("sin", "sin", 1), ("cos", "cos", 1), ("tan", "tan", 1),
+    ("asin", "asin", 1), ("acos", "acos", 1), ("atan", "atan", 1),
+    ("sinh", "sinh", 1), ("cosh", "cosh", 1), ("tanh", "tanh", 1),
+    ("asinh", "asinh", 1), ("acosh", "acosh", 1), ("atanh", "atanh", 1),
+    ("exp", "exp", 1), ("exp2", "exp2", 1), ("expm1", "expm1", 1),
+    ("ln", "log", 1), ("log2", "log2", 1), ("log10", "log10", 1), ("log1p", "log1p", 1),
+    ("sqrt", "sqrt", 1), ("cbrt", "cbrt", 1),
+    ("erf", "erf", 1), ("erfc", "erfc", 1), ("tgamma", "tgamma", 1), ("lgamma", "lgamma", 1),
+    ("j0", "j0", 1), ("j1", "j1", 1),
+    // targets of llvm.rint, llvm.pow and llvm.fma when the CPU has no instruction for them
+    ("rint", "rint", 1), ("pow", "pow", 2), ("fma", "fma", 3), ("fabs", "fabs", 1)
+];
+
+/// Stops with the `error` heading unless `name` can name a C function, as registered and exported functions have to.
+pub fn check_c_identifier(name: &str, error: &str) {
+    if name.is_empty() || !name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_') || name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        unrecoverable_error!(error, format!("'{}' has to consist of letters, digits and '_' and can't start with a digit", name));
+    }
+}
+
+/// Symbol of libm implementing the function the generated code calls `name`, eg. `log` for `ln`.
+pub fn libm_symbol(name: &str) -> Option<&'static str> {
+    LIBM_FUNCTIONS.iter().find(|(function, _, _)| *function == name).map(|(_, symbol, _)| *symbol)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ExternalSymbol {
+    address: usize,
+    arity: usize
+}
+
+/// Functions the generated code may call, by the name used in formulas and in the IR.
+/// The lexer turns `name(...)` of a registered one argument function into a call, the linker resolves the calls to their addresses.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolResolver {
+    symbols: HashMap<String, ExternalSymbol>
+}
+
+impl SymbolResolver {
+    pub fn new() -> Self {
+        SymbolResolver { symbols: HashMap::new() }
+    }
+
+    /// The math functions of libm, looked up with `dlsym`. Functions missing from the libm of the system are left out.
+    pub fn with_libm() -> Self {
+        let mut resolver = Self::new();
+        let libm = unsafe { libc::dlopen(c"libm.so.6".as_ptr(), libc::RTLD_NOW | libc::RTLD_NOLOAD) };
+        for (name, symbol, arity) in LIBM_FUNCTIONS {
+            let symbol = CString::new(symbol).unwrap();
+            let mut address = unsafe { libc::dlsym(libc::RTLD_DEFAULT, symbol.as_ptr()) };
+            if address.is_null() && !libm.is_null() {
+                address = unsafe { libc::dlsym(libm, symbol.as_ptr()) };
+            }
+            if !address.is_null() {
+                resolver.symbols.insert(name.to_string(), ExternalSymbol { address: address as usize, arity });
+            }
+        }
+        resolver
+    }
+
+    /// Registers a function under `name`, replacing a function registered before under the same name.
+    /// Names of the lexer's functions and of the libm functions are reserved.
+    pub fn register(&mut self, name: &str, function: ExternalFunction) {
+        check_c_identifier(name, "Symbol resolver error | Invalid function name");
+        // `sin`, `ln`, ... are evaluated by the Taylor path and called in libm, another function under the name would split the two
+        if is_builtin_name(name) || LIBM_FUNCTIONS.iter().any(|(function, symbol, _)| *function == name || *symbol == name) {
+            unrecoverable_error!("Symbol resolver error | Reserved function name", format!("'{}' is a built in or libm function and can't be replaced", name));
+        }
+        self.symbols.insert(name.to_string(), ExternalSymbol { address: function as usize, arity: 1 });
+    }
+
+    pub fn address(&self, name: &str) -> Option<usize> {
+        self.symbols.get(name).map(|symbol| symbol.address)
+    }
+
+    /// Number of f64 arguments of the function.
+    pub fn arity(&self, name: &str) -> Option<usize> {
+        self.symbols.get(name).map(|symbol| symbol.arity)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.symbols.keys().map(|name| name.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.symbols.contains_key(name)
+    }
+
+    /// Registered function of one argument, which formulas can call.
+    pub fn unary_function(&self, name: &str) -> Option<ExternalFunction> {
+        match self.symbols.get(name) {
+            Some(ExternalSymbol { address, arity: 1 }) => Some(unsafe { std::mem::transmute::<usize, ExternalFunction>(*address) }),
+            _ => None
+        }
+    }
+}
+
+static GLOBAL_RESOLVER: OnceLock<RwLock<SymbolResolver>> = OnceLock::new();
+
+fn global() -> &'static RwLock<SymbolResolver> {
+    GLOBAL_RESOLVER.get_or_init(|| RwLock::new(SymbolResolver::with_libm()))
+}
+
+/// Resolver consulted by the lexer and the linker, starts out with the libm functions.
+pub fn global_resolver() -> RwLockReadGuard<'static, SymbolResolver> {
+    global().read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Makes `function` callable from every formula compiled afterwards, eg. `register_external_function("bessel_j0", bessel_j0)`.
+pub fn register_external_function(name: &str, function: ExternalFunction) {
+    global().write().unwrap_or_else(|poisoned| poisoned.into_inner()).register(name, function);
+}
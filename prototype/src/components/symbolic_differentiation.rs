@@ -0,0 +1,277 @@
+#![allow(unused_imports)]
+use crate::unrecoverable_error;
+use crate::components::{
+    object_type_definitions::{Func, Node},
+    expression_simplifier::apply,
+    terminal_decoration::Color
+};
+use crate::stages::function_lexing::{lex_function, convert_infix_to_postfix};
+use std::{
+    fmt,
+    process::exit
+};
+
+/// Builds the expression tree of a postfix sequence.
+/// Unary functions keep their argument in `left`, binary operators keep the first operand in `left` and the second in `right`.
+pub fn postfix_to_tree(postfix: &[Func]) -> Node {
+    let mut stack: Vec<Node> = Vec::<Node>::new();
+    let pop = |stack: &mut Vec<Node>| -> Box<Node> {
+        match stack.pop() {
+            Some(node) => Box::new(node),
+            None => {
+                unrecoverable_error!("Frontend error | During building of the expression tree", "No operands on the stack, even though at least one was expected to be.");
+            }
+        }
+    };
+
+    for elem in postfix {
+        let node = match elem {
+            Func::X | Func::Const(_) => Node::from_func(elem.clone()),
+            Func::Exact(subterm) if !subterm.is_empty() => Node::from_func(elem.clone()),
+            Func::Add | Func::Sub | Func::Mul | Func::Div | Func::Pow => {
+                let right = pop(&mut stack);
+                let left = pop(&mut stack);
+                Node { left: Some(left), right: Some(right), op: elem.clone() }
+            },
+            Func::Ob | Func::Cb | Func::None | Func::Poly(_) | Func::Rational(_) | Func::Reduced(_) => {
+                unrecoverable_error!(
+                    "Frontend error | During building of the expression tree",
+                    format!("'Func::{:?}' was encountered, which shouldn't be in the postfix form of a formula.", elem)
+                );
+            },
+            _ => Node { left: Some(pop(&mut stack)), right: None, op: elem.clone() }
+        };
+        stack.push(node);
+    }
+
+    let root = pop(&mut stack);
+    if !stack.is_empty() {
+        unrecoverable_error!("Frontend error | During building of the expression tree", "More than one operand was left on the stack.");
+    }
+    *root
+}
+
+/// Inverse of `postfix_to_tree`.
+pub fn tree_to_postfix(root: &Node) -> Vec<Func> {
+    let mut postfix = Vec::<Func>::new();
+    push_postfix(root, &mut postfix);
+    postfix
+}
+
+fn push_postfix(node: &Node, postfix: &mut Vec<Func>) {
+    if let Some(left) = &node.left {
+        push_postfix(left, postfix);
+    }
+    if let Some(right) = &node.right {
+        push_postfix(right, postfix);
+    }
+    postfix.push(node.op.clone());
+}
+
+/// Replaces every `diff(f(x))` of a postfix sequence with the simplified derivative of f, nested diffs are expanded from the inside out.
+pub fn expand_derivatives(postfix: &mut Vec<Func>) {
+    if postfix.contains(&Func::Diff) {
+        *postfix = tree_to_postfix(&expand_node(&postfix_to_tree(postfix)));
+    }
+}
+
+fn expand_node(node: &Node) -> Node {
+    let left = node.left.as_ref().map(|left| Box::new(expand_node(left)));
+    let right = node.right.as_ref().map(|right| Box::new(expand_node(right)));
+    match (&node.op, left) {
+        (Func::Diff, Some(argument)) => argument.derivative().simplified(),
+        (_, left) => Node { left, right, op: node.op.clone() }
+    }
+}
+
+/// Simplified derivative of a formula, eg. `symbolic_derivative("sin(x)*ln(x)")`, which can be printed back to a formula string.
+pub fn symbolic_derivative(function: &str) -> Node {
+    let mut sequence = lex_function(function);
+    convert_infix_to_postfix(&mut sequence);
+    expand_derivatives(&mut sequence);
+    postfix_to_tree(&sequence).derivative().simplified()
+}
+
+fn constant(value: f64) -> Node {
+    Node::from_value(value)
+}
+
+fn negated(node: Node) -> Node {
+    Node::binary(Func::Sub, constant(0.0), node)
+}
+
+impl Node {
+    fn first(&self) -> &Node {
+        match &self.left {
+            Some(left) => left,
+            None => {
+                unrecoverable_error!("Frontend error | Malformed expression tree", format!("'{}' has no operand.", self.op));
+            }
+        }
+    }
+
+    fn second(&self) -> &Node {
+        match &self.right {
+            Some(right) => right,
+            None => {
+                unrecoverable_error!("Frontend error | Malformed expression tree", format!("'{}' has no second operand.", self.op));
+            }
+        }
+    }
+
+    /// True if x doesn't appear in the tree.
+    pub fn is_constant(&self) -> bool {
+        if let Func::Exact(subterm) = &self.op {
+            if !subterm.is_empty() {
+                return !subterm.contains(&Func::X);
+            }
+        }
+        self.op != Func::X
+            && self.left.as_ref().is_none_or(|left| left.is_constant())
+            && self.right.as_ref().is_none_or(|right| right.is_constant())
+    }
+
+    /// d/dx of the tree, without any simplification.
+    pub fn derivative(&self) -> Node {
+        if self.is_constant() {
+            return constant(0.0);
+        }
+
+        match &self.op {
+            Func::X => constant(1.0),
+            Func::Add | Func::Sub => Node::binary(self.op.clone(), self.first().derivative(), self.second().derivative()),
+            Func::Mul => {
+                let (u, v) = (self.first(), self.second());
+                Node::binary(Func::Add,
+                    Node::binary(Func::Mul, u.derivative(), v.clone()),
+                    Node::binary(Func::Mul, u.clone(), v.derivative())
+                )
+            },
+            Func::Div => {
+                let (u, v) = (self.first(), self.second());
+                Node::binary(Func::Div,
+                    Node::binary(Func::Sub,
+                        Node::binary(Func::Mul, u.derivative(), v.clone()),
+                        Node::binary(Func::Mul, u.clone(), v.derivative())
+                    ),
+                    Node::binary(Func::Pow, v.clone(), constant(2.0))
+                )
+            },
+            Func::Pow => {
+                let (u, v) = (self.first(), self.second());
+                if v.is_constant() {
+                    // (u^c)' = c*u^(c-1)*u'
+                    Node::binary(Func::Mul,
+                        Node::binary(Func::Mul, v.clone(), Node::binary(Func::Pow, u.clone(), Node::binary(Func::Sub, v.clone(), constant(1.0)))),
+                        u.derivative()
+                    )
+                }else if u.is_constant() {
+                    // (a^v)' = a^v*ln(a)*v'
+                    Node::binary(Func::Mul, Node::binary(Func::Mul, self.clone(), Node::unary(Func::Ln, u.clone())), v.derivative())
+                }else{
+                    // (u^v)' = u^v*(v'*ln(u) + v*u'/u)
+                    Node::binary(Func::Mul, self.clone(), Node::binary(Func::Add,
+                        Node::binary(Func::Mul, v.derivative(), Node::unary(Func::Ln, u.clone())),
+                        Node::binary(Func::Div, Node::binary(Func::Mul, v.clone(), u.derivative()), u.clone())
+                    ))
+                }
+            },
+            Func::Diff => self.first().derivative().derivative(),
+            Func::Exact(subterm) if subterm.is_empty() => Node::unary(self.op.clone(), self.first().derivative()),
+            Func::Exact(subterm) => postfix_to_tree(subterm).derivative(),
+            _ => {
+                let u = self.first();
+                let outer = match &self.op {
+                    Func::Sin => Node::unary(Func::Cos, u.clone()),
+                    Func::Cos => negated(Node::unary(Func::Sin, u.clone())),
+                    Func::Tg => Node::binary(Func::Div, constant(1.0), Node::binary(Func::Pow, Node::unary(Func::Cos, u.clone()), constant(2.0))),
+                    Func::Ctg => negated(Node::binary(Func::Div, constant(1.0), Node::binary(Func::Pow, Node::unary(Func::Sin, u.clone()), constant(2.0)))),
+                    Func::Asin => Node::binary(Func::Div, constant(1.0), Node::unary(Func::Sqrt, Node::binary(Func::Sub, constant(1.0), Node::binary(Func::Pow, u.clone(), constant(2.0))))),
+                    Func::Acos => negated(Node::binary(Func::Div, constant(1.0), Node::unary(Func::Sqrt, Node::binary(Func::Sub, constant(1.0), Node::binary(Func::Pow, u.clone(), constant(2.0)))))),
+                    Func::Atg => Node::binary(Func::Div, constant(1.0), Node::binary(Func::Add, constant(1.0), Node::binary(Func::Pow, u.clone(), constant(2.0)))),
+                    Func::Actg => negated(Node::binary(Func::Div, constant(1.0), Node::binary(Func::Add, constant(1.0), Node::binary(Func::Pow, u.clone(), constant(2.0))))),
+                    Func::Ln => Node::binary(Func::Div, constant(1.0), u.clone()),
+                    Func::Exp => self.clone(),
+                    Func::Sqrt => Node::binary(Func::Div, constant(1.0), Node::binary(Func::Mul, constant(2.0), self.clone())),
+                    _ => {
+                        unrecoverable_error!(
+                            "Differentiation error | Function can't be differentiated symbolically",
+                            format!("'{}' is not supported.", self.op)
+                        );
+                    }
+                };
+                Node::binary(Func::Mul, outer, u.derivative())
+            }
+        }
+    }
+
+    /// Value of the tree at x, evaluated with the Rust implementations of the functions.
+    pub fn evaluate(&self, x: f64) -> f64 {
+        match &self.op {
+            Func::X => x,
+            Func::Const(value) => *value,
+            Func::Diff => self.first().derivative().evaluate(x),
+            Func::Exact(subterm) if subterm.is_empty() => self.first().evaluate(x),
+            Func::Exact(subterm) => postfix_to_tree(subterm).evaluate(x),
+            op => {
+                let a = self.first().evaluate(x);
+                let b = self.right.as_ref().map_or(0.0, |right| right.evaluate(x));
+                match apply(op, a, b) {
+                    Some(value) => value,
+                    None => {
+                        unrecoverable_error!("Frontend error | Malformed expression tree", format!("'{}' can't be evaluated.", op));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Binding strength used to decide where the printed formula needs brackets.
+fn print_priority(node: &Node) -> u8 {
+    match node.op {
+        Func::Add | Func::Sub => 1,
+        Func::Mul | Func::Div => 2,
+        Func::Pow => 3,
+        Func::Const(value) if value < 0.0 => 1,
+        _ => 4
+    }
+}
+
+/// Prints the tree as a formula string that `lex_function` accepts again, eg. `cos(x)*ln(x)+sin(x)/x`.
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bracketed = |f: &mut fmt::Formatter<'_>, node: &Node, needed: bool| {
+            if needed { write!(f, "({})", node) } else { write!(f, "{}", node) }
+        };
+
+        match &self.op {
+            Func::X => write!(f, "x"),
+            Func::Const(value) if *value < 0.0 => write!(f, "0-{}", -value),
+            Func::Const(value) => write!(f, "{}", value),
+            Func::Exact(subterm) if !subterm.is_empty() => write!(f, "{}", self.op),
+            Func::Add | Func::Sub | Func::Mul | Func::Div | Func::Pow => {
+                let priority = print_priority(self);
+                let (left, right) = (self.first(), self.second());
+                bracketed(f, left, print_priority(left) < priority || (self.op == Func::Pow && print_priority(left) == priority))?;
+                write!(f, "{}", self.op)?;
+                bracketed(f, right, print_priority(right) < priority || (print_priority(right) == priority && matches!(self.op, Func::Sub | Func::Div | Func::Pow)))
+            },
+            _ => {
+                let name = match &self.op {
+                    Func::Exp => "e^",
+                    Func::Asin => "asin",
+                    Func::Acos => "acos",
+                    Func::Atg => "atg",
+                    Func::Actg => "actg",
+                    _ => ""
+                };
+                if name.is_empty() {
+                    write!(f, "{}({})", self.op, self.first())
+                }else{
+                    write!(f, "{}({})", name, self.first())
+                }
+            }
+        }
+    }
+}
@@ -1,96 +1,89 @@
 #![allow(dead_code, unused_imports)]
 use super::polynomials::TsPoly;
+use super::coefficient_tables::inverse_factorial;
 use crate::unrecoverable_error;
 use crate::components::terminal_decoration::Color;
 use std::process::exit;
 use std::f64::consts::PI;
 
 
+/// All series are generated in the shifted basis, coefitients are next to powers of (x - offset).
+/// Use `into_monomial_basis` to expand them into powers of x.
 impl TsPoly {
     pub fn generate_sin(mut offset: f64, max_p: usize, from_x: bool) -> TsPoly{
-        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
+        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
+        //derivatives are periodic, so the reduced offset is only used for evaluating them
         let multiple = f64::floor(offset/(2.0*PI));
         offset-=multiple*2.0*PI;
-
-        
-        let mut fact: f64 = 1.0;
         temp.coefs[0] = f64::sin(offset);
         for i in 1..=max_p{
-            fact *= i as f64;
 
             //TODO optimize this match to work faster using just lookup table
             temp.coefs[i] = match i & 0x3 {
-                0 => f64::sin(offset)/fact,
-                1 => f64::cos(offset)/fact,
-                2 => -f64::sin(offset)/fact,
-                3 => -f64::cos(offset)/fact,
+                0 => f64::sin(offset)*inverse_factorial(i),
+                1 => f64::cos(offset)*inverse_factorial(i),
+                2 => -f64::sin(offset)*inverse_factorial(i),
+                3 => -f64::cos(offset)*inverse_factorial(i),
                 num => {
                     unrecoverable_error!("Unforseen error | TsPoly::generate_sin - i%4 gives this as a result", num);
                 }
             }
         }
 
-        temp.put_offset(offset);
         temp
     }
 
     pub fn generate_cos(mut offset: f64, max_p: usize, from_x: bool) -> TsPoly{
-        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
+        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
+        //derivatives are periodic, so the reduced offset is only used for evaluating them
         let multiple = f64::floor(offset/(2.0*PI));
         offset-=multiple*2.0*PI;
-
-        
-        let mut fact: f64 = 1.0;
         temp.coefs[0] = f64::cos(offset);
         for i in 1..=max_p{
-            fact *= i as f64;
 
             //TODO optimize this match to work faster using just lookup table
             temp.coefs[i] = match i & 0x3 {
-                0 => f64::cos(offset)/fact,
-                1 => -f64::sin(offset)/fact,
-                2 => -f64::cos(offset)/fact,
-                3 => f64::sin(offset)/fact,
+                0 => f64::cos(offset)*inverse_factorial(i),
+                1 => -f64::sin(offset)*inverse_factorial(i),
+                2 => -f64::cos(offset)*inverse_factorial(i),
+                3 => f64::sin(offset)*inverse_factorial(i),
                 num => {
                     unrecoverable_error!("Unforseen error | TsPoly::generate_sin - i%4 gives this as a result", num);
                 }
             }
         }
 
-        temp.put_offset(offset);
         temp
     }
 
     pub fn generate_tg_parts(mut offset: f64, max_p: usize, from_x: bool) -> (TsPoly, TsPoly){
-        let multiple = f64::floor(offset/PI);
-        offset-=multiple*PI;
+        let mut sin_poly = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
+        let mut cos_poly = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
 
-        let mut sin_poly = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
-        let mut cos_poly = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
-
-        let mut fact: f64 = 1.0;
+        //both parts change sign after a period of PI, so the reduction has to be a multiple of 2*PI
+        let multiple = f64::floor(offset/(2.0*PI));
+        offset-=multiple*2.0*PI;
         sin_poly.coefs[0] = f64::sin(offset);
         cos_poly.coefs[0] = f64::cos(offset);
         for i in 1..=max_p{
-            fact *= i as f64;
 
             //TODO optimize this match to work faster using just ifs and ands
             match i & 0x3 {
                 0 => {
-                    sin_poly.coefs[i] = f64::sin(offset)/fact;
-                    cos_poly.coefs[i] = f64::cos(offset)/fact;
+                    sin_poly.coefs[i] = f64::sin(offset)*inverse_factorial(i);
+                    cos_poly.coefs[i] = f64::cos(offset)*inverse_factorial(i);
                 },
                 1 => {
-                    sin_poly.coefs[i] = f64::cos(offset)/fact;
-                    cos_poly.coefs[i] = -f64::sin(offset)/fact;
+                    sin_poly.coefs[i] = f64::cos(offset)*inverse_factorial(i);
+                    cos_poly.coefs[i] = -f64::sin(offset)*inverse_factorial(i);
                 },
                 2 => {
-                    sin_poly.coefs[i] = -f64::sin(offset)/fact;
-                    cos_poly.coefs[i] = -f64::cos(offset)/fact;
+                    sin_poly.coefs[i] = -f64::sin(offset)*inverse_factorial(i);
+                    cos_poly.coefs[i] = -f64::cos(offset)*inverse_factorial(i);
                 },
                 3 => {
-                    sin_poly.coefs[i] = -f64::cos(offset)/fact;
-                    cos_poly.coefs[i] = f64::sin(offset)/fact;
+                    sin_poly.coefs[i] = -f64::cos(offset)*inverse_factorial(i);
+                    cos_poly.coefs[i] = f64::sin(offset)*inverse_factorial(i);
                 },
                 num => {
                     unrecoverable_error!("Unforseen error | TsPoly::generate_sin - i%4 gives this as a result", num);
@@ -98,20 +91,15 @@ impl TsPoly {
             }
         }
 
-        sin_poly.put_offset(offset);
-        cos_poly.put_offset(offset);
         (sin_poly, cos_poly)
     }
 
     pub fn generate_exp(offset: f64, max_p: usize, from_x: bool) -> TsPoly{
-        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
-        let mut fact: f64 = 1.0;
+        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
         temp.coefs[0] = f64::exp(offset);
         for i in 1..=max_p{
-            fact *= i as f64;
-            temp.coefs[i] = f64::exp(offset)/fact;
+            temp.coefs[i] = f64::exp(offset)*inverse_factorial(i);
         }
-        temp.put_offset(offset);
         temp
     }
 
@@ -122,15 +110,12 @@ impl TsPoly {
                 format!("{}  < 0.25", offset)
             );
         }
-        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
+        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
         temp.coefs[0] = f64::ln(offset);
         if max_p == 0 {return temp};
 
         temp.coefs[1] = 1.0/offset;
-        if max_p == 1 {
-            temp.put_offset(offset);
-            return temp;
-        }
+        if max_p == 1 {return temp;}
 
         temp.coefs[2] = -1.0/(2.0*offset.powf(2.0));
         for i in 3..=max_p{
@@ -141,51 +126,42 @@ impl TsPoly {
             temp.coefs[i] = temp_value;
         }
 
-        temp.put_offset(offset);
         temp
     }
 
     pub fn generate_sinh(offset: f64, max_p: usize, from_x: bool) -> TsPoly{
-        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
+        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
         let exp2x = f64::exp(2.0*offset);
         let sinh_off = (exp2x - 1.0)/exp2x;
         let cosh_off = (exp2x + 1.0)/exp2x;
 
         temp.coefs[0] = sinh_off;
-
-        let mut fact: f64 = 1.0;
         for i in 1..=max_p{
-            fact *= i as f64;
             if i & 0x1 == 0{
-                temp.coefs[i] = cosh_off/fact;
+                temp.coefs[i] = cosh_off*inverse_factorial(i);
             }else{
-                temp.coefs[i] = sinh_off/fact;
+                temp.coefs[i] = sinh_off*inverse_factorial(i);
             }
         }
 
-        temp.put_offset(offset);
         temp
     }
 
     pub fn generate_cosh(offset: f64, max_p: usize, from_x: bool) -> TsPoly{
-        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x};
+        let mut temp = TsPoly { coefs: vec![0.0; Self::DEFAULT_MAX_POW], max_pow: max_p, from_x, center: offset};
         let exp2x = f64::exp(2.0*offset);
         let sinh_off = (exp2x - 1.0)/exp2x;
         let cosh_off = (exp2x + 1.0)/exp2x;
 
         temp.coefs[0] = cosh_off;
-
-        let mut fact: f64 = 1.0;
         for i in 1..=max_p{
-            fact *= i as f64;
             if i & 0x1 == 0{
-                temp.coefs[i] = sinh_off/fact;
+                temp.coefs[i] = sinh_off*inverse_factorial(i);
             }else{
-                temp.coefs[i] = cosh_off/fact;
+                temp.coefs[i] = cosh_off*inverse_factorial(i);
             }
         }
 
-        temp.put_offset(offset);
         temp
     }
 
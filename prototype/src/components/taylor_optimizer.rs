@@ -2,9 +2,11 @@
 use crate::{
     unrecoverable_error,
     components::{
-        object_type_definitions::Func, 
+        object_type_definitions::{Func, Node},
+        symbolic_differentiation::{postfix_to_tree, tree_to_postfix},
         polynomials::TsPoly,
-        terminal_decoration::Color
+        terminal_decoration::Color,
+        compilation_options::PolynomialBasis
     }
 };
 
@@ -12,7 +14,7 @@ use std::f64::consts::PI;
 
 ///value is operand for the unary operator, but &mut sequence[*index-2] is the first operand for binary operation, while value is the second operand
 // #[inline(always)]
-fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &mut usize, poly_degree: usize){
+fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &mut usize, poly_degree: usize, basis_center: f64){
     match operation {
         Func::Sin => {
             sequence[*index-1] = Func::Const(value.sin());
@@ -124,6 +126,11 @@ fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &
             sequence.remove(*index);
             *index-=1;
         }
+        Func::Reduced(reduced) => {
+            sequence[*index-1] = Func::Const(reduced.exact(value));
+            sequence.remove(*index);
+            *index-=1;
+        }
         Func::Sqrt => {
             if value < 0.0 {
                 unrecoverable_error!(
@@ -138,7 +145,7 @@ fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &
         Func::Add => {
             match &mut sequence[*index-2] {
                 Func::X => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![value, 1.0], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![value, 1.0], true).recentered(basis_center));
                     sequence.remove(*index);
                     sequence.remove(*index-1);
                     *index-=2;
@@ -161,7 +168,7 @@ fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &
         Func::Sub => {
             match &mut sequence[*index-2] {
                 Func::X => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![-value, 1.0], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![-value, 1.0], true).recentered(basis_center));
                     sequence.remove(*index);
                     sequence.remove(*index-1);
                     *index-=2;
@@ -184,7 +191,7 @@ fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &
         Func::Mul => {
             match &mut sequence[*index-2] {
                 Func::X => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, value], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, value], true).recentered(basis_center));
                     sequence.remove(*index);
                     sequence.remove(*index-1);
                     *index-=2;
@@ -214,7 +221,7 @@ fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &
                             format!("{:?}", sequence)
                         );
                     }
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, 1.0/value], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, 1.0/value], true).recentered(basis_center));
                     sequence.remove(*index);
                     sequence.remove(*index-1);
                     *index-=2;
@@ -260,6 +267,7 @@ fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &
                         let mut temp = TsPoly::zero();
                         temp.max_pow = value as usize;
                         temp.coefs[temp.max_pow] = 1.0;
+                        temp = temp.recentered(basis_center);
                         temp.truncate(poly_degree);
                         sequence[*index-2] = Func::Poly(temp);
                         sequence.remove(*index);
@@ -282,65 +290,62 @@ fn const_handler(operation: Func, sequence: &mut Vec<Func>, value: f64, index: &
 
 ///X is the first operand, but &mut sequence[[*index-2]] is the first operand for binary operation, while X is the second operand
 // #[inline(always)]
-fn x_handler(operation: Func, sequence: &mut Vec<Func>, index: &mut usize, precision_center: f64, poly_degree: usize) {
+fn x_handler(operation: Func, sequence: &mut Vec<Func>, index: &mut usize, precision_center: f64, poly_degree: usize, basis_center: f64) {
     match operation {
         Func::Sin => {
-            sequence[*index-1] = Func::Poly(TsPoly::generate_sin(precision_center, poly_degree, true));
+            sequence[*index-1] = Func::Poly(series_in_basis(TsPoly::generate_sin(precision_center, poly_degree, true), basis_center));
             sequence.remove(*index);
             *index-=1;
         }
         Func::Cos => {
-            sequence[*index-1] = Func::Poly(TsPoly::generate_cos(precision_center, poly_degree, true));
+            sequence[*index-1] = Func::Poly(series_in_basis(TsPoly::generate_cos(precision_center, poly_degree, true), basis_center));
             sequence.remove(*index);
             *index-=1;
         }
-        Func::Tg => todo!("Need to impelment taylor generation for tg"),
-        Func::Ctg => todo!("Need to impelment taylor generation for ctg"),
+        Func::Tg | Func::Ctg | Func::Atg | Func::Actg | Func::Asin | Func::Acos => unreachable!("{:?} is collapsed into Func::Exact", operation),
         Func::Sinh => {
-            sequence[*index-1] = Func::Poly(TsPoly::generate_sinh(precision_center, poly_degree, true));
+            sequence[*index-1] = Func::Poly(series_in_basis(TsPoly::generate_sinh(precision_center, poly_degree, true), basis_center));
             sequence.remove(*index);
             *index-=1;
         }
         Func::Cosh => {
-            sequence[*index-1] = Func::Poly(TsPoly::generate_cosh(precision_center, poly_degree, true));
+            sequence[*index-1] = Func::Poly(series_in_basis(TsPoly::generate_cosh(precision_center, poly_degree, true), basis_center));
             sequence.remove(*index);
             *index-=1;
         }
         Func::Tgh => todo!("Need to impelment taylor generation for tgh"),
         Func::Ctgh => todo!("Need to impelment taylor generation for ctgh"),
-        Func::Atg => todo!("Need to impelment taylor generation for atg"),
-        Func::Actg => todo!("Need to impelment taylor generation for actg"),
-        Func::Asin => todo!("Need to impelment taylor generation for asin"),
-        Func::Acos => todo!("Need to impelment taylor generation for acos"),
         Func::Arsinh => todo!("Need to impelment taylor generation for asinh"),
         Func::Arcosh => todo!("Need to impelment taylor generation for acosh"),
         Func::Artgh => todo!("Need to impelment taylor generation for actg"),
         Func::Arctgh => todo!("Need to impelment taylor generation for actgh"),
         Func::Ln => {
-            sequence[*index-1] = Func::Poly(TsPoly::generate_ln(precision_center, poly_degree, true));
+            sequence[*index-1] = Func::Poly(series_in_basis(TsPoly::generate_ln(precision_center, poly_degree, true), basis_center));
             sequence.remove(*index);
             *index-=1;
         }
         Func::Exp => {
-            sequence[*index-1] = Func::Poly(TsPoly::generate_exp(precision_center, poly_degree, true));
+            sequence[*index-1] = Func::Poly(series_in_basis(TsPoly::generate_exp(precision_center, poly_degree, true), basis_center));
             sequence.remove(*index);
             *index-=1;
         }
         Func::Add => {
             match &mut sequence[*index-2] {
                 Func::X => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, 2.0], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, 2.0], true).recentered(basis_center));
                     sequence.remove(*index);
                     sequence.remove(*index-1);
                     *index-=2;
                 }
                 Func::Const(value) => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![*value, 1.0], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![*value, 1.0], true).recentered(basis_center));
                     sequence.remove(*index);
                     sequence.remove(*index-1);
                     *index-=2;
                 }
                 Func::Poly(poly) => {
+                    poly.center = basis_center;
+                    poly.coefs[0] += basis_center;
                     poly.coefs[1] += 1.0;
                     if poly.max_pow == 0 {
                         poly.max_pow = 1;
@@ -362,12 +367,14 @@ fn x_handler(operation: Func, sequence: &mut Vec<Func>, index: &mut usize, preci
                     *index-=2;
                 }
                 Func::Const(value) => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![*value, -1.0], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![*value, -1.0], true).recentered(basis_center));
                     sequence.remove(*index);
                     sequence.remove(*index-1);
                     *index-=2;
                 }
                 Func::Poly(poly) if poly.from_x => {
+                    poly.center = basis_center;
+                    poly.coefs[0] -= basis_center;
                     poly.coefs[1] -= 1.0;
                     if poly.max_pow == 1 && poly.coefs[1] == 0.0{
                         poly.max_pow = 0;
@@ -384,15 +391,15 @@ fn x_handler(operation: Func, sequence: &mut Vec<Func>, index: &mut usize, preci
         Func::Mul => {
             if match &mut sequence[*index-2] {
                 Func::X => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, 0.0, 1.0], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, 0.0, 1.0], true).recentered(basis_center));
                     true
                 },
                 Func::Const(value) => {
-                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, *value], true));
+                    sequence[*index-2] = Func::Poly(TsPoly::from_vec(vec![0.0, *value], true).recentered(basis_center));
                     true
                 },
                 Func::Poly(poly) if poly.from_x => {
-                    *poly *= TsPoly::from_vec(vec![0.0, 1.0], true);
+                    *poly *= TsPoly::variable(basis_center);
                     poly.truncate(poly_degree);
                     true  
                 },
@@ -429,29 +436,26 @@ fn x_handler(operation: Func, sequence: &mut Vec<Func>, index: &mut usize, preci
 
 ///Polynomial is the first operand, but &mut sequence[*index-2] is the first operand for binary operation, while polynomial is the second operand
 #[inline(always)]
-fn poly_handler(mut poly: TsPoly , operation: Func, sequence: &mut Vec<Func>, index: &mut usize, precision_center: f64, poly_degree: usize){
+fn poly_handler(mut poly: TsPoly , operation: Func, sequence: &mut Vec<Func>, index: &mut usize, precision_center: f64, poly_degree: usize, basis_center: f64){
     match operation {
-        Func::Sin => sequence[*index] = Func::Poly(TsPoly::generate_sin(precision_center, poly_degree, false)),
-        Func::Cos => sequence[*index] = Func::Poly(TsPoly::generate_cos(precision_center, poly_degree, false)),
-        Func::Tg => todo!("Need to impelment taylor generation for tg"),
-        Func::Ctg => todo!("Need to impelment taylor generation for ctg"),
-        Func::Sinh => sequence[*index] = Func::Poly(TsPoly::generate_sinh(precision_center, poly_degree, false)),
-        Func::Cosh => sequence[*index] = Func::Poly(TsPoly::generate_cosh(precision_center, poly_degree, false)),
+        Func::Sin => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_sin(precision_center, poly_degree, false), basis_center)),
+        Func::Cos => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_cos(precision_center, poly_degree, false), basis_center)),
+        Func::Tg | Func::Ctg | Func::Atg | Func::Actg | Func::Asin | Func::Acos => unreachable!("{:?} is collapsed into Func::Exact", operation),
+        Func::Sinh => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_sinh(precision_center, poly_degree, false), basis_center)),
+        Func::Cosh => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_cosh(precision_center, poly_degree, false), basis_center)),
         Func::Tgh => todo!("Need to impelment taylor generation for tgh"),
         Func::Ctgh => todo!("Need to impelment taylor generation for ctgh"),
-        Func::Atg => todo!("Need to impelment taylor generation for atg"),
-        Func::Actg => todo!("Need to impelment taylor generation for actg"),
-        Func::Asin => todo!("Need to impelment taylor generation for asin"),
-        Func::Acos => todo!("Need to impelment taylor generation for acos"),
         Func::Arsinh => todo!("Need to impelment taylor generation for asinh"),
         Func::Arcosh => todo!("Need to impelment taylor generation for acosh"),
         Func::Artgh => todo!("Need to impelment taylor generation for actg"),
         Func::Arctgh => todo!("Need to impelment taylor generation for actgh"),
-        Func::Ln => sequence[*index] = Func::Poly(TsPoly::generate_ln(precision_center, poly_degree, false)),
-        Func::Exp => sequence[*index] = Func::Poly(TsPoly::generate_exp(precision_center, poly_degree, false)),
+        Func::Ln => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_ln(precision_center, poly_degree, false), basis_center)),
+        Func::Exp => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_exp(precision_center, poly_degree, false), basis_center)),
         Func::Add => {
             match &sequence[*index-2] {
                 Func::X if poly.from_x => {
+                    poly.center = basis_center;
+                    poly.coefs[0] += basis_center;
                     poly.coefs[1] += 1.0;
                     if poly.coefs[1] != 0.0 && poly.max_pow == 0 {
                         poly.max_pow = 1;
@@ -483,7 +487,7 @@ fn poly_handler(mut poly: TsPoly , operation: Func, sequence: &mut Vec<Func>, in
         Func::Sub => {
             match &sequence[*index-2] {
                 Func::X if poly.from_x => {
-                    let mut temp = TsPoly::from_vec(vec![0.0, 1.0], true)-poly.clone();
+                    let mut temp = TsPoly::variable(basis_center)-poly.clone();
                     temp.truncate(poly_degree);
                     sequence[*index-2] = Func::Poly(temp);
                     sequence.remove(*index);
@@ -510,7 +514,7 @@ fn poly_handler(mut poly: TsPoly , operation: Func, sequence: &mut Vec<Func>, in
         Func::Mul => {
             match &mut sequence[*index-2] {
                 Func::X if poly.from_x => {
-                    let mut temp = TsPoly::from_vec(vec![0.0, 1.0], true)*poly.clone();
+                    let mut temp = TsPoly::variable(basis_center)*poly.clone();
                     temp.truncate(poly_degree);
                     sequence[*index-2] = Func::Poly(temp);
                     sequence.remove(*index);
@@ -538,44 +542,95 @@ fn poly_handler(mut poly: TsPoly , operation: Func, sequence: &mut Vec<Func>, in
     }
 }
 
-fn transition_op_handler(operation: Func, sequence: &mut [Func], index: &mut usize, precision_center: f64, poly_degree: usize) {
+fn transition_op_handler(operation: Func, sequence: &mut [Func], index: &mut usize, precision_center: f64, poly_degree: usize, basis_center: f64) {
     match operation {
-        Func::Sin => sequence[*index] = Func::Poly(TsPoly::generate_sin(precision_center, poly_degree, false)),
-        Func::Cos => sequence[*index] = Func::Poly(TsPoly::generate_cos(precision_center, poly_degree, false)),
-        Func::Tg => todo!("Need to impelment taylor generation for tg"),
-        Func::Ctg => todo!("Need to impelment taylor generation for ctg"),
-        Func::Sinh => sequence[*index] = Func::Poly(TsPoly::generate_sinh(precision_center, poly_degree, false)),
-        Func::Cosh => sequence[*index] = Func::Poly(TsPoly::generate_cosh(precision_center, poly_degree, false)),
+        Func::Sin => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_sin(precision_center, poly_degree, false), basis_center)),
+        Func::Cos => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_cos(precision_center, poly_degree, false), basis_center)),
+        Func::Tg | Func::Ctg | Func::Atg | Func::Actg | Func::Asin | Func::Acos => unreachable!("{:?} is collapsed into Func::Exact", operation),
+        Func::Sinh => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_sinh(precision_center, poly_degree, false), basis_center)),
+        Func::Cosh => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_cosh(precision_center, poly_degree, false), basis_center)),
         Func::Tgh => todo!("Need to impelment taylor generation for tgh"),
         Func::Ctgh => todo!("Need to impelment taylor generation for ctgh"),
-        Func::Atg => todo!("Need to impelment taylor generation for atg"),
-        Func::Actg => todo!("Need to impelment taylor generation for actg"),
-        Func::Asin => todo!("Need to impelment taylor generation for asin"),
-        Func::Acos => todo!("Need to impelment taylor generation for acos"),
         Func::Arsinh => todo!("Need to impelment taylor generation for asinh"),
         Func::Arcosh => todo!("Need to impelment taylor generation for acosh"),
         Func::Artgh => todo!("Need to impelment taylor generation for actg"),
         Func::Arctgh => todo!("Need to impelment taylor generation for actgh"),
-        Func::Ln => sequence[*index] = Func::Poly(TsPoly::generate_ln(precision_center, poly_degree, false)),
-        Func::Exp => sequence[*index] = Func::Poly(TsPoly::generate_exp(precision_center, poly_degree, false)),
-        Func::Sqrt => todo!("Need to implement handling of SQRT "),
+        Func::Ln => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_ln(precision_center, poly_degree, false), basis_center)),
+        Func::Exp => sequence[*index] = Func::Poly(series_in_basis(TsPoly::generate_exp(precision_center, poly_degree, false), basis_center)),
+        // kept as it is, the IR builder takes llvm.sqrt of the operand
+        Func::Sqrt => {},
         _ => {}
     }
 }
 
+/// Series are generated in the shifted basis, they are expanded into monomials unless the whole sequence is kept in the shifted basis.
+#[inline(always)]
+fn series_in_basis(series: TsPoly, basis_center: f64) -> TsPoly {
+    if series.center == basis_center { series } else { series.into_monomial_basis() }
+}
+
+/// Replaces every `exact(f(x))` subterm with a single `Func::Exact` element holding the postfix of f, which the optimizer leaves as it is.
+/// Markers nested inside an exact subterm are dropped, since the whole subterm is already exact.
+/// Calls of external functions, and of the functions without a Taylor generator (tg, ctg, atg, actg, asin, acos) on arguments
+/// depending on x, are collapsed as if they were written as `exact(name(f(x)))`, so the Taylor path supports what the exact path does.
+pub fn collapse_exact_subterms(sequence: &mut Vec<Func>) {
+    if sequence.iter().any(|elem| *elem == Func::Exact(Vec::new()) || is_evaluated_exactly(elem)) {
+        *sequence = tree_to_postfix(&collapse_node(&postfix_to_tree(sequence)));
+    }
+}
+
+/// Functions the optimizer has no series for, they are called through libm or the symbol resolver.
+fn is_evaluated_exactly(func: &Func) -> bool {
+    matches!(func, Func::External(_) | Func::Tg | Func::Ctg | Func::Atg | Func::Actg | Func::Asin | Func::Acos)
+}
+
+fn collapsed(node: &Node) -> Node {
+    let mut exact = tree_to_postfix(node);
+    exact.retain(|elem| *elem != Func::Exact(Vec::new()));
+    Node::from_func(Func::Exact(exact))
+}
+
+fn collapse_node(node: &Node) -> Node {
+    match (&node.op, &node.left) {
+        (Func::Exact(subterm), Some(argument)) if subterm.is_empty() => {
+            let mut exact = tree_to_postfix(argument);
+            exact.retain(|elem| *elem != Func::Exact(Vec::new()));
+            Node::from_func(Func::Exact(exact))
+        },
+        (Func::External(_), Some(_)) => collapsed(node),
+        // constant arguments are left to the constant folding
+        (op, Some(argument)) if is_evaluated_exactly(op) && tree_to_postfix(argument).contains(&Func::X) => collapsed(node),
+        _ => Node {
+            left: node.left.as_ref().map(|left| Box::new(collapse_node(left))),
+            right: node.right.as_ref().map(|right| Box::new(collapse_node(right))),
+            op: node.op.clone()
+        }
+    }
+}
+
+pub fn optimize_postfix_using_taylor(sequence: &mut Vec<Func>, precision_center: f64, poly_degree: usize){
+    optimize_postfix_using_taylor_in_basis(sequence, precision_center, poly_degree, PolynomialBasis::Monomial);
+}
+
 //TODO write detiled description for all component functions in this file
 //FIXME Optimize all these clone operations in handler functions
-pub fn optimize_postfix_using_taylor(sequence: &mut Vec<Func>, precision_center: f64, poly_degree: usize){
+pub fn optimize_postfix_using_taylor_in_basis(sequence: &mut Vec<Func>, precision_center: f64, poly_degree: usize, basis: PolynomialBasis){
+    collapse_exact_subterms(sequence);
+    let basis_center = match basis {
+        PolynomialBasis::Monomial => 0.0,
+        PolynomialBasis::Shifted => precision_center,
+    };
+
     let mut index: usize = 1;
     while index < sequence.len() {
         let current_elem = sequence[index-1].clone();
         let operation = sequence[index].clone();
 
         match current_elem {
-            Func::X => x_handler(operation, sequence, &mut index, precision_center, poly_degree),
-            Func::Const(value) => const_handler(operation, sequence, value, &mut index, poly_degree),
-            Func::Poly(poly) => poly_handler(poly, operation, sequence, &mut index, precision_center, poly_degree),
-            Func::Div | Func::Pow => transition_op_handler(operation, sequence, &mut index, precision_center, poly_degree),
+            Func::X => x_handler(operation, sequence, &mut index, precision_center, poly_degree, basis_center),
+            Func::Const(value) => const_handler(operation, sequence, value, &mut index, poly_degree, basis_center),
+            Func::Poly(poly) => poly_handler(poly, operation, sequence, &mut index, precision_center, poly_degree, basis_center),
+            Func::Div | Func::Pow => transition_op_handler(operation, sequence, &mut index, precision_center, poly_degree, basis_center),
             _ => {},
         }
         
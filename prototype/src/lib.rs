@@ -7,4 +7,27 @@ mod tests {
     mod topt_poly_const_x_ops_;
     mod topt_static_const_eval;
     mod topt_poly_from_postfix;
+    mod pade_approximants;
+    mod polynomial_evaluation;
+    mod shifted_basis;
+    mod range_reduction;
+    mod coefficient_tables;
+    mod ir_builder;
+    mod pass_pipeline;
+    mod float_mode;
+    mod target_cpu;
+    mod compiler;
+    mod batch_evaluation;
+    mod derivatives;
+    mod symbolic_differentiation;
+    mod expression_simplifier;
+    mod exact_subterms;
+    mod executable_memory;
+    mod compiled_function;
+    mod elf_object;
+    mod relocations;
+    mod symbol_resolver;
+    mod orc_jit;
+    mod library_export;
+    mod compilation_cache;
 }
\ No newline at end of file
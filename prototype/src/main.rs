@@ -4,7 +4,9 @@
 mod components;
 mod stages;
 
-use crate::stages::binary_compile::{generate_binary_from_ir, save_generated_binary_to_file, generate_function};
+use crate::stages::binary_compile::{generate_binary_from_ir, save_generated_binary_to_file};
+use crate::stages::compiler::Compiler;
+use crate::components::compilation_options::CompilationOptions;
 use crate::stages::ir_compile::generate_ir;
 use crate::stages::custom_ir_compile::generate_custom_function;
 //use std::time::Instant;
@@ -14,7 +16,8 @@ use std::arch::x86_64::_rdtsc;
 fn main(){
     let x: f64 = 1.0;
     
-    let fja = generate_function("sin(x)*exp(x)", 0.9, 8);
+    let compiler = Compiler::default();
+    let (fja, _) = compiler.compile_function("sin(x)*exp(x)", 0.9, 8, &CompilationOptions::default());
 
     let samples = 100_000_000;
     // let mut times: Vec<u64> = vec![0; samples];
@@ -22,7 +25,7 @@ fn main(){
 
     for _ in 0..samples {
         let ruler = unsafe{_rdtsc()};
-        let _temp_x = fja(x);
+        let _temp_x = fja.call(x);
         avg += (unsafe{_rdtsc()} - ruler) as f64;
     }
     
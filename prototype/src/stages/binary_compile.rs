@@ -3,199 +3,188 @@
 extern crate libc;
 
 use crate::{
-    unrecoverable_error,
-    components::terminal_decoration::Color,
-    components::taylor_optimizer::optimize_postfix_using_taylor,
-    stages::function_lexing::{lex_function, convert_infix_to_postfix},
-    stages::taylor_ir_compile::generate_ir_from_taylor_sequence,
-    stages::linking::{link_buffer, FunctionType},
+    components::compilation_options::{CompilationOptions, TargetCpu},
+    components::ir_builder::IrModule,
+    components::compilation_cache::CompilationCache,
+    stages::compiler::Compiler,
+    stages::linking::CompiledFunction,
 };
 use std::{
-    ffi::{CString, CStr},
-    ptr, ptr::NonNull
+    cell::RefCell,
+    ffi::CStr,
+    fmt,
+    rc::Rc
 };
 use llvm_sys::{
-    core::*,
-    prelude::*,
-    ir_reader::LLVMParseIRInContext,
-    target::*,
-    target_machine::*
+    core::LLVMDisposeMessage,
+    target_machine::{LLVMGetHostCPUName, LLVMGetHostCPUFeatures}
 };
 
-pub fn generate_binary_from_ir(llvm_ir: String) -> (Vec<u8>, usize){
-    let llvm_ir_len = llvm_ir.len();
-    let ir_c_string = CString::new(llvm_ir).unwrap();
-    let buffer_data: Vec<u8>;
-    let buffer_len: usize;
-
-    unsafe {
-        let context = LLVMContextCreate();
-        let buffer = LLVMCreateMemoryBufferWithMemoryRangeCopy(
-            ir_c_string.as_ptr(),
-            llvm_ir_len, 
-            CString::new("LLVM IR").unwrap().as_ptr()
-        );
-
-        let mut module: LLVMModuleRef = ptr::null_mut();
-        let mut error: *mut i8 = ptr::null_mut();
-        if LLVMParseIRInContext(context, buffer, &mut module, &mut error) != 0 {
-            let c_str = CStr::from_ptr(error); // Interpret as CStr
-            let temp_str = match c_str.to_str() {
-                Ok(rust_str) => rust_str,
-                Err(e) => &format!("Failed to convert to &str: {}", e),
-            };
+/// CPU and feature string a module is compiled for. Features are LLVM's comma separated "+feature"/"-feature" list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetDescription {
+    pub cpu: String,
+    pub features: String,
+}
 
-            unrecoverable_error!("LLVM Error | Error occured while lexing and parsing the IR string", temp_str);
-        }
+impl TargetDescription {
+    pub fn generic() -> Self {
+        TargetDescription { cpu: String::from("generic"), features: String::new() }
+    }
 
-        let result = LLVM_InitializeNativeTarget();
-        if result != 0 {
-            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native target.");
+    pub fn host() -> Self {
+        unsafe {
+            let cpu = LLVMGetHostCPUName();
+            let features = LLVMGetHostCPUFeatures();
+            let temp = TargetDescription {
+                cpu: CStr::from_ptr(cpu).to_string_lossy().into_owned(),
+                features: CStr::from_ptr(features).to_string_lossy().into_owned()
+            };
+            LLVMDisposeMessage(cpu);
+            LLVMDisposeMessage(features);
+            temp
         }
+    }
 
-        let result = LLVM_InitializeNativeAsmPrinter();
-        if result != 0 {
-            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native assembler printer.");
+    pub fn resolve(target_cpu: &TargetCpu) -> Self {
+        match target_cpu {
+            TargetCpu::Generic => Self::generic(),
+            TargetCpu::Host => Self::host(),
+            TargetCpu::Explicit { cpu, features } => TargetDescription { cpu: cpu.clone(), features: features.clone() },
         }
+    }
 
-        let triple = LLVMGetDefaultTargetTriple();
-        let mut target: LLVMTargetRef = ptr::null_mut();
+    /// Features explicitly enabled in the feature string, features implied by the CPU name aren't listed.
+    pub fn required_features(&self) -> Vec<&str> {
+        self.features.split(',').filter_map(|feature| feature.trim().strip_prefix('+')).collect()
+    }
 
-        if LLVMGetTargetFromTriple(triple, &mut target, &mut error) != 0 {
-            let c_str = CStr::from_ptr(error); // Interpret as CStr
-            let temp_str = match c_str.to_str() {
-                Ok(rust_str) => rust_str,
-                Err(e) => &format!("Failed to convert to &str: {}", e),
-            };
-            unrecoverable_error!("LLVM Error | Error getting target information", temp_str);
-        }
+    /// Checks that the machine running the program has every required feature, so the compiled code can't hit an illegal instruction.
+    pub fn runs_on_host(&self) -> bool {
+        self.missing_on_host().is_empty()
+    }
 
-        let target_machine = LLVMCreateTargetMachine(
-            target,
-            triple,
-            CString::new("generic").unwrap().as_ptr(),
-            CString::new("").unwrap().as_ptr(),
-            LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
-            LLVMRelocMode::LLVMRelocDefault,
-            LLVMCodeModel::LLVMCodeModelDefault
-        );
-
-        let mut memory_buffer: LLVMMemoryBufferRef = ptr::null_mut();
-        if LLVMTargetMachineEmitToMemoryBuffer(
-            target_machine,
-            module,
-            LLVMCodeGenFileType::LLVMObjectFile,
-            &mut error,
-            &mut memory_buffer
-        ) != 0{
-            let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
-            unrecoverable_error!("LLVM Error | Error emitting machine code to buffer", error_message);
-        }
+    /// Required features the machine running the program doesn't have.
+    pub fn missing_on_host(&self) -> Vec<String> {
+        let host = Self::host();
+        let host_features = host.required_features();
+        self.required_features().into_iter().filter(|feature| !host_features.contains(feature)).map(String::from).collect()
+    }
+}
 
-        let buffer_start = LLVMGetBufferStart(memory_buffer) as *mut u8;
-        buffer_len = LLVMGetBufferSize(memory_buffer);
+impl fmt::Display for TargetDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cpu: {}, features: [{}]", self.cpu, self.required_features().join(", "))
+    }
+}
 
-        buffer_data = std::slice::from_raw_parts_mut(buffer_start, buffer_len).to_vec();
+/// Instruction counts of a module before and after the mid-level optimization pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineReport {
+    pub pipeline: String,
+    pub instructions_before: usize,
+    pub instructions_after: usize,
+}
 
-        LLVMDisposeMemoryBuffer(memory_buffer);
-        LLVMDisposeModule(module);
-        LLVMDisposeTargetMachine(target_machine);
-        LLVMContextDispose(context);
-        LLVMDisposeMessage(triple);
+impl fmt::Display for PipelineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} | {} -> {} instructions", self.pipeline, self.instructions_before, self.instructions_after)
     }
-
-    (buffer_data, buffer_len)
 }
 
-pub fn save_generated_binary_to_file(llvm_ir: String, obj_file: String) {
-    let llvm_ir_len = llvm_ir.len();
-    let ir_c_string = CString::new(llvm_ir).unwrap();
-
-    unsafe {
-        let context = LLVMContextCreate();
-        let buffer = LLVMCreateMemoryBufferWithMemoryRangeCopy(
-            ir_c_string.as_ptr(),
-            llvm_ir_len, 
-            CString::new("LLVM IR").unwrap().as_ptr()
-        );
-
-        let mut module: LLVMModuleRef = ptr::null_mut();
-        let mut error: *mut i8 = ptr::null_mut();
-        if LLVMParseIRInContext(context, buffer, &mut module, &mut error) != 0 {
-            let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
-            unrecoverable_error!("LLVM Error | Error occured while lexing and parsing the IR string", error_message);
-        }
+/// Most compilers a thread keeps for the free functions, the least recently used one is dropped to make room for a new one.
+pub(crate) const SHARED_COMPILER_LIMIT: usize = 8;
 
-        let result = LLVM_InitializeNativeTarget();
-        if result != 0 {
-            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native target.");
-        }
+thread_local! {
+    /// Compilers behind the free functions below, one per target and cache, least recently used first.
+    /// Created the first time a thread needs them and kept until the thread exits or `SHARED_COMPILER_LIMIT` pushes them out.
+    static SHARED_COMPILERS: RefCell<Vec<Rc<Compiler>>> = const { RefCell::new(Vec::new()) };
+}
 
-        let result = LLVM_InitializeNativeAsmPrinter();
-        if result != 0 {
-            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native assembler printer.");
+/// Compiler of this thread for the target, so the free functions don't set up a target machine on every call.
+pub(crate) fn shared_compiler(target_description: &TargetDescription, cache: Option<CompilationCache>) -> Rc<Compiler> {
+    SHARED_COMPILERS.with(|compilers| {
+        let mut compilers = compilers.borrow_mut();
+        if let Some(position) = compilers.iter().position(|compiler| compiler.target() == target_description && compiler.cache() == cache.as_ref()) {
+            let compiler = compilers.remove(position);
+            compilers.push(Rc::clone(&compiler));
+            return compiler;
         }
+        if compilers.len() == SHARED_COMPILER_LIMIT {
+            compilers.remove(0);
+        }
+        let compiler = Compiler::for_target(target_description.clone());
+        let compiler = Rc::new(match cache {
+            Some(cache) => compiler.with_cache(cache),
+            None => compiler
+        });
+        compilers.push(Rc::clone(&compiler));
+        compiler
+    })
+}
 
-        let triple = LLVMGetDefaultTargetTriple();
-        let mut target: LLVMTargetRef = ptr::null_mut();
+pub(crate) fn default_compiler() -> Rc<Compiler> {
+    shared_compiler(&TargetDescription::generic(), None)
+}
 
-        if LLVMGetTargetFromTriple(triple, &mut target, &mut error) != 0 {
-            let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
-            unrecoverable_error!("LLVM Error | Error getting target information", error_message);
-        }
+/// Runs a new pass manager pipeline (eg. "default<O3>") over the module in place.
+pub fn optimize_module(module: &IrModule, pipeline: &str) -> PipelineReport {
+    default_compiler().optimize(module, pipeline)
+}
 
-        let target_machine = LLVMCreateTargetMachine(
-            target,
-            triple,
-            CString::new("generic").unwrap().as_ptr(),
-            CString::new("").unwrap().as_ptr(),
-            LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
-            LLVMRelocMode::LLVMRelocDefault,
-            LLVMCodeModel::LLVMCodeModelDefault
-        );
-
-        if LLVMTargetMachineEmitToFile(
-            target_machine,
-            module,
-            CString::new(obj_file).unwrap().as_ptr(),
-            LLVMCodeGenFileType::LLVMObjectFile,
-            &mut error
-        ) != 0{
-            let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
-            unrecoverable_error!("LLVM Error | Error emitting machine code to buffer", error_message);
-        }
+/// Same as `optimize_module`, cost models of the passes (eg. vectorizers) are the ones of the described CPU.
+pub fn optimize_module_for_target(module: &IrModule, pipeline: &str, target_description: &TargetDescription) -> PipelineReport {
+    shared_compiler(target_description, None).optimize(module, pipeline)
+}
 
-        LLVMDisposeModule(module);
-        LLVMDisposeTargetMachine(target_machine);
-        LLVMContextDispose(context);
-        LLVMDisposeMessage(triple);
-    }
+pub fn generate_binary_from_module(module: &IrModule) -> (Vec<u8>, usize){
+    default_compiler().emit_object(module)
 }
 
-extern "C" {
-    static __code_buffer: u8;  // Start of the reserved block, size is 16KB
+pub fn generate_binary_for_target(module: &IrModule, target_description: &TargetDescription) -> (Vec<u8>, usize){
+    shared_compiler(target_description, None).emit_object(module)
 }
 
-pub fn generate_function(function: &str, precision_center:f64, max_power: usize) -> FunctionType{
-    let mut sequence = lex_function(function);
-    convert_infix_to_postfix(&mut sequence);
-    optimize_postfix_using_taylor(&mut sequence, precision_center, max_power);
-    let ir_code = generate_ir_from_taylor_sequence(&sequence);
+/// Compiles hand written textual IR.
+pub fn generate_binary_from_ir(llvm_ir: String) -> (Vec<u8>, usize){
+    let compiler = default_compiler();
+    compiler.emit_object(&compiler.parse_module(&llvm_ir))
+}
 
-    //println!("{}", ir_code);
+pub fn save_module_to_file(module: &IrModule, obj_file: String) {
+    default_compiler().emit_object_file(module, &obj_file);
+}
 
-    let (mut buffer_data, buffer_len) = generate_binary_from_ir(ir_code);
+/// Relocatable object defining `fja`, `library_export` writes shared libraries and archives with a C header.
+pub fn save_generated_binary_to_file(llvm_ir: String, obj_file: String) {
+    let compiler = default_compiler();
+    compiler.emit_object_file(&compiler.parse_module(&llvm_ir), &obj_file);
+}
 
-    unsafe {
-        let object_space: *const u8 = &__code_buffer;
+/// Compiles with the compiler this thread shares between the free functions, use a `Compiler` directly to choose its lifetime.
+/// The returned function owns its code, which is unmapped when the function is dropped.
+pub fn generate_function(function: &str, precision_center:f64, max_power: usize) -> CompiledFunction{
+    generate_function_with_options(function, precision_center, max_power, &CompilationOptions::default())
+}
 
-        let temp = link_buffer(
-            &mut buffer_data,
-            NonNull::new_unchecked(object_space as *mut u8)
-        );
+pub fn generate_function_with_options(function: &str, precision_center:f64, max_power: usize, options: &CompilationOptions) -> CompiledFunction{
+    generate_function_with_report(function, precision_center, max_power, options).0
+}
 
-        std::ptr::copy_nonoverlapping(buffer_data.as_ptr(), object_space as *mut u8, buffer_len);
+/// What was done while compiling a function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompilationReport {
+    /// Instruction counts, if an optimization pipeline was run.
+    pub pipeline: Option<PipelineReport>,
+    /// CPU and features the machine code was generated for, the code can only run where they are available.
+    pub target: TargetDescription,
+    /// The object file was loaded from the compilation cache, no optimization pipeline ran.
+    pub cached: bool,
+}
 
-        temp
-    }
+/// Same as `generate_function_with_options`, also reports the optimization results and the features the code requires.
+/// If `FJA_CACHE_DIR` is set, the object file is looked up in the compilation cache there before anything is compiled.
+pub fn generate_function_with_report(function: &str, precision_center:f64, max_power: usize, options: &CompilationOptions) -> (CompiledFunction, CompilationReport){
+    let compiler = shared_compiler(&TargetDescription::resolve(&options.target_cpu), CompilationCache::from_environment());
+    compiler.compile_function(function, precision_center, max_power, options)
 }
@@ -0,0 +1,380 @@
+#![allow(dead_code)]
+use crate::{
+    unrecoverable_error,
+    components::{
+        terminal_decoration::Color,
+        taylor_optimizer::{optimize_postfix_using_taylor_in_basis, collapse_exact_subterms},
+        pade_approximants::apply_pade_approximation,
+        range_reduction::apply_range_reduction,
+        compilation_options::{CompilationOptions, ApproximationMode, TargetCpu, ExecutionBackend},
+        ir_builder::{IrContext, IrModule},
+        executable_memory::ExecutableRegion,
+        orc_jit::OrcJit,
+        compilation_cache::{CompilationCache, CacheKey},
+        symbolic_differentiation::expand_derivatives
+    },
+    stages::{
+        function_lexing::{lex_function, convert_infix_to_postfix},
+        taylor_ir_compile::{build_taylor_function, build_taylor_jet_function},
+        ir_compile::build_ir_function,
+        binary_compile::{TargetDescription, PipelineReport, CompilationReport},
+        linking::{link_object, linked_size, function_at, batch_function_at, jet_function_at, CompiledFunction, BatchFunction, JetFunction, LoadedCode}
+    }
+};
+use std::{
+    ffi::{CString, CStr},
+    ptr,
+    rc::Rc,
+    sync::Once
+};
+use llvm_sys::{
+    core::*,
+    prelude::*,
+    target::*,
+    target_machine::*,
+    error::{LLVMGetErrorMessage, LLVMDisposeErrorMessage},
+    transforms::pass_builder::{LLVMRunPasses, LLVMCreatePassBuilderOptions, LLVMDisposePassBuilderOptions}
+};
+
+fn create_target_machine(target: &TargetDescription, relocation_model: LLVMRelocMode) -> LLVMTargetMachineRef {
+    unsafe {
+        let triple = LLVMGetDefaultTargetTriple();
+        let mut llvm_target: LLVMTargetRef = ptr::null_mut();
+        let mut error: *mut i8 = ptr::null_mut();
+
+        if LLVMGetTargetFromTriple(triple, &mut llvm_target, &mut error) != 0 {
+            let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
+            unrecoverable_error!("LLVM Error | Error getting target information", error_message);
+        }
+
+        let cpu = CString::new(target.cpu.as_str()).unwrap();
+        let features = CString::new(target.features.as_str()).unwrap();
+        let target_machine = LLVMCreateTargetMachine(
+            llvm_target,
+            triple,
+            cpu.as_ptr(),
+            features.as_ptr(),
+            LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
+            relocation_model,
+            LLVMCodeModel::LLVMCodeModelDefault
+        );
+        LLVMDisposeMessage(triple);
+        target_machine
+    }
+}
+
+static NATIVE_TARGET_INIT: Once = Once::new();
+
+/// Inlines `fja` into the batch loop, then vectorizes the loop with the vector width of the target CPU.
+const BATCH_PIPELINE: &str = "always-inline,function(loop-vectorize)";
+
+fn initialize_native_target() {
+    NATIVE_TARGET_INIT.call_once(|| unsafe {
+        if LLVM_InitializeNativeTarget() != 0 {
+            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native target.");
+        }
+        if LLVM_InitializeNativeAsmPrinter() != 0 {
+            unrecoverable_error!("LLVM Error | Initialization", "Failed to initialize native assembler printer.");
+        }
+    });
+}
+
+/// Owns the LLVM context and the target machine, so a sequence of compilations pays for their setup only once.
+/// Every module the compiler creates lives in its context, modules created elsewhere can still be optimized and emitted.
+/// Loaded functions own the memory of their code, so they can outlive the compiler.
+/// The object files are linked by `link_buffer` unless the compiler is switched to the ORC JIT with `with_backend`.
+/// With a cache set by `with_cache`, Taylor approximated formulas compiled before are loaded from their stored object files.
+pub struct Compiler {
+    context: Rc<IrContext>,
+    target_machine: LLVMTargetMachineRef,
+    target: TargetDescription,
+    backend: ExecutionBackend,
+    cache: Option<CompilationCache>,
+}
+
+impl Compiler {
+    pub fn new(target_cpu: &TargetCpu) -> Self {
+        Self::for_target(TargetDescription::resolve(target_cpu))
+    }
+
+    pub fn for_target(target: TargetDescription) -> Self {
+        initialize_native_target();
+        let target_machine = create_target_machine(&target, LLVMRelocMode::LLVMRelocDefault);
+        Compiler { context: IrContext::new(), target_machine, target, backend: ExecutionBackend::Linker, cache: None }
+    }
+
+    pub fn with_backend(mut self, backend: ExecutionBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn backend(&self) -> ExecutionBackend {
+        self.backend
+    }
+
+    pub fn with_cache(mut self, cache: CompilationCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn cache(&self) -> Option<&CompilationCache> {
+        self.cache.as_ref()
+    }
+
+    pub fn target(&self) -> &TargetDescription {
+        &self.target
+    }
+
+    pub fn create_module(&self, name: &str) -> IrModule {
+        IrModule::new_in(&self.context, name)
+    }
+
+    pub fn parse_module(&self, llvm_ir: &str) -> IrModule {
+        IrModule::parse_in(&self.context, llvm_ir)
+    }
+
+    fn verify(&self, module: &IrModule) {
+        if let Err(error_message) = module.verify() {
+            unrecoverable_error!("LLVM Error | Generated module is malformed", format!("{}\n{}", error_message, module.dump()));
+        }
+    }
+
+    /// Runs a new pass manager pipeline (eg. "default<O3>") over the module in place, cost models are the ones of the target CPU.
+    pub fn optimize(&self, module: &IrModule, pipeline: &str) -> PipelineReport {
+        self.verify(module);
+        let instructions_before = module.instruction_count();
+        self.run_passes(module, pipeline);
+
+        PipelineReport { pipeline: pipeline.to_owned(), instructions_before, instructions_after: module.instruction_count() }
+    }
+
+    fn run_passes(&self, module: &IrModule, pipeline: &str) {
+        let passes = CString::new(pipeline).unwrap();
+
+        unsafe {
+            let pass_options = LLVMCreatePassBuilderOptions();
+            let error = LLVMRunPasses(module.as_raw(), passes.as_ptr(), self.target_machine, pass_options);
+            LLVMDisposePassBuilderOptions(pass_options);
+
+            if !error.is_null() {
+                let message = LLVMGetErrorMessage(error);
+                let error_message = CStr::from_ptr(message).to_string_lossy().into_owned();
+                LLVMDisposeErrorMessage(message);
+                unrecoverable_error!("LLVM Error | Error running the optimization pipeline", format!("'{}' => {}", pipeline, error_message));
+            }
+        }
+    }
+
+    /// Adds `fja_batch` next to `fja` and vectorizes its loop. Only the batch loop is changed, `fja` itself stays as built.
+    fn add_batch_function(&self, module: &IrModule) {
+        module.add_batch_function("fja", "fja_batch");
+        self.verify(module);
+        self.run_passes(module, BATCH_PIPELINE);
+    }
+
+    /// Emits the module as an ELF object file into memory.
+    pub fn emit_object(&self, module: &IrModule) -> (Vec<u8>, usize) {
+        self.emit_object_with(self.target_machine, module)
+    }
+
+    /// Object file of position independent code, which can be linked into shared libraries and PIE executables.
+    pub fn emit_position_independent_object(&self, module: &IrModule) -> Vec<u8> {
+        let target_machine = create_target_machine(&self.target, LLVMRelocMode::LLVMRelocPIC);
+        let (object_file, _) = self.emit_object_with(target_machine, module);
+        unsafe { LLVMDisposeTargetMachine(target_machine); }
+        object_file
+    }
+
+    fn emit_object_with(&self, target_machine: LLVMTargetMachineRef, module: &IrModule) -> (Vec<u8>, usize) {
+        self.verify(module);
+
+        unsafe {
+            let mut error: *mut i8 = ptr::null_mut();
+            let mut memory_buffer: LLVMMemoryBufferRef = ptr::null_mut();
+            if LLVMTargetMachineEmitToMemoryBuffer(
+                target_machine,
+                module.as_raw(),
+                LLVMCodeGenFileType::LLVMObjectFile,
+                &mut error,
+                &mut memory_buffer
+            ) != 0{
+                let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
+                unrecoverable_error!("LLVM Error | Error emitting machine code to buffer", error_message);
+            }
+
+            let buffer_start = LLVMGetBufferStart(memory_buffer) as *const u8;
+            let buffer_len = LLVMGetBufferSize(memory_buffer);
+            let buffer_data = std::slice::from_raw_parts(buffer_start, buffer_len).to_vec();
+            LLVMDisposeMemoryBuffer(memory_buffer);
+
+            (buffer_data, buffer_len)
+        }
+    }
+
+    pub fn emit_object_file(&self, module: &IrModule, obj_file: &str) {
+        self.verify(module);
+
+        unsafe {
+            let mut error: *mut i8 = ptr::null_mut();
+            let obj_file = CString::new(obj_file).unwrap();
+
+            if LLVMTargetMachineEmitToFile(
+                self.target_machine,
+                module.as_raw(),
+                obj_file.as_ptr(),
+                LLVMCodeGenFileType::LLVMObjectFile,
+                &mut error
+            ) != 0{
+                let error_message = CStr::from_ptr(error).to_string_lossy().into_owned();
+                unrecoverable_error!("LLVM Error | Error emitting machine code to file", error_message);
+            }
+        }
+    }
+
+    /// Links the object file into new executable memory owned by the returned function.
+    pub fn load(&self, binary: (Vec<u8>, usize)) -> CompiledFunction {
+        let code_size = binary.1;
+        let (function, code) = self.load_with(binary, "fja", function_at);
+        CompiledFunction::new(function, code, code_size, self.target.clone())
+    }
+
+    /// Same as `load`, returns `fja_batch` of the object file.
+    pub fn load_batch(&self, binary: (Vec<u8>, usize)) -> BatchFunction {
+        let (function, code) = self.load_with(binary, "fja_batch", batch_function_at);
+        function.owning(code)
+    }
+
+    /// Same as `load`, returns `fja_jet` of the object file.
+    pub fn load_jet(&self, binary: (Vec<u8>, usize), order: usize) -> JetFunction {
+        let (function, code) = self.load_with(binary, "fja_jet", |entry| jet_function_at(entry, order));
+        function.owning(code)
+    }
+
+    /// With the linker, the object is linked for the address of a fresh RW region, copied there, then the region is made RX.
+    /// The GOT and trampolines of the object are placed right after it, in the same region.
+    /// Code for features this machine doesn't have is refused, objects for other CPUs can still be emitted and exported.
+    fn load_with<T>(&self, (mut buffer_data, buffer_len): (Vec<u8>, usize), entry_symbol: &str, function_at: impl FnOnce(*mut u8) -> T) -> (T, LoadedCode) {
+        let missing = self.target.missing_on_host();
+        if !missing.is_empty() {
+            unrecoverable_error!("Linker Error | The code can't run on this machine", format!("{} needs {}, which the CPU doesn't have", self.target, missing.join(", ")));
+        }
+        match self.backend {
+            ExecutionBackend::Linker => {
+                let image_len = linked_size(&buffer_data[..buffer_len]).max(buffer_len);
+                buffer_data.resize(image_len, 0);
+                let mut region = ExecutableRegion::allocate(image_len);
+                let entry = link_object(&mut buffer_data, region.as_ptr(), entry_symbol);
+                region.write(0, &buffer_data);
+                region.make_executable();
+                (function_at(entry), LoadedCode::Region(region))
+            },
+            ExecutionBackend::OrcJit => {
+                let jit = OrcJit::load_object(&buffer_data[..buffer_len]);
+                (function_at(jit.lookup(entry_symbol)), LoadedCode::Jit(jit))
+            }
+        }
+    }
+
+    /// Builds the module of the Taylor approximated function, runs the optimization pipeline of the options if there is one.
+    /// The code is generated for the target of the compiler, `options.target_cpu` is only read by `Compiler::new`.
+    pub fn build_taylor_module(&self, function: &str, precision_center: f64, max_power: usize, options: &CompilationOptions) -> (IrModule, CompilationReport) {
+        let mut sequence = lex_function(function);
+        convert_infix_to_postfix(&mut sequence);
+        expand_derivatives(&mut sequence);
+        collapse_exact_subterms(&mut sequence);
+        if options.range_reduction {
+            apply_range_reduction(&mut sequence, max_power);
+        }
+        optimize_postfix_using_taylor_in_basis(&mut sequence, precision_center, max_power, options.basis);
+        if let ApproximationMode::Pade { denominator_degree } = options.approximation {
+            apply_pade_approximation(&mut sequence, precision_center, max_power, denominator_degree);
+        }
+
+        let module = self.create_module("taylor");
+        build_taylor_function(&module, &sequence, options);
+        if options.derivatives > 0 {
+            build_taylor_jet_function(&module, &sequence, options);
+        }
+        let module = match options.float_mode.instruction_flags() {
+            Some(flags) => module.with_fast_math_flags(flags),
+            None => module
+        };
+        module.set_target_attributes(&self.target.cpu, &self.target.features);
+        self.add_batch_function(&module);
+        let pipeline = options.pass_pipeline.description().map(|pipeline| self.optimize(&module, &pipeline));
+
+        (module, CompilationReport { pipeline, target: self.target.clone(), cached: false })
+    }
+
+    /// Object file of `build_taylor_module`, taken from the cache on a hit and stored in it on a miss.
+    pub fn emit_taylor_object(&self, function: &str, precision_center: f64, max_power: usize, options: &CompilationOptions) -> ((Vec<u8>, usize), CompilationReport) {
+        let key = self.cache.as_ref().map(|_| CacheKey::new(function, precision_center, max_power, &self.target, options));
+        if let (Some(cache), Some(key)) = (&self.cache, &key) {
+            if let Some(object_file) = cache.lookup(key) {
+                let object_size = object_file.len();
+                return ((object_file, object_size), CompilationReport { pipeline: None, target: self.target.clone(), cached: true });
+            }
+        }
+
+        let (module, report) = self.build_taylor_module(function, precision_center, max_power, options);
+        let binary = self.emit_object(&module);
+        if let (Some(cache), Some(key)) = (&self.cache, &key) {
+            cache.store(key, &binary.0);
+        }
+        (binary, report)
+    }
+
+    pub fn compile_function(&self, function: &str, precision_center: f64, max_power: usize, options: &CompilationOptions) -> (CompiledFunction, CompilationReport) {
+        let (binary, report) = self.emit_taylor_object(function, precision_center, max_power, options);
+        (self.load(binary).with_source(function), report)
+    }
+
+    /// Same as `compile_function`, returns the vectorized `fja_batch` instead of `fja`.
+    pub fn compile_batch_function(&self, function: &str, precision_center: f64, max_power: usize, options: &CompilationOptions) -> (BatchFunction, CompilationReport) {
+        let (binary, report) = self.emit_taylor_object(function, precision_center, max_power, options);
+        (self.load_batch(binary), report)
+    }
+
+    /// Same as `compile_function`, returns `fja_jet` with the first `options.derivatives` derivatives, which have to be at least 1.
+    pub fn compile_jet_function(&self, function: &str, precision_center: f64, max_power: usize, options: &CompilationOptions) -> (JetFunction, CompilationReport) {
+        if options.derivatives == 0 {
+            unrecoverable_error!("Differentiation error | No derivatives requested", "The jet function is only emitted when options.derivatives is at least 1");
+        }
+        let (binary, report) = self.emit_taylor_object(function, precision_center, max_power, options);
+        (self.load_jet(binary, options.derivatives), report)
+    }
+
+    pub fn build_exact_module(&self, function: &str) -> IrModule {
+        let module = self.create_module("postfix");
+        build_ir_function(&module, function);
+        module.set_target_attributes(&self.target.cpu, &self.target.features);
+        self.add_batch_function(&module);
+        module
+    }
+
+    /// Function evaluated exactly, through calls to the external (libm) implementations.
+    pub fn compile_exact_function(&self, function: &str) -> CompiledFunction {
+        self.load(self.emit_object(&self.build_exact_module(function))).with_source(function)
+    }
+
+    pub fn compile_exact_batch_function(&self, function: &str) -> BatchFunction {
+        self.load_batch(self.emit_object(&self.build_exact_module(function)))
+    }
+
+    /// Hand written IR defining `fja`.
+    pub fn compile_ir(&self, llvm_ir: &str) -> CompiledFunction {
+        self.load(self.emit_object(&self.parse_module(llvm_ir))).with_source(llvm_ir)
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::for_target(TargetDescription::generic())
+    }
+}
+
+impl Drop for Compiler {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeTargetMachine(self.target_machine); }
+    }
+}
@@ -1,59 +1,41 @@
 #![allow(dead_code, unused_imports)]
 use crate::components::object_type_definitions::*;
 use crate::components::terminal_decoration::Color;
+use crate::components::symbol_resolver::global_resolver;
 use crate::unrecoverable_error;
 use std::process::exit;
 
+/// Every token the lexer recognizes by itself.
+const TOKENS: [(&str, Func); 23] = [
+    ("*", Func::Mul), ("/", Func::Div), ("+", Func::Add), ("-", Func::Sub), ("^", Func::Pow),
+    ("x", Func::X), ("(", Func::Ob), (")", Func::Cb),
+    ("ln", Func::Ln), ("e^", Func::Exp), ("tg", Func::Tg),
+    ("sin", Func::Sin), ("cos", Func::Cos), ("ctg", Func::Ctg), ("atg", Func::Atg), ("exp", Func::Exp),
+    ("sqrt", Func::Sqrt), ("asin", Func::Asin), ("acos", Func::Acos), ("atan", Func::Atg), ("actg", Func::Actg), ("diff", Func::Diff),
+    ("exact", Func::Exact(Vec::new()))
+];
+
 fn try_lexing(chunk: &str, function: &str) -> Option<Func> {
-    match chunk.len() {
-        1 => {
-            match chunk{
-                "*" => Some(Func::Mul),
-                "/" => Some(Func::Div),
-                "+" => Some(Func::Add),
-                "-" => Some(Func::Sub),
-                "^" => Some(Func::Pow),
-                "x" => Some(Func::X),
-                "(" => Some(Func::Ob),
-                ")" => Some(Func::Cb),
-                _ => None
-            }
-        }
-        2 => {
-            match chunk{
-                "ln" => Some(Func::Ln),
-                "e^" => Some(Func::Exp),
-                "tg" => Some(Func::Tg),
-                _ => None
-            }
-        }
-        3 => {
-            match chunk{
-                "sin" => Some(Func::Sin),
-                "cos" => Some(Func::Cos),
-                "ctg" => Some(Func::Ctg),
-                "atg" => Some(Func::Atg),
-                "exp" => Some(Func::Exp),
-                _ => None
-            }
-        }
-        4 => {
-            match chunk{
-                "sqrt" => Some(Func::Sqrt),
-                "asin" => Some(Func::Asin),
-                "acos" => Some(Func::Acos),
-                "atan" => Some(Func::Atg),
-                "actg" => Some(Func::Actg),
-                _ => None
-            }
-        }
-        _ => {
-            unrecoverable_error!(
-                "Lexing Error | Highlighted part of a function string is unknown/unsupported function",
-                &function.replace(chunk, &format!("{}{} {} {}{}", Color::CBlack, Color::BYellow,&chunk, Color::CYellow, Color::BBlack))
-            );
-        }
+    if TOKENS.iter().all(|(token, _)| token.len() < chunk.len()) {
+        unrecoverable_error!(
+            "Lexing Error | Highlighted part of a function string is unknown/unsupported function",
+            &function.replace(chunk, &format!("{}{} {} {}{}", Color::CBlack, Color::BYellow,&chunk, Color::CYellow, Color::BBlack))
+        );
     }
+    TOKENS.iter().find(|(token, _)| *token == chunk).map(|(_, func)| func.clone())
+}
+
+/// Names the lexer recognizes by itself (`e` of `e^`), a function registered under one of them couldn't be called from formulas.
+pub fn is_builtin_name(name: &str) -> bool {
+    TOKENS.iter().any(|(token, _)| token.trim_end_matches('^') == name)
+}
+
+/// Name of a one argument function of the symbol resolver called at the start of `rest`, eg. `bessel_j0` of `bessel_j0(x)+1`.
+fn external_function_name(rest: &str) -> Option<&str> {
+    let end = rest.find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+    let name = &rest[..end];
+    let is_call = end > 0 && rest[end..].starts_with('(') && !is_builtin_name(name);
+    (is_call && global_resolver().unary_function(name).is_some()).then_some(name)
 }
 
 pub fn lex_function(function: &str) -> Vec<Func> {
@@ -63,6 +45,14 @@ pub fn lex_function(function: &str) -> Vec<Func> {
     let mut buffer: usize = 1;
     
     while i+buffer<function.len()+1{
+        if buffer == 1 {
+            if let Some(name) = external_function_name(&function[i..]) {
+                tokens.push(Func::External(name.to_string()));
+                i += name.len();
+                continue;
+            }
+        }
+
         let mut temp = i;
 
         while let Some(ch) = function.chars().nth(temp) {
@@ -0,0 +1,194 @@
+#![allow(unused_imports)]
+use crate::{
+    unrecoverable_error,
+    components::{
+        terminal_decoration::Color,
+        compilation_options::CompilationOptions,
+        elf_object::ElfObject,
+        ir_builder::IrModule,
+        shared_object::write_shared_object,
+        symbol_resolver::{check_c_identifier, global_resolver, libm_symbol}
+    },
+    stages::compiler::Compiler
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{exit, Command}
+};
+
+/// What `export_module` writes next to the C header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// Relocatable `.o`, linked into the program like any other object file.
+    Object,
+    /// `.so` linked by the system C compiler driver (`$CC`, `cc`, `gcc` or `clang`), or by `write_shared_object` if none is installed.
+    SharedLibrary,
+    /// `.a` holding the object file, for static linking with `-l` or by path.
+    StaticArchive,
+}
+
+/// Every export is linked against libm, the generated code calls it for the functions it evaluates exactly.
+const LIBM: &str = "libm.so.6";
+
+/// Compiler driver used to link shared libraries, `$CC` takes precedence over the ones found on the PATH.
+fn system_linker() -> Option<String> {
+    std::env::var("CC").ok().into_iter()
+        .chain(["cc", "gcc", "clang"].map(String::from))
+        .find(|linker| Command::new(linker).arg("--version").output().is_ok_and(|output| output.status.success()))
+}
+
+/// Name of the object file in the archive, short enough for the 16 byte name field whatever the symbol name.
+const ARCHIVE_MEMBER_NAME: &str = "fja.o/";
+
+/// `ar` archive with a single member and the GNU symbol index, which `ld` needs to pick the member up.
+fn static_archive(object_file: &[u8]) -> Vec<u8> {
+    let object = ElfObject::parse(object_file);
+    let symbols: Vec<&str> = object.symbols.iter()
+        .filter(|symbol| symbol.is_defined() && symbol.binding != 0 && !symbol.name.is_empty())
+        .map(|symbol| symbol.name)
+        .collect();
+
+    let member_header = |name: &str, size: usize| format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, 0, 0, 0, 644, size).into_bytes();
+
+    let mut index = (symbols.len() as u32).to_be_bytes().to_vec();
+    let index_size = 4 + 4*symbols.len() + symbols.iter().map(|name| name.len() + 1).sum::<usize>();
+    let member_offset = 8 + 60 + index_size.next_multiple_of(2);
+    for _ in &symbols {
+        index.extend((member_offset as u32).to_be_bytes());
+    }
+    for name in &symbols {
+        index.extend(name.as_bytes());
+        index.push(0);
+    }
+
+    let mut archive = b"!<arch>\n".to_vec();
+    for (name, contents) in [("/", &index[..]), (ARCHIVE_MEMBER_NAME, object_file)] {
+        archive.extend(member_header(name, contents.len()));
+        archive.extend(contents);
+        if !archive.len().is_multiple_of(2) {
+            archive.push(b'\n');
+        }
+    }
+    archive
+}
+
+/// C declarations of the exported functions, plus the external functions the program has to provide.
+fn c_header(symbol_name: &str, module: &IrModule, source: Option<&str>, jet_order: Option<usize>, externals: &[String]) -> String {
+    let guard = format!("{}_H", symbol_name.to_ascii_uppercase());
+    let mut header = String::new();
+    if let Some(source) = source {
+        header += &format!("/* f(x) = {} */\n", source.replace("*/", "* /"));
+    }
+    header += &format!("#ifndef {}\n#define {}\n\n#include <stddef.h>\n\n#ifdef __cplusplus\nextern \"C\" {{\n#endif\n\n", guard, guard);
+
+    header += &format!("double {}(double x);\n", symbol_name);
+    if module.has_function(&format!("{}_batch", symbol_name)) {
+        // the IR marks both pointers noalias, C callers have to promise the same
+        let restrict = format!("{}_RESTRICT", symbol_name.to_ascii_uppercase());
+        header += &format!("#ifdef __cplusplus\n#define {} __restrict\n#else\n#define {} restrict\n#endif\n", restrict, restrict);
+        header += &format!("/* ys[i] = {}(xs[i]) for i < n, xs and ys must not overlap (no in-place calls) */\n", symbol_name);
+        header += &format!("void {}_batch(const double* {} xs, double* {} ys, size_t n);\n", symbol_name, restrict, restrict);
+    }
+    if module.has_function(&format!("{}_jet", symbol_name)) {
+        match jet_order {
+            Some(order) => header += &format!("/* out[k] = f^(k)(x) for k = 0, ..., {}, out has to hold {} doubles */\n", order, order + 1),
+            None => header += "/* out[k] = f^(k)(x) */\n"
+        }
+        header += &format!("void {}_jet(double x, double* out);\n", symbol_name);
+    }
+
+    if !externals.is_empty() {
+        header += "\n/* Called by the functions above, the program has to define them */\n";
+        let resolver = global_resolver();
+        for name in externals {
+            let parameters = vec!["double"; resolver.arity(name).unwrap_or(1)].join(", ");
+            header += &format!("double {}({});\n", name, parameters);
+        }
+    }
+
+    header += "\n#ifdef __cplusplus\n}\n#endif\n\n#endif\n";
+    header
+}
+
+/// Writes the functions of the module under `symbol_name` to `output`, and their C header to `output` with the extension `.h`.
+/// `fja`, `fja_batch` and `fja_jet` become `symbol_name`, `symbol_name_batch` and `symbol_name_jet`, the module is renamed in place.
+/// The code is position independent, so every format can go into shared libraries and PIE executables.
+pub fn export_module(compiler: &Compiler, module: &IrModule, symbol_name: &str, format: ExportFormat, output: &Path) {
+    export(compiler, module, symbol_name, format, output, None, None);
+}
+
+/// Compiles the Taylor approximation of `function` for `options.target_cpu` and exports it as `export_module` does.
+/// The header quotes the formula and documents how many derivatives `symbol_name_jet` writes.
+pub fn export_function(function: &str, precision_center: f64, max_power: usize, options: &CompilationOptions, symbol_name: &str, format: ExportFormat, output: &Path) {
+    let compiler = Compiler::new(&options.target_cpu);
+    let (module, _) = compiler.build_taylor_module(function, precision_center, max_power, options);
+    let jet_order = (options.derivatives > 0).then_some(options.derivatives);
+    export(&compiler, &module, symbol_name, format, output, Some(function), jet_order);
+}
+
+fn export(compiler: &Compiler, module: &IrModule, symbol_name: &str, format: ExportFormat, output: &Path, source: Option<&str>, jet_order: Option<usize>) {
+    check_c_identifier(symbol_name, "Export error | Invalid symbol name");
+    let declared = module.declared_functions();
+    // the library calls the libm symbols, eg. `log` for `ln`, so those are taken as well
+    let taken = |name: &String| declared.iter().any(|function| function == name || libm_symbol(function) == Some(name.as_str()));
+    if let Some(name) = [symbol_name.to_string(), format!("{}_batch", symbol_name), format!("{}_jet", symbol_name)].into_iter().find(taken) {
+        unrecoverable_error!("Export error | Symbol name is taken", format!("The generated code calls an external function '{}'", name));
+    }
+
+    if !module.rename_function("fja", symbol_name) {
+        unrecoverable_error!("Export error | Module has nothing to export", "'fja' wasn't found in the module");
+    }
+    module.rename_function("fja_batch", &format!("{}_batch", symbol_name));
+    module.rename_function("fja_jet", &format!("{}_jet", symbol_name));
+    // calls use the names of the formulas, eg. `ln`, the library has to call the libm symbols
+    for name in &declared {
+        if let Some(symbol) = libm_symbol(name).filter(|symbol| symbol != name) {
+            module.rename_function(name, symbol);
+        }
+    }
+    let externals: Vec<String> = declared.into_iter().filter(|name| libm_symbol(name).is_none()).collect();
+
+    let object_file = compiler.emit_position_independent_object(module);
+    let written = match format {
+        ExportFormat::Object => fs::write(output, &object_file),
+        ExportFormat::StaticArchive => fs::write(output, static_archive(&object_file)),
+        ExportFormat::SharedLibrary => match system_linker() {
+            Some(linker) => {
+                link_shared_library(&linker, &object_file, output);
+                Ok(())
+            },
+            None => {
+                let soname = output.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                fs::write(output, write_shared_object(&object_file, &soname, &[LIBM]))
+            }
+        }
+    };
+    if let Err(error) = written {
+        unrecoverable_error!("Export error | Failed to write the library", format!("{}: {}", output.display(), error));
+    }
+
+    let header_path = output.with_extension("h");
+    if let Err(error) = fs::write(&header_path, c_header(symbol_name, module, source, jet_order, &externals)) {
+        unrecoverable_error!("Export error | Failed to write the header", format!("{}: {}", header_path.display(), error));
+    }
+}
+
+fn link_shared_library(linker: &str, object_file: &[u8], output: &Path) {
+    let object_path = PathBuf::from(format!("{}.{}.o", output.display(), std::process::id()));
+    if let Err(error) = fs::write(&object_path, object_file) {
+        unrecoverable_error!("Export error | Failed to write the object file for the linker", format!("{}: {}", object_path.display(), error));
+    }
+    let result = Command::new(linker).arg("-shared").arg("-o").arg(output).arg(&object_path).arg("-lm").output();
+    let _ = fs::remove_file(&object_path);
+
+    match result {
+        Ok(result) if result.status.success() => {},
+        Ok(result) => {
+            unrecoverable_error!(format!("Export error | {} failed to link the shared library", linker), String::from_utf8_lossy(&result.stderr));
+        },
+        Err(error) => {
+            unrecoverable_error!(format!("Export error | {} couldn't be run", linker), error);
+        }
+    }
+}
@@ -1,165 +1,350 @@
 #![allow(unused_imports)]
 use crate::{
     unrecoverable_error,
-    components::external_functions::*,
-    components::terminal_decoration::Color
+    components::symbol_resolver::global_resolver,
+    components::terminal_decoration::Color,
+    components::executable_memory::ExecutableRegion,
+    components::orc_jit::OrcJit,
+    components::elf_object::*,
+    stages::binary_compile::TargetDescription
 };
 
 use std::{
+    fmt,
     process::exit,
     ptr::NonNull
 };
 
-//pub type FunctionType = fn(f64, *mut f64) -> f64;
-pub type FunctionType = fn(f64) -> f64;
+/// `double fja(double x)`, generated code follows the C calling convention.
+pub type FunctionType = extern "C" fn(f64) -> f64;
 
-fn parse_symbol_table<'a>(symbols: &mut Vec<&'a str>, sym_table: Option<&[u8]>, object_file_buffer: &'a [u8], string_table_start: usize) -> usize{
-    let mut temp_fja_offset: u64 = u64::MAX;
+/// Memory holding the code of a loaded function.
+pub enum LoadedCode {
+    /// Linked by `link_buffer` into a region of its own.
+    Region(ExecutableRegion),
+    /// Linked by, and living in, an LLJIT instance.
+    Jit(OrcJit),
+}
 
-    if let Some(sym_t) = sym_table {
-        let mut entry_offset: usize = 0;
-        while entry_offset < sym_t.len(){
+/// `fja` together with the memory holding its code, the code is unmapped when the handle is dropped.
+/// Handles are independent of each other and of the compiler that produced them.
+pub struct CompiledFunction {
+    function: FunctionType,
+    code: LoadedCode,
+    code_size: usize,
+    source: Option<String>,
+    target: TargetDescription
+}
 
-            let offset_of_string_name = u32::from_le_bytes(sym_t[entry_offset..entry_offset + 4].try_into().expect("Slice with incorrect length")) as usize;
-            let symbol_name_buffer = &object_file_buffer[string_table_start + offset_of_string_name..];
+impl CompiledFunction {
+    pub fn new(function: FunctionType, code: LoadedCode, code_size: usize, target: TargetDescription) -> Self {
+        CompiledFunction { function, code, code_size, source: None, target }
+    }
 
-            let symbol_name: &str = if let Some(null_terminator_index) = symbol_name_buffer.iter().position(|&b| b == 0) {
-                unsafe { std::str::from_utf8_unchecked(&symbol_name_buffer[..null_terminator_index]) }
-            }else {
-                ""
-            };
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
 
-            if symbol_name == "fja" {
-                temp_fja_offset = u64::from_le_bytes(sym_t[(entry_offset+8)..(entry_offset+16)].try_into().expect("Slice with incorrect length"));
-            }
+    #[inline]
+    pub fn call(&self, x: f64) -> f64 {
+        (self.function)(x)
+    }
 
-            symbols.push(symbol_name);
-            entry_offset+=24;
-        }
+    /// Formula or IR the function was compiled from, None for functions loaded from an object file.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// CPU and features the code was generated for.
+    pub fn target(&self) -> &TargetDescription {
+        &self.target
+    }
+
+    /// Size of the object file in bytes, the mapped memory is rounded up to whole pages.
+    pub fn code_size(&self) -> usize {
+        self.code_size
+    }
 
-        if temp_fja_offset == u64::MAX {
-            unrecoverable_error!("Linker error | Parsing of the symbol table", "'fja' symbol wasn't found in the symbol table");
+    /// Region of the code, None if it was loaded by the JIT.
+    pub fn region(&self) -> Option<&ExecutableRegion> {
+        match &self.code {
+            LoadedCode::Region(region) => Some(region),
+            LoadedCode::Jit(_) => None
         }
+    }
 
-        temp_fja_offset as usize
-    }else{
-        unrecoverable_error!("Linker error | Parsing of the symbol table", "Symbol table wasn't found in the byte buffer provided");
+    pub fn is_jit_compiled(&self) -> bool {
+        matches!(self.code, LoadedCode::Jit(_))
     }
 }
 
-fn resolve_relative_offset(symbol_offset: usize, symbol_name: &str, fc_offset: usize, buffer_ptr: *mut u8) -> i32{
-    if symbol_name.contains(".LCPI") {
-        if let Some(pos) = symbol_name.find('_') {
-            match &symbol_name[pos + 1..].parse::<i32>() {
-                Ok(value) => {
-                    return ((fc_offset as i32) + value*8) - (symbol_offset as i32);
-                },
-                Err(e) => {unrecoverable_error!("Linker Error | failed to parse usize during the resolve of relative offset", e);}
-            }
+impl fmt::Debug for CompiledFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledFunction")
+            .field("source", &self.source)
+            .field("address", &(self.function as *const ()))
+            .field("jit", &self.is_jit_compiled())
+            .field("code_size", &self.code_size)
+            .field("target", &self.target)
+            .finish()
+    }
+}
+
+pub type BatchFunctionType = unsafe extern "C" fn(*const f64, *mut f64, usize);
+
+/// `fja_batch(const double* xs, double* ys, size_t n)`, ys[i] = fja(xs[i]).
+/// `code` is None when the object was linked into memory owned by the caller.
+pub struct BatchFunction {
+    function: BatchFunctionType,
+    code: Option<LoadedCode>
+}
+
+impl BatchFunction {
+    /// Takes ownership of the memory the function was loaded into.
+    pub fn owning(mut self, code: LoadedCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn evaluate(&self, xs: &[f64], ys: &mut [f64]) {
+        if xs.len() != ys.len() {
+            unrecoverable_error!("Batch evaluation error | Input and output slices differ in length", format!("{} != {}", xs.len(), ys.len()));
         }
+        unsafe { (self.function)(xs.as_ptr(), ys.as_mut_ptr(), xs.len()) }
     }
+}
+
+pub type JetFunctionType = unsafe extern "C" fn(f64, *mut f64);
 
-    let pointer_addr: usize = unsafe { buffer_ptr.add(symbol_offset) as usize};
-    (match symbol_name{
-        "sin" => {(sin as usize).wrapping_sub(pointer_addr)},
-        "cos" => {(cos as usize).wrapping_sub(pointer_addr)},
-        "tan" => {(tan as usize).wrapping_sub(pointer_addr)},
-        "exp" => {(exp as usize).wrapping_sub(pointer_addr)},
-        "ln" => {(ln as usize).wrapping_sub(pointer_addr)},
-        "asin" => {(asin as usize).wrapping_sub(pointer_addr)},
-        "acos" => {(acos as usize).wrapping_sub(pointer_addr)},
-        "atan" => {(atan as usize).wrapping_sub(pointer_addr)},
-        "sqrt" => {(sqrt as usize).wrapping_sub(pointer_addr)},
-        _ => {unrecoverable_error!("Linker Error | Unrecognized symbol in the external functions table", symbol_name);}
-    }) as i32
+/// `fja_jet(double x, double* out)`, out[k] = f^(k)(x) for k up to the order it was compiled with.
+pub struct JetFunction {
+    function: JetFunctionType,
+    order: usize,
+    code: Option<LoadedCode>
 }
 
-pub fn link_buffer(buffer: &mut[u8], buffer_ptr: NonNull<u8>) -> FunctionType{
-    let immutable_buffer: &mut Vec<u8> = &mut Vec::<u8>::new();
-    buffer.clone_into(immutable_buffer);
-    let section_toff = u64::from_le_bytes(immutable_buffer[0x28..0x28 + 8].try_into().expect("Slice with incorrect length"));
-    let entry_num_section_t = u16::from_le_bytes(immutable_buffer[0x3C..0x3C + 2].try_into().expect("Slice with incorrect length"));
-    let string_index = u16::from_le_bytes(immutable_buffer[0x3E..0x3E + 2].try_into().expect("Slice with incorrect length"));
-
-    let string_table_start = (section_toff + (string_index as u64) * 0x40) as usize;
-
-    let string_table_offset = u64::from_le_bytes(immutable_buffer[string_table_start+0x18..string_table_start + 0x18 + 8].try_into().expect("Slice with incorrect length")) as usize;
-
-    let mut text_section: Option<&[u8]> = None;
-    let mut text_offset: usize = 0;
-    let mut rela_text: Option<&[u8]> = None;
-    let mut sym_table: Option<&[u8]> = None;
-    let mut fc_offset: usize = 0;
-
-    let mut entry_offset= section_toff as usize;
-    for index in 0..entry_num_section_t{
-        if index != 1{
-            let offset_of_string_name = u32::from_le_bytes(immutable_buffer[entry_offset..entry_offset + 4].try_into().expect("Slice with incorrect length")) as usize;
-            let section_name = &immutable_buffer[string_table_offset + offset_of_string_name..];
-
-            if let Some(null_terminator_index) = section_name.iter().position(|&b| b == 0) {
-                let section_name_str = std::str::from_utf8(&section_name[..null_terminator_index])
-                    .expect("Failed to convert bytes to string");
-                match section_name_str {
-                    ".text" => {
-                        text_offset = u64::from_le_bytes(immutable_buffer[entry_offset + 0x18..entry_offset +0x18 + 8].try_into().expect("Slice with incorrect length")) as usize;
-                        let section_lenght = u64::from_le_bytes(immutable_buffer[entry_offset + 0x20..entry_offset +0x20 + 8].try_into().expect("Slice with incorrect length")) as usize;
-                        text_section = Some(&immutable_buffer[text_offset..text_offset+section_lenght]);
-                    }
-                    ".rela.text" => {
-                        let section_offset = u64::from_le_bytes(immutable_buffer[entry_offset + 0x18..entry_offset +0x18 + 8].try_into().expect("Slice with incorrect length")) as usize;
-                        let section_lenght = u64::from_le_bytes(immutable_buffer[entry_offset + 0x20..entry_offset +0x20 + 8].try_into().expect("Slice with incorrect length")) as usize;
-                        rela_text = Some(&immutable_buffer[section_offset..section_offset+section_lenght]);
-                    }
-                    ".symtab" => {
-                        let section_offset = u64::from_le_bytes(immutable_buffer[entry_offset + 0x18..entry_offset +0x18 + 8].try_into().expect("Slice with incorrect length")) as usize;
-                        let section_lenght = u64::from_le_bytes(immutable_buffer[entry_offset + 0x20..entry_offset +0x20 + 8].try_into().expect("Slice with incorrect length")) as usize;
-                        sym_table = Some(&immutable_buffer[section_offset..section_offset+section_lenght]);
-                    }
-                    ".rodata.cst8" => {
-                        fc_offset = u64::from_le_bytes(immutable_buffer[entry_offset + 0x18..entry_offset +0x18 + 8].try_into().expect("Slice with incorrect length")) as usize;
-                    }
-                    _ => {}
-                }
-            } else {
-                unrecoverable_error!("Linker Error | During analysis of ELF headers", "No null terminator found in the section name, check ELF byte buffer");
-            }
+impl JetFunction {
+    /// Takes ownership of the memory the function was loaded into.
+    pub fn owning(mut self, code: LoadedCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn evaluate_into(&self, x: f64, derivatives: &mut [f64]) {
+        if derivatives.len() != self.order+1 {
+            unrecoverable_error!("Jet evaluation error | Output slice has to hold the value and every derivative", format!("{} != {}", derivatives.len(), self.order+1));
         }
-        entry_offset+=0x40;
+        unsafe { (self.function)(x, derivatives.as_mut_ptr()) }
     }
 
-    if text_section.is_none() {
-        unrecoverable_error!("Linker Error | Invalid result of ELF headers analisys", "Text section wasn't found in ELF byte buffer");
+    /// [f(x), f'(x), ..., f^(order)(x)]
+    pub fn evaluate(&self, x: f64) -> Vec<f64> {
+        let mut derivatives = vec![0.0; self.order+1];
+        self.evaluate_into(x, &mut derivatives);
+        derivatives
     }
+}
 
-    let mut symbols = Vec::<&str>::new();
-    let fja_offset = text_offset + parse_symbol_table(
-        &mut symbols,
-        sym_table,
-        immutable_buffer,
-        string_table_offset
-    );
+fn external_function_address(symbol_name: &str) -> usize {
+    match global_resolver().address(symbol_name) {
+        Some(address) => address,
+        None => {unrecoverable_error!("Linker Error | Unrecognized symbol in the external functions table", symbol_name);}
+    }
+}
 
-    let raw_buffer_ptr: *mut u8 = buffer_ptr.as_ptr();
+/// Address of S. Symbols defined in the object (functions, constant pools) are addressed
+/// through the file offset of their section, as the whole object file is copied to `buffer_ptr`.
+fn symbol_address(symbol: &Symbol, object: &ElfObject, buffer_ptr: *mut u8) -> usize{
+    match symbol.section {
+        SHN_UNDEF => external_function_address(symbol.name),
+        SHN_ABS => symbol.value,
+        section if symbol.is_defined() && object.sections[section].kind != SHT_NOBITS => unsafe {
+            buffer_ptr.add(object.sections[section].offset + symbol.value) as usize
+        },
+        _ => {
+            unrecoverable_error!("Linker Error | Unsupported symbol", format!("'{}' is defined in section {:#x}, which isn't loaded with the object file", symbol.name, symbol.section));
+        }
+    }
+}
 
-    if let Some(r_text) = rela_text {
-        let mut entry_offset: usize = 0;
-        while entry_offset < r_text.len(){
-            let r_offset = u64::from_le_bytes(r_text[entry_offset..entry_offset + 8].try_into().expect("Slice with incorrect length")) as usize;
-            let r_index = (u64::from_le_bytes(r_text[entry_offset+8..entry_offset+16].try_into().expect("Slice with incorrect length"))>>32) as usize;
+/// Stores a 32 bit field, `value` has to survive the truncation (sign extension of the field gives it back).
+fn write_i32(buffer: &mut [u8], place_offset: usize, value: i64, symbol: &Symbol) {
+    match i32::try_from(value) {
+        Ok(value) => buffer[place_offset..place_offset+4].copy_from_slice(&value.to_le_bytes()),
+        Err(_) => {
+            unrecoverable_error!("Linker Error | Relocation out of range", format!("'{}' resolves to {:#x}, which doesn't fit into 32 bits", symbol.name, value));
+        }
+    }
+}
 
-            let symbol_offset = text_offset+r_offset;
-            let offset = resolve_relative_offset(symbol_offset+4, symbols[r_index], fc_offset, raw_buffer_ptr).to_le_bytes();
-            buffer[symbol_offset..symbol_offset+4].copy_from_slice(&offset[..4]);
-            entry_offset+=24;
+/// Width of the field a relocation type patches.
+fn relocation_width(kind: u32) -> usize {
+    match kind {
+        R_X86_64_64 => 8,
+        R_X86_64_PC32 | R_X86_64_PLT32 | R_X86_64_32S | R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX => 4,
+        _ => {
+            unrecoverable_error!(
+                "Linker Error | Unsupported relocation type",
+                format!("Type {} isn't one of R_X86_64_64, PC32, PLT32, 32S, GOTPCREL, GOTPCRELX or REX_GOTPCRELX", kind)
+            );
         }
     }
-    //else{
-    //     unrecoverable_error!("Linker Error | Invalid result of ELF headers analisys", "Relative text section wasn't found in the ELF byte buffer");
-    // }
+}
 
-    unsafe{
-        std::mem::transmute::<*mut u8, FunctionType>(raw_buffer_ptr.add(fja_offset))
+fn uses_got(kind: u32) -> bool {
+    matches!(kind, R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX)
+}
+
+/// Offset of the global offset table, placed after the contents of the object file.
+fn got_offset(object: &ElfObject) -> usize {
+    object.file_size().next_multiple_of(8)
+}
+
+/// Symbols loaded through the GOT, each gets one 8 byte entry.
+fn got_symbols(object: &ElfObject) -> Vec<usize> {
+    let mut symbols = Vec::<usize>::new();
+    for relocation in object.relocation_sections.iter().flat_map(|relocations| &relocations.relocations) {
+        if uses_got(relocation.kind) && !symbols.contains(&relocation.symbol) {
+            symbols.push(relocation.symbol);
+        }
+    }
+    symbols
+}
+
+/// External functions called through R_X86_64_PLT32, each gets a trampoline in case it's out of rel32 reach.
+fn trampoline_symbols(object: &ElfObject) -> Vec<usize> {
+    let mut symbols = Vec::<usize>::new();
+    for relocation in object.relocation_sections.iter().flat_map(|relocations| &relocations.relocations) {
+        if relocation.kind == R_X86_64_PLT32 && object.symbols[relocation.symbol].section == SHN_UNDEF && !symbols.contains(&relocation.symbol) {
+            symbols.push(relocation.symbol);
+        }
     }
+    symbols
 }
 
+/// `jmp [rip+2]`, two bytes of int3 padding, then the 8 byte absolute address of the target.
+const TRAMPOLINE_SIZE: usize = 16;
+
+fn trampoline(address: u64) -> [u8; TRAMPOLINE_SIZE] {
+    let mut code = [0xFF, 0x25, 0x02, 0x00, 0x00, 0x00, 0xCC, 0xCC, 0, 0, 0, 0, 0, 0, 0, 0];
+    code[8..].copy_from_slice(&address.to_le_bytes());
+    code
+}
+
+/// Bytes the linked object occupies, the object file followed by its GOT and trampolines.
+pub fn linked_size(object_file: &[u8]) -> usize {
+    let object = ElfObject::parse(object_file);
+    got_offset(&object) + 8*got_symbols(&object).len() + TRAMPOLINE_SIZE*trampoline_symbols(&object).len()
+}
+
+pub(crate) fn function_at(entry: *mut u8) -> FunctionType{
+    unsafe{ std::mem::transmute::<*mut u8, FunctionType>(entry) }
+}
+
+pub(crate) fn batch_function_at(entry: *mut u8) -> BatchFunction{
+    BatchFunction {
+        function: unsafe{ std::mem::transmute::<*mut u8, BatchFunctionType>(entry) },
+        code: None
+    }
+}
+
+pub(crate) fn jet_function_at(entry: *mut u8, order: usize) -> JetFunction{
+    JetFunction {
+        function: unsafe{ std::mem::transmute::<*mut u8, JetFunctionType>(entry) },
+        order,
+        code: None
+    }
+}
+
+pub fn link_buffer(buffer: &mut[u8], buffer_ptr: NonNull<u8>) -> FunctionType{
+    function_at(link_object(buffer, buffer_ptr, "fja"))
+}
+
+pub fn link_batch_buffer(buffer: &mut[u8], buffer_ptr: NonNull<u8>) -> BatchFunction{
+    batch_function_at(link_object(buffer, buffer_ptr, "fja_batch"))
+}
+
+pub fn link_jet_buffer(buffer: &mut[u8], buffer_ptr: NonNull<u8>, order: usize) -> JetFunction{
+    jet_function_at(link_object(buffer, buffer_ptr, "fja_jet"), order)
+}
+
+/// Resolves the relocations of every loaded section as if the object file was placed at `buffer_ptr`, returns the address of `entry_symbol`.
+/// `buffer` holds the object file and has to be `linked_size` bytes long if the object uses a GOT or calls external functions.
+pub(crate) fn link_object(buffer: &mut[u8], buffer_ptr: NonNull<u8>, entry_symbol: &str) -> *mut u8{
+    let object_file = buffer.to_vec();
+    let object = ElfObject::parse(&object_file);
+
+    if let Some(section) = object.sections.iter().find(|section| section.is_allocated() && section.kind == SHT_NOBITS && section.size > 0) {
+        unrecoverable_error!("Linker Error | Unsupported section", format!("'{}' has no contents in the object file, only sections stored in the file are loaded", section.name));
+    }
+
+    let entry_symbol_offset = match object.defined_symbol(entry_symbol) {
+        Some(symbol) => object.sections[symbol.section].offset + symbol.value,
+        None => { unrecoverable_error!("Linker error | Parsing of the symbol table", format!("'{}' symbol wasn't found in the symbol table", entry_symbol)); }
+    };
+
+    let raw_buffer_ptr: *mut u8 = buffer_ptr.as_ptr();
+
+    let got_offset = got_offset(&object);
+    let got_symbols = got_symbols(&object);
+    let trampolines_offset = got_offset + 8*got_symbols.len();
+    let trampoline_symbols = trampoline_symbols(&object);
+    let linked_size = trampolines_offset + TRAMPOLINE_SIZE*trampoline_symbols.len();
+    if linked_size > buffer.len() {
+        unrecoverable_error!("Linker Error | Buffer too small for the GOT and trampolines", format!("The linked object needs {} bytes, the buffer has {}", linked_size, buffer.len()));
+    }
+    for (entry, &symbol) in got_symbols.iter().enumerate() {
+        let address = symbol_address(&object.symbols[symbol], &object, raw_buffer_ptr) as u64;
+        buffer[got_offset + 8*entry..got_offset + 8*entry + 8].copy_from_slice(&address.to_le_bytes());
+    }
+    for (entry, &symbol) in trampoline_symbols.iter().enumerate() {
+        let address = symbol_address(&object.symbols[symbol], &object, raw_buffer_ptr) as u64;
+        let offset = trampolines_offset + TRAMPOLINE_SIZE*entry;
+        buffer[offset..offset + TRAMPOLINE_SIZE].copy_from_slice(&trampoline(address));
+    }
+
+    for relocation_section in object.relocation_sections.iter().filter(|relocations| object.sections[relocations.target].is_allocated()) {
+        let section = &object.sections[relocation_section.target];
+        for relocation in &relocation_section.relocations {
+            if relocation.offset + relocation_width(relocation.kind) > section.size {
+                unrecoverable_error!("Linker Error | Malformed object file", format!("Relocation at {} doesn't fit into section '{}'", relocation.offset, section.name));
+            }
+            let symbol = &object.symbols[relocation.symbol];
+            let place_offset = section.offset + relocation.offset;
+            let place = raw_buffer_ptr as i64 + place_offset as i64;
+            let target = symbol_address(symbol, &object, raw_buffer_ptr) as i64;
+
+            match relocation.kind {
+                // S + A
+                R_X86_64_64 => buffer[place_offset..place_offset+8].copy_from_slice(&target.wrapping_add(relocation.addend).to_le_bytes()),
+                R_X86_64_32S => write_i32(buffer, place_offset, target.wrapping_add(relocation.addend), symbol),
+                R_X86_64_PC32 => write_i32(buffer, place_offset, target.wrapping_add(relocation.addend).wrapping_sub(place), symbol),
+                // S + A - P if the function is in reach, otherwise the call goes through its trampoline
+                R_X86_64_PLT32 => {
+                    let direct = target.wrapping_add(relocation.addend).wrapping_sub(place);
+                    let value = match trampoline_symbols.iter().position(|&trampoline_symbol| trampoline_symbol == relocation.symbol) {
+                        Some(entry) if i32::try_from(direct).is_err() => {
+                            let trampoline_address = raw_buffer_ptr as i64 + (trampolines_offset + TRAMPOLINE_SIZE*entry) as i64;
+                            trampoline_address.wrapping_add(relocation.addend).wrapping_sub(place)
+                        },
+                        _ => direct
+                    };
+                    write_i32(buffer, place_offset, value, symbol)
+                },
+                // G + GOT + A - P, the instructions aren't relaxed, the GOT entry is loaded as emitted
+                _ => {
+                    let entry = got_symbols.iter().position(|&got_symbol| got_symbol == relocation.symbol).unwrap();
+                    let entry_address = raw_buffer_ptr as i64 + (got_offset + 8*entry) as i64;
+                    write_i32(buffer, place_offset, entry_address.wrapping_add(relocation.addend).wrapping_sub(place), symbol)
+                }
+            }
+        }
+    }
+
+    unsafe{ raw_buffer_ptr.add(entry_symbol_offset) }
+}
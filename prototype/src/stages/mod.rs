@@ -3,4 +3,6 @@ pub mod ir_compile;
 pub mod taylor_ir_compile;
 pub mod linking;
 pub mod binary_compile;
-pub mod custom_ir_compile;
\ No newline at end of file
+pub mod compiler;
+pub mod custom_ir_compile;
+pub mod library_export;
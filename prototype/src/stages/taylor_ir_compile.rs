@@ -1,17 +1,29 @@
 #![allow(unused_imports)]
 use crate::{
     components::{
-        object_type_definitions::Func, taylor_optimizer::optimize_postfix_using_taylor, terminal_decoration::Color
+        object_type_definitions::{Func, Node}, taylor_optimizer::optimize_postfix_using_taylor, terminal_decoration::Color,
+        polynomials::{TsPoly, SharedPowers},
+        pade_approximants::RationalPoly,
+        coefficient_tables::factorial,
+        compilation_options::{CompilationOptions, EvaluationScheme},
+        ir_builder::{IrModule, FunctionBuilder},
+        jets::Jet,
+        symbolic_differentiation::{expand_derivatives, postfix_to_tree, tree_to_postfix}
     }, stages::function_lexing::{
         convert_infix_to_postfix,
         lex_function
     }, unrecoverable_error
 };
-use std::process::exit;
+use std::{
+    collections::HashMap,
+    process::exit,
+    f64::consts::FRAC_PI_2
+};
+use llvm_sys::prelude::LLVMValueRef;
 
 //TODO Write description for this function
 #[inline(always)]
-fn stack_pop_wrapper(stack: &mut Vec<String>) -> String {
+fn stack_pop_wrapper<T>(stack: &mut Vec<T>) -> T {
     match stack.pop() { 
         Some(value) => value,
         None => { //TODO work on error handling message here
@@ -20,151 +32,302 @@ fn stack_pop_wrapper(stack: &mut Vec<String>) -> String {
     }
 }
 
-pub fn generate_verbose_ir_from_taylor_sequence(sequence: &[Func]) -> String {
-    let mut result_stack = Vec::<String>::new();
-    let mut generated_poly_addr: i16 = -1;
-    let mut fun_code = String::new();
+/// Values shared between the polynomials of one generated function.
+#[derive(Default)]
+struct PolyIrState {
+    /// Powers of the argument which can be reused by every following polynomial of the same argument.
+    shared_powers: Option<SharedPowers>,
+    /// Center c and the value of x - c, if it was already built.
+    shifted_x: Option<(f64, LLVMValueRef)>,
+}
 
-    let mut instrinsic_declarations = String::new();
-    let mut declared_instrinsics: u8 = 0; // Bit position/Intrinsic => 1/Pow, 0/Sqrt
+/// Builds the evaluation of a polynomial element of the sequence, using the evaluation scheme selected in options.
+fn build_poly_ir(ts_poly: &TsPoly, index: usize, argument: LLVMValueRef, state: &mut PolyIrState, builder: &FunctionBuilder, options: &CompilationOptions) -> LLVMValueRef {
+    let argument = poly_argument(ts_poly, argument, index, state, builder);
+    evaluate_poly(ts_poly, argument, &index.to_string(), state, builder, options)
+}
 
-    for (index,elem) in sequence.iter().enumerate() {
-        match elem {
-            Func::Poly(ts_poly) => {
-                let temp_code: String; let register: String;
+/// Polynomials kept in the shifted basis are evaluated at (argument - center), for polynomials of x the subtraction is built only once.
+fn poly_argument(ts_poly: &TsPoly, argument: LLVMValueRef, index: usize, state: &mut PolyIrState, builder: &FunctionBuilder) -> LLVMValueRef {
+    if !ts_poly.from_x {
+        if ts_poly.center == 0.0 || ts_poly.max_pow == 0 {
+            argument
+        }else{
+            builder.fsub(argument, builder.constant(ts_poly.center), &format!("at{}", index))
+        }
+    }else if ts_poly.center == 0.0 || ts_poly.max_pow == 0 {
+        argument
+    }else{
+        match state.shifted_x {
+            Some((center, shifted_x)) if center == ts_poly.center => shifted_x,
+            Some(_) => builder.fsub(argument, builder.constant(ts_poly.center), &format!("at{}", index)),
+            None => {
+                let shifted_x = builder.fsub(argument, builder.constant(ts_poly.center), "xt");
+                state.shifted_x = Some((ts_poly.center, shifted_x));
+                shifted_x
+            }
+        }
+    }
+}
 
-                if ts_poly.from_x {
-                    if generated_poly_addr < 0 {
-                        (temp_code, register) = ts_poly.generate_ir(None, index as u16);
-                        generated_poly_addr = index as i16;
-                    }else{
-                        (temp_code, register) = ts_poly.generate_ir_from_existing_powers( index as u16, generated_poly_addr as u16);
+fn evaluate_poly(ts_poly: &TsPoly, argument: LLVMValueRef, suffix: &str, state: &mut PolyIrState, builder: &FunctionBuilder, options: &CompilationOptions) -> LLVMValueRef {
+    let fma = options.fused_multiply_add;
+    match options.evaluation_scheme {
+        EvaluationScheme::SharedPowers => {
+            let name = format!("s{}", suffix);
+            if !ts_poly.from_x {
+                ts_poly.build_ir(builder, &mut SharedPowers::new(argument, &name), &name, fma)
+            }else{
+                match &mut state.shared_powers {
+                    Some(powers) if powers.argument() == argument => ts_poly.build_ir(builder, powers, &name, fma),
+                    Some(_) => ts_poly.build_ir(builder, &mut SharedPowers::new(argument, &name), &name, fma),
+                    None => {
+                        let powers = state.shared_powers.insert(SharedPowers::new(argument, "t"));
+                        ts_poly.build_ir(builder, powers, &name, fma)
                     }
-                }else{
-                    let arg = stack_pop_wrapper(&mut result_stack);
-                    (temp_code, register) = ts_poly.generate_ir(Some(arg), index as u16);
                 }
-                
-                result_stack.push(register);
-                fun_code+=&format!(";{}. elem\n{}\n",index, temp_code); //fun_code+=&temp_code;
-            },
-            Func::Add | Func::Sub | Func::Mul | Func::Div=> {
-                let arg2 = stack_pop_wrapper(&mut result_stack);
-                let arg1 = stack_pop_wrapper(&mut result_stack);
+            }
+        },
+        EvaluationScheme::Horner => ts_poly.build_horner_ir(builder, argument, &format!("h{}", suffix), fma),
+        EvaluationScheme::Estrin => ts_poly.build_estrin_ir(builder, argument, &format!("e{}", suffix), fma),
+    }
+}
 
-                let temp_arg = format!("%t{}", index as u16);
-                fun_code += &format!(";{}. elem\n{} = {} double {}, {}\n\n", index, temp_arg, elem.ir_string(), arg1, arg2); //fun_code += &format!("{} = f{} double {}, {}", temp_arg, elem, arg1, arg2);
-                result_stack.push(temp_arg);
-            },
-            Func::Sqrt => {
-                let arg = stack_pop_wrapper(&mut result_stack);
-                if declared_instrinsics & 1 == 0 {
-                    instrinsic_declarations += "declare double @llvm.sqrt.f64(double)";
-                    declared_instrinsics |= 1;
-                }
+/// Builds `double fja(double x)` evaluating the optimized postfix sequence.
+pub fn build_module_from_taylor_sequence(sequence: &[Func], options: &CompilationOptions) -> IrModule {
+    let module = IrModule::new("taylor");
+    build_taylor_function(&module, sequence, options);
+    match options.float_mode.instruction_flags() {
+        Some(flags) => module.with_fast_math_flags(flags),
+        None => module
+    }
+}
 
-                let temp_arg = format!("%t{}", index as u16);
-                fun_code += &format!(";{}. elem\n{} = call double @llvm.sqrt.f64(double {})\n", index, temp_arg, arg);//fun_code += &format!("{} = call double @llvm.sqrt.f64(double {})", temp_arg, arg);
-                result_stack.push(temp_arg);
-            },
-            Func::Pow => {
-                let arg2 = stack_pop_wrapper(&mut result_stack);
-                let arg1 = stack_pop_wrapper(&mut result_stack);
-                if declared_instrinsics & 2 == 0 {
-                    instrinsic_declarations += "declare double @llvm.pow.f64(double, double)";
-                    declared_instrinsics |= 2;
-                }
+/// Adds `fja` to an existing module, eg. one created by a `Compiler`.
+pub fn build_taylor_function(module: &IrModule, sequence: &[Func], options: &CompilationOptions) {
+    let builder = FunctionBuilder::new(module, "fja", options.float_mode);
+    let value = build_sequence(&builder, sequence, builder.argument(), false, options, "");
+    builder.ret(value);
+}
 
-                let temp_arg = format!("%t{}", index as u16);
-                fun_code += &format!(";{}. elem\n{} = call double @llvm.pow.f64(double {}, double {})\n", index, temp_arg, arg1, arg2);//fun_code += &format!("{} = call double @llvm.pow.f64(double {}, double {})\n", temp_arg, arg1, arg2);
-                result_stack.push(temp_arg);
-            },
-            Func::X => result_stack.push(String::from("%x")),
-            Func::Const(value) => result_stack.push(format!("{:.15e}", value)),
-            _ => { unrecoverable_error!("Taylor compilation | Encountered invalid element in provided sequence", elem); }
+/// Adds `fja` evaluating a sequence which wasn't Taylor optimized, every elementary function is a call to its external implementation.
+pub fn build_exact_function(module: &IrModule, sequence: &[Func], options: &CompilationOptions) {
+    let builder = FunctionBuilder::new(module, "fja", options.float_mode);
+    let value = build_sequence(&builder, sequence, builder.argument(), true, options, "");
+    builder.ret(value);
+}
+
+/// Number of values an element of the sequence takes from the stack.
+fn operand_count(elem: &Func) -> usize {
+    match elem {
+        Func::X | Func::Const(_) => 0,
+        Func::Exact(sequence) if !sequence.is_empty() => 0,
+        Func::Poly(TsPoly { from_x: true, .. }) | Func::Rational(RationalPoly { from_x: true, .. }) => 0,
+        Func::Add | Func::Sub | Func::Mul | Func::Div | Func::Pow => 2,
+        _ => 1
+    }
+}
+
+/// Builds the value of a postfix sequence in which x is `argument`, every element chooses between an inline polynomial and a call:
+/// polynomials, rational and range reduced functions are inlined, elementary functions the optimizer left in the sequence
+/// and `exact(...)` subterms call their external implementations.
+/// In `exact` sequences sqrt is also a call, otherwise it is the LLVM intrinsic.
+/// Subexpressions are hash-consed, an element on operands that were already seen reuses the earlier value instead of emitting new code.
+fn build_sequence(builder: &FunctionBuilder, sequence: &[Func], argument: LLVMValueRef, exact: bool, options: &CompilationOptions, prefix: &str) -> LLVMValueRef {
+    let mut values = Vec::<LLVMValueRef>::new();
+    let mut known: HashMap<(String, Vec<usize>), usize> = HashMap::new();
+    let mut operand_stack = Vec::<usize>::new();
+    let mut poly_state = PolyIrState::default();
+    let fma = options.fused_multiply_add;
+
+    for (index, elem) in sequence.iter().enumerate() {
+        let mut operands: Vec<usize> = (0..operand_count(elem)).map(|_| stack_pop_wrapper(&mut operand_stack)).collect();
+        operands.reverse();
+        let key = (format!("{:?}", elem), operands);
+        if let Some(&id) = known.get(&key) {
+            operand_stack.push(id);
+            continue;
         }
+
+        let operand = |position: usize| values[key.1[position]];
+        let name = format!("{}t{}", prefix, index);
+        let value = match elem {
+            Func::Poly(ts_poly) => {
+                let poly_arg = if ts_poly.from_x { argument } else { operand(0) };
+                build_poly_ir(ts_poly, index, poly_arg, &mut poly_state, builder, options)
+            },
+            Func::Rational(rational_poly) => {
+                let rational_arg = if rational_poly.from_x { argument } else { operand(0) };
+                rational_poly.build_ir(builder, rational_arg, &format!("{}r{}", prefix, index), fma)
+            },
+            Func::Reduced(reduced_func) => reduced_func.build_ir(builder, operand(0), &format!("{}rr{}", prefix, index), fma),
+            Func::Exact(subterm) if subterm.is_empty() => operand(0),
+            Func::Exact(subterm) => build_sequence(builder, subterm, argument, true, options, &format!("{}x{}_", prefix, index)),
+            Func::Add => builder.fadd(operand(0), operand(1), &name),
+            Func::Sub => builder.fsub(operand(0), operand(1), &name),
+            Func::Mul => builder.fmul(operand(0), operand(1), &name),
+            Func::Div => builder.fdiv(operand(0), operand(1), &name),
+            Func::Pow => builder.call("llvm.pow.f64", &[operand(0), operand(1)], &name),
+            Func::Sqrt if !exact => builder.call("llvm.sqrt.f64", &[operand(0)], &name),
+            Func::Sqrt | Func::Ln | Func::Exp | Func::Sin | Func::Cos | Func::Tg | Func::Ctg | Func::Asin | Func::Acos | Func::Atg | Func::Actg | Func::External(_) => {
+                elementary_call(builder, elem, operand(0), &name)
+            },
+            Func::X => argument,
+            Func::Const(value) => builder.constant(*value),
+            _ => { unrecoverable_error!("IR generation | Encountered an element which can't be compiled", elem); }
+        };
+
+        values.push(value);
+        known.insert(key, values.len()-1);
+        operand_stack.push(values.len()-1);
     }
 
-    let temp_addr = stack_pop_wrapper(&mut result_stack);
+    values[stack_pop_wrapper(&mut operand_stack)]
+}
 
-    format!("{}\ndefine double @fja(double %x){{\n\n{}ret double {}\n}}", instrinsic_declarations, fun_code, temp_addr)
+/// Call to the external implementation of an elementary function, ctg and arcctg are derived from tan and atan.
+fn elementary_call(builder: &FunctionBuilder, elem: &Func, argument: LLVMValueRef, name: &str) -> LLVMValueRef {
+    let temp = builder.call(&elem.ir_string(), &[argument], name);
+    match elem {
+        Func::Ctg => builder.fdiv(builder.constant(1.0), temp, &format!("{}_ctg", name)),
+        Func::Actg => builder.fsub(builder.constant(FRAC_PI_2), temp, &format!("{}_actg", name)),
+        _ => temp
+    }
 }
 
-pub fn generate_ir_from_taylor_sequence(sequence: &[Func]) -> String {
-    let mut result_stack = Vec::<String>::new();
-    let mut generated_poly_addr: i16 = -1;
-    let mut fun_code = String::new();
+/// Terms f^(j)(argument)/j!, j = 0..=order, of a formula tree in x.
+/// They are built from the simplified symbolic derivatives of the tree, with the elementary functions as external calls.
+fn symbolic_jet_terms(builder: &FunctionBuilder, tree: &Node, argument: LLVMValueRef, order: usize, options: &CompilationOptions, name: &str) -> Vec<Option<LLVMValueRef>> {
+    let mut derivative = tree.simplified();
+    (0..=order).map(|j| {
+        if j > 0 {
+            derivative = derivative.derivative().simplified();
+        }
+        if j > 0 && derivative.op == Func::Const(0.0) {
+            return None;
+        }
+        let value = build_sequence(builder, &tree_to_postfix(&derivative), argument, true, options, &format!("{}_d{}_", name, j));
+        Some(if j > 1 { builder.fmul(value, builder.constant(1.0/factorial(j)), &format!("{}_c{}", name, j)) } else { value })
+    }).collect()
+}
 
-    let mut instrinsic_declarations = String::new();
-    let mut declared_instrinsics: u8 = 0; // Bit position/Intrinsic => 1/Pow, 0/Sqrt
+/// Adds `void fja_jet(double x, double* out)` writing f(x), f'(x), ..., f^(k)(x) to out[0..=k], k = `options.derivatives`.
+/// Every element maps its argument's Taylor series to its own: polynomials through the derivatives of their coefitients,
+/// the remaining functions through their symbolic derivatives, which are again built from the function itself.
+pub fn build_taylor_jet_function(module: &IrModule, sequence: &[Func], options: &CompilationOptions) {
+    let order = options.derivatives;
+    if order == 0 {
+        unrecoverable_error!("Taylor compilation | Jet function needs at least the first derivative", order);
+    }
+
+    let builder = FunctionBuilder::with_output(module, "fja_jet", options.float_mode);
+    let fma = options.fused_multiply_add;
+    let mut jet_stack = Vec::<Jet>::new();
+    let mut poly_state = PolyIrState::default();
 
     for (index, elem) in sequence.iter().enumerate() {
-        match elem {
+        let name = format!("j{}", index);
+        let jet = match elem {
             Func::Poly(ts_poly) => {
-                //println!("{:?}", &ts_poly.coefs);
-                let temp_code: String; let register: String;
-                //println!("{:?}", &ts_poly.coefs);
-
-                if ts_poly.from_x {
-                    if generated_poly_addr < 0 {
-                        (temp_code, register) = ts_poly.generate_ir(None, index as u16);
-                        generated_poly_addr = index as i16;
+                let inner = if ts_poly.from_x { Jet::variable(&builder, order) } else { stack_pop_wrapper(&mut jet_stack) };
+                let argument = poly_argument(ts_poly, inner.value(&builder), index, &mut poly_state, &builder);
+                let outer_terms: Vec<Option<LLVMValueRef>> = (0..=order).map(|j| {
+                    let derivative = if j == 0 { ts_poly.clone() } else { ts_poly.normalized_derivative(j) };
+                    if j > 0 && derivative.max_pow == 0 && derivative.coefs[0] == 0.0 {
+                        None
                     }else{
-                        (temp_code, register) = ts_poly.generate_ir_from_existing_powers( index as u16, generated_poly_addr as u16);
+                        Some(evaluate_poly(&derivative, argument, &format!("{}d{}", index, j), &mut poly_state, &builder, options))
                     }
-                }else{
-                    let arg = stack_pop_wrapper(&mut result_stack);
-                    (temp_code, register) = ts_poly.generate_ir(Some(arg), index as u16);
-                }
-                
-                result_stack.push(register);
-                fun_code+=&temp_code;
+                }).collect();
+                inner.compose(&builder, &outer_terms, fma, &name)
             },
-            Func::Add | Func::Sub | Func::Mul | Func::Div=> {
-                let arg2 = stack_pop_wrapper(&mut result_stack);
-                let arg1 = stack_pop_wrapper(&mut result_stack);
+            Func::Rational(rational_poly) => {
+                let inner = if rational_poly.from_x { Jet::variable(&builder, order) } else { stack_pop_wrapper(&mut jet_stack) };
+                let outer_terms = rational_poly.build_jet_ir(&builder, inner.value(&builder), order, &format!("r{}", index), fma);
+                inner.compose(&builder, &outer_terms, fma, &name)
+            },
+            Func::Reduced(reduced_func) => {
+                let inner = stack_pop_wrapper(&mut jet_stack);
+                let outer_terms = reduced_func.build_jet_ir(&builder, inner.value(&builder), order, &format!("rr{}", index), fma);
+                inner.compose(&builder, &outer_terms, fma, &name)
+            },
+            Func::Add | Func::Sub | Func::Mul | Func::Div => {
+                let arg2 = stack_pop_wrapper(&mut jet_stack);
+                let arg1 = stack_pop_wrapper(&mut jet_stack);
 
-                let temp_arg = format!("%t{}", index as u16);
-                fun_code += &format!("{} = {} double {}, {}\n", temp_arg, elem.ir_string(), arg1, arg2); //fun_code += &format!("{} = f{} double {}, {}", temp_arg, elem, arg1, arg2);
-                result_stack.push(temp_arg);
+                match elem {
+                    Func::Add => arg1.add(&builder, &arg2, &name),
+                    Func::Sub => arg1.sub(&builder, &arg2, &name),
+                    Func::Mul => arg1.mul(&builder, &arg2, fma, &name),
+                    _ => arg1.div(&builder, &arg2, &name),
+                }
             },
             Func::Sqrt => {
-                let arg = stack_pop_wrapper(&mut result_stack);
-                if declared_instrinsics & 1 == 0 {
-                    instrinsic_declarations += "declare double @llvm.sqrt.f64(double)";
-                    declared_instrinsics |= 1;
-                }
-
-                let temp_arg = format!("%t{}", index as u16);
-                fun_code += &format!("{} = call double @llvm.sqrt.f64(double {})\n", temp_arg, arg);//fun_code += &format!("{} = call double @llvm.sqrt.f64(double {})", temp_arg, arg);
-                result_stack.push(temp_arg);
+                // sqrt^(j)(u)/j! = binomial(1/2, j)*u^(1/2-j)
+                let inner = stack_pop_wrapper(&mut jet_stack);
+                let argument = inner.value(&builder);
+                let value = builder.call("llvm.sqrt.f64", &[argument], &format!("t{}", index));
+                let outer_terms = power_series_terms(&builder, argument, value, builder.constant(0.5), order, &name);
+                inner.compose(&builder, &outer_terms, fma, &name)
             },
             Func::Pow => {
-                let arg2 = stack_pop_wrapper(&mut result_stack);
-                let arg1 = stack_pop_wrapper(&mut result_stack);
-                if declared_instrinsics & 2 == 0 {
-                    instrinsic_declarations += "declare double @llvm.pow.f64(double, double)";
-                    declared_instrinsics |= 2;
+                let exponent = stack_pop_wrapper(&mut jet_stack);
+                let inner = stack_pop_wrapper(&mut jet_stack);
+                if !exponent.is_constant() {
+                    unrecoverable_error!("Taylor compilation | Derivatives of a power are only supported for exponents which don't depend on x", elem);
                 }
-
-                let temp_arg = format!("%t{}", index as u16);
-                fun_code += &format!("{} = call double @llvm.pow.f64(double {}, double {})\n", temp_arg, arg1, arg2);//fun_code += &format!("{} = call double @llvm.pow.f64(double {}, double {})\n", temp_arg, arg1, arg2);
-                result_stack.push(temp_arg);
+                let argument = inner.value(&builder);
+                let exponent = exponent.value(&builder);
+                let value = builder.call("llvm.pow.f64", &[argument, exponent], &format!("t{}", index));
+                let outer_terms = power_series_terms(&builder, argument, value, exponent, order, &name);
+                inner.compose(&builder, &outer_terms, fma, &name)
+            },
+            Func::Exact(subterm) if subterm.is_empty() => stack_pop_wrapper(&mut jet_stack),
+            Func::Exact(subterm) => Jet {
+                terms: symbolic_jet_terms(&builder, &postfix_to_tree(subterm), builder.argument(), order, options, &name)
             },
-            Func::X => result_stack.push(String::from("%x")),
-            Func::Const(value) => result_stack.push(format!("{:.15e}", value)),
+            Func::Ln | Func::Exp | Func::Sin | Func::Cos | Func::Tg | Func::Ctg | Func::Asin | Func::Acos | Func::Atg | Func::Actg => {
+                let inner = stack_pop_wrapper(&mut jet_stack);
+                let outer = Node::unary(elem.clone(), Node::from_func(Func::X));
+                let outer_terms = symbolic_jet_terms(&builder, &outer, inner.value(&builder), order, options, &name);
+                inner.compose(&builder, &outer_terms, fma, &name)
+            },
+            Func::X => Jet::variable(&builder, order),
+            Func::Const(value) => Jet::constant(builder.constant(*value), order),
             _ => { unrecoverable_error!("Taylor compilation | Encountered invalid element in provided sequence", elem); }
-        }
+        };
+
+        jet_stack.push(jet);
     }
 
-    let temp_addr = stack_pop_wrapper(&mut result_stack);
+    stack_pop_wrapper(&mut jet_stack).store_derivatives(&builder);
+    builder.ret_void();
+}
+
+/// Taylor coefitients of u^e around u, c_0 = u^e and c_j = c_(j-1)*(e-j+1)/(j*u).
+fn power_series_terms(builder: &FunctionBuilder, argument: LLVMValueRef, value: LLVMValueRef, exponent: LLVMValueRef, order: usize, name: &str) -> Vec<Option<LLVMValueRef>> {
+    let inverse = builder.fdiv(builder.constant(1.0), argument, &format!("{}_inv", name));
+    let mut terms = vec![Some(value)];
+    let mut previous = value;
+    for j in 1..=order {
+        let factor = builder.fsub(exponent, builder.constant((j-1) as f64), &format!("{}_e{}", name, j));
+        let factor = builder.fmul(factor, builder.constant(1.0/j as f64), &format!("{}_f{}", name, j));
+        let scaled = builder.fmul(previous, inverse, &format!("{}_u{}", name, j));
+        previous = builder.fmul(scaled, factor, &format!("{}_c{}", name, j));
+        terms.push(Some(previous));
+    }
+    terms
+}
 
-    format!("{}\ndefine double @fja(double %x){{\n{}ret double {}\n}}", instrinsic_declarations, fun_code, temp_addr)
+/// Textual IR of `build_module_from_taylor_sequence`, only meant for debugging.
+pub fn generate_ir_from_taylor_sequence(sequence: &[Func], options: &CompilationOptions) -> String {
+    build_module_from_taylor_sequence(sequence, options).dump()
 }
 
 pub fn generate_taylor_ir(function: &str, precision_center: f64, poly_degre: usize) -> String {
     let mut sequence = lex_function(function);
     convert_infix_to_postfix(&mut sequence);
+    expand_derivatives(&mut sequence);
     optimize_postfix_using_taylor(&mut sequence, precision_center, poly_degre);
     // let mut temp_str = String::new();
     // for elem in &sequence {
@@ -172,7 +335,7 @@ pub fn generate_taylor_ir(function: &str, precision_center: f64, poly_degre: usi
     //     temp_str += ",";
     // }
     // println!("{}", temp_str);
-    generate_ir_from_taylor_sequence(&sequence)
+    generate_ir_from_taylor_sequence(&sequence, &CompilationOptions::default())
 }
 
 // if let Func::Poly(poly) = &sequence[0] {
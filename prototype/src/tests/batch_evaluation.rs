@@ -0,0 +1,57 @@
+use crate::{
+    components::{
+        compilation_options::{CompilationOptions, FloatMode, TargetCpu},
+        executable_memory::ExecutableRegion
+    },
+    stages::{
+        compiler::Compiler,
+        linking::{link_buffer, link_batch_buffer, linked_size}
+    }
+};
+
+#[test]
+fn batch_0(){
+    let compiler = Compiler::default();
+    let (module, _) = compiler.build_taylor_module("sin(x)*exp(x)", 0.5, 7, &CompilationOptions::default());
+    let dump = module.dump();
+
+    assert!(dump.contains("@fja_batch("));
+    assert!(dump.contains("fmul <2 x double>"));
+    assert!(dump.contains("llvm.loop.isvectorized"));
+    // the scalar function is left as built
+    assert!(module.dump().contains("define double @fja(double %x)"));
+}
+
+#[test]
+fn batch_1(){
+    let compiler = Compiler::default();
+    let module = compiler.build_exact_module("sin(x)*exp(x)+ln(x)");
+    assert!(module.dump().contains("@fja_batch("));
+
+    // both entry points are resolved against the same placement of the object file
+    let (mut scalar_data, _) = compiler.emit_object(&module);
+    scalar_data.resize(linked_size(&scalar_data).max(scalar_data.len()), 0);
+    let mut batch_data = scalar_data.clone();
+    let space = ExecutableRegion::allocate(scalar_data.len());
+    let placement = space.as_ptr();
+
+    let scalar = link_buffer(&mut scalar_data, placement) as usize;
+    link_batch_buffer(&mut batch_data, placement);
+    assert_eq!(scalar_data, batch_data);
+    assert!(scalar >= placement.as_ptr() as usize && scalar < placement.as_ptr() as usize + space.len());
+}
+
+#[test]
+fn batch_2(){
+    // fja_batch is where fja ends up inlined, it needs the same target and fast-math attributes
+    let compiler = Compiler::new(&TargetCpu::Explicit { cpu: String::from("haswell"), features: String::from("+avx2,+fma") });
+    let options = CompilationOptions { float_mode: FloatMode::Fast, ..Default::default() };
+    let (module, _) = compiler.build_taylor_module("sin(x)*exp(x)", 0.5, 7, &options);
+
+    for (key, value) in [("target-cpu", "haswell"), ("target-features", "+avx2,+fma"), ("unsafe-fp-math", "true"), ("no-nans-fp-math", "true")] {
+        assert_eq!(module.function_attribute("fja_batch", key).as_deref(), Some(value), "{}", key);
+    }
+    let (module, _) = Compiler::default().build_taylor_module("sin(x)*exp(x)", 0.5, 7, &CompilationOptions::default());
+    assert!(module.function_attribute("fja_batch", "unsafe-fp-math").is_none());
+    assert_eq!(module.function_attribute("fja_batch", "target-cpu"), module.function_attribute("fja", "target-cpu"));
+}
@@ -0,0 +1,37 @@
+use crate::components::{
+    coefficient_tables::{binomial, factorial, inverse_factorial, BINOMIALS, FACTORIALS, TABLE_SIZE},
+    polynomials::TsPoly
+};
+
+#[test]
+fn tables_0(){
+    assert_eq!(FACTORIALS[0], 1);
+    assert_eq!(FACTORIALS[20], 2432902008176640000);
+    assert_eq!(FACTORIALS[29], 8841761993739701954543616000000);
+    assert_eq!(BINOMIALS[29][14], 77558760);
+    for n in 0..TABLE_SIZE {
+        assert_eq!(BINOMIALS[n][0], 1);
+        assert_eq!(BINOMIALS[n][n], 1);
+        for k in 0..=n {
+            assert_eq!(BINOMIALS[n][k], BINOMIALS[n][n-k]);
+        }
+    }
+}
+
+#[test]
+fn tables_1(){
+    // Past the end of the tables the values are computed in f64, so they don't overflow
+    assert_eq!(binomial(5, 7), 0.0);
+    assert_eq!(binomial(100, 3), 161700.0);
+    assert!((binomial(60, 30)/118264581564861424.0 - 1.0).abs() < 1e-14);
+    assert!((factorial(31)/(factorial(30)*31.0) - 1.0).abs() < 1e-15);
+    assert_eq!(inverse_factorial(3), 1.0/6.0);
+}
+
+#[test]
+fn tables_2(){
+    // (x-2)^3 = x^3 - 6x^2 + 12x - 8
+    let mut poly = TsPoly::from_vec(vec![0.0, 0.0, 0.0, 1.0], true);
+    poly.put_offset(2.0);
+    assert_eq!(&poly.coefs[..4], &[-8.0, 12.0, -6.0, 1.0]);
+}
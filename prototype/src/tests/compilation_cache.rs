@@ -0,0 +1,113 @@
+use crate::{
+    components::{
+        compilation_cache::{CacheKey, CompilationCache, CODEGEN_VERSION},
+        compilation_options::{CompilationOptions, FloatMode}
+    },
+    stages::{
+        binary_compile::TargetDescription,
+        compiler::Compiler
+    }
+};
+use super::library_export::test_directory;
+use std::{fs, path::PathBuf};
+
+fn entries(cache: &CompilationCache) -> Vec<PathBuf> {
+    fs::read_dir(cache.directory()).unwrap().map(|entry| entry.unwrap().path()).collect()
+}
+
+#[test]
+fn compilation_cache_0(){
+    let target = TargetDescription::generic();
+    let options = CompilationOptions::default();
+    let key = CacheKey::new("sin(x) * e^(x)", 0.5, 12, &target, &options);
+    assert_eq!(key, CacheKey::new("sin(x)*e^(x)", 0.5, 12, &target, &options));
+
+    let fast = CompilationOptions { float_mode: FloatMode::Fast, ..Default::default() };
+    let host = TargetDescription { cpu: String::from("haswell"), features: String::from("+avx2,+fma") };
+    for other in [
+        CacheKey::new("sin(x)*e^(x)", 0.5, 12, &target, &fast),
+        CacheKey::new("sin(x)*e^(x)", 0.25, 12, &target, &options),
+        CacheKey::new("sin(x)*e^(x)", 0.5, 13, &target, &options),
+        CacheKey::new("sin(x)*e^(x)", 0.5, 12, &host, &options),
+        CacheKey::new("sin(x)*e^(-x)", 0.5, 12, &target, &options)
+    ] {
+        assert_ne!(key.hash(), other.hash(), "{}", other.description());
+    }
+}
+
+#[test]
+fn compilation_cache_1(){
+    let cache = CompilationCache::new(test_directory("compilation_cache_1"));
+    let options = CompilationOptions { derivatives: 2, ..Default::default() };
+    let formula = "sin(x)*e^(x)+ln(x)";
+
+    let (compiled, report) = Compiler::default().with_cache(cache.clone()).compile_function(formula, 0.5, 12, &options);
+    assert!(!report.cached);
+    assert_eq!(entries(&cache).len(), 1);
+
+    // a new compiler, as in the next run of a batch job, links the stored object file
+    let compiler = Compiler::default().with_cache(cache.clone());
+    let (cached, report) = compiler.compile_function(formula, 0.5, 12, &options);
+    assert!(report.cached && report.pipeline.is_none());
+    let (batch, _) = compiler.compile_batch_function(formula, 0.5, 12, &options);
+    let (jet, report) = compiler.compile_jet_function(formula, 0.5, 12, &options);
+    assert!(report.cached);
+    assert_eq!(entries(&cache).len(), 1);
+
+    let (uncached_jet, _) = Compiler::default().compile_jet_function(formula, 0.5, 12, &options);
+    let xs = [0.3, 0.5, 0.7];
+    let mut ys = [0.0; 3];
+    batch.evaluate(&xs, &mut ys);
+    for (x, y) in xs.iter().zip(ys) {
+        assert_eq!(cached.call(*x).to_bits(), compiled.call(*x).to_bits());
+        assert_eq!(y.to_bits(), compiled.call(*x).to_bits());
+        assert_eq!(jet.evaluate(*x), uncached_jet.evaluate(*x));
+    }
+    fs::remove_dir_all(cache.directory()).unwrap();
+}
+
+#[test]
+fn compilation_cache_2(){
+    // an entry that doesn't hold the key it's named after is a miss and gets replaced
+    let cache = CompilationCache::new(test_directory("compilation_cache_2"));
+    let options = CompilationOptions::default();
+    let compiler = Compiler::default().with_cache(cache.clone());
+    let (compiled, _) = compiler.compile_function("cos(x)/(1+x)", 0.0, 10, &options);
+
+    let entry = entries(&cache).pop().unwrap();
+    fs::write(&entry, b"FJACACHE\x05\x00\x00\x00\x00\x00\x00\x00other").unwrap();
+    let key = CacheKey::new("cos(x)/(1+x)", 0.0, 10, compiler.target(), &options);
+    assert!(cache.lookup(&key).is_none());
+
+    let (recompiled, report) = compiler.compile_function("cos(x)/(1+x)", 0.0, 10, &options);
+    assert!(!report.cached);
+    assert!(cache.lookup(&key).is_some());
+    assert_eq!(recompiled.call(0.4).to_bits(), compiled.call(0.4).to_bits());
+    fs::remove_dir_all(cache.directory()).unwrap();
+}
+
+#[test]
+fn compilation_cache_3(){
+    // entries with a valid header and a truncated or foreign object file are misses instead of link errors
+    let cache = CompilationCache::new(test_directory("compilation_cache_3"));
+    let options = CompilationOptions::default();
+    let compiler = Compiler::default().with_cache(cache.clone());
+    let key = CacheKey::new("sin(x)+x", 0.5, 9, compiler.target(), &options);
+    assert!(key.description().contains(&format!("codegen {}\n", CODEGEN_VERSION)));
+    assert!(key.description().contains(&format!("llvm {}\n", env!("FJA_LLVM_VERSION"))));
+    let (compiled, _) = compiler.compile_function("sin(x)+x", 0.5, 9, &options);
+
+    let entry = entries(&cache).pop().unwrap();
+    let stored = fs::read(&entry).unwrap();
+    // magic, description length, description
+    let object_start = 16 + key.description().len();
+    for broken in [stored[..stored.len() - 100].to_vec(), [&stored[..object_start], b"not an object file"].concat()] {
+        fs::write(&entry, &broken).unwrap();
+        assert!(cache.lookup(&key).is_none());
+    }
+    let (recompiled, report) = compiler.compile_function("sin(x)+x", 0.5, 9, &options);
+    assert!(!report.cached);
+    assert_eq!(recompiled.call(0.3).to_bits(), compiled.call(0.3).to_bits());
+    assert_eq!(fs::read(&entry).unwrap(), stored);
+    fs::remove_dir_all(cache.directory()).unwrap();
+}
@@ -0,0 +1,58 @@
+use crate::{
+    components::compilation_options::CompilationOptions,
+    stages::{
+        compiler::Compiler,
+        linking::CompiledFunction
+    }
+};
+
+fn is_mapped(address: *mut u8) -> bool {
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+    let page_start = (address as usize & !(page-1)) as *mut libc::c_void;
+    unsafe { libc::msync(page_start, page, libc::MS_ASYNC) == 0 }
+}
+
+#[test]
+fn compiled_function_0(){
+    let compiler = Compiler::default();
+    let formulas = ["sin(x)", "e^(x)", "x*x+1", "ln(x)+cos(x)"];
+    let functions: Vec<CompiledFunction> = formulas.iter().map(|formula| compiler.compile_exact_function(formula)).collect();
+    drop(compiler);
+
+    for x in [0.5f64, 1.0, 2.0] {
+        let expected = [x.sin(), x.exp(), x*x+1.0, x.ln()+x.cos()];
+        for (function, expected) in functions.iter().zip(expected) {
+            assert!((function.call(x) - expected).abs() < 1e-15);
+        }
+    }
+    for (function, formula) in functions.iter().zip(formulas) {
+        assert_eq!(function.source(), Some(formula));
+        let region = function.region().unwrap();
+        assert!(function.code_size() > 0 && function.code_size() <= region.len());
+        assert!(region.is_executable());
+    }
+}
+
+#[test]
+fn compiled_function_1(){
+    // dropping a function unmaps its code, the others keep working
+    let compiler = Compiler::default();
+    let (first, report) = compiler.compile_function("sin(x)", 0.0, 12, &CompilationOptions::default());
+    let second = compiler.compile_exact_function("cos(x)");
+    assert_eq!(first.target(), &report.target);
+
+    let address = first.region().unwrap().as_ptr().as_ptr();
+    assert!(is_mapped(address));
+    drop(first);
+    assert!(!is_mapped(address));
+    assert!((second.call(0.3) - 0.3f64.cos()).abs() < 1e-15);
+}
+
+#[test]
+fn compiled_function_2(){
+    let compiler = Compiler::default();
+    let function = compiler.load(compiler.emit_object(&compiler.build_exact_module("x+2")));
+    assert_eq!(function.source(), None);
+    assert_eq!(function.call(1.0), 3.0);
+    assert!(format!("{:?}", function).starts_with("CompiledFunction"));
+}
@@ -0,0 +1,91 @@
+use crate::{
+    components::compilation_options::{CompilationOptions, PassPipeline, TargetCpu},
+    stages::{
+        binary_compile::{TargetDescription, generate_function, generate_function_with_options, shared_compiler, SHARED_COMPILER_LIMIT},
+        compiler::Compiler,
+        ir_compile::build_ir_function
+    }
+};
+
+#[test]
+fn compiler_0(){
+    let compiler = Compiler::default();
+    assert_eq!(compiler.target(), &TargetDescription::generic());
+
+    let options = CompilationOptions::default();
+    let (first, first_report) = compiler.build_taylor_module("sin(x)*exp(x)", 0.5, 7, &options);
+    let (second, _) = compiler.build_taylor_module("cos(x)/2", 0.5, 7, &options);
+    assert_eq!(first.context(), second.context());
+    assert!(first_report.pipeline.is_none());
+
+    for module in [&first, &second, &first] {
+        assert!(compiler.emit_object(module).1 > 0);
+    }
+}
+
+#[test]
+fn compiler_1(){
+    let compiler = Compiler::new(&TargetCpu::Host);
+    let options = CompilationOptions { pass_pipeline: PassPipeline::Default(2), ..Default::default() };
+    let (module, report) = compiler.build_taylor_module("sin(x)*exp(x)", 0.5, 7, &options);
+
+    assert_eq!(report.target, TargetDescription::host());
+    assert!(module.dump().contains(&format!("\"target-cpu\"=\"{}\"", report.target.cpu)));
+    let pipeline = report.pipeline.unwrap();
+    assert_eq!(pipeline.pipeline, "default<O2>");
+    assert!(pipeline.instructions_before > 0);
+    assert!(compiler.emit_object(&module).1 > 0);
+}
+
+#[test]
+fn compiler_2(){
+    let compiler = Compiler::default();
+    let exact = compiler.create_module("postfix");
+    build_ir_function(&exact, "ln(x)+sqrt(x)");
+    assert!(exact.dump().contains("@sqrt"));
+
+    let parsed = compiler.parse_module("define double @fja(double %x){\n%t = fadd double %x, %x\nret double %t\n}");
+    assert_eq!(parsed.context(), exact.context());
+
+    // modules outlive the compiler that created them
+    drop(compiler);
+    assert_eq!(parsed.instruction_count(), 2);
+    assert!(Compiler::default().emit_object(&exact).1 > 0);
+}
+
+#[test]
+fn compiler_3(){
+    // the free functions of a thread share one compiler per target
+    let generic = TargetDescription::generic();
+    let compiler = shared_compiler(&generic, None);
+    assert!(std::rc::Rc::ptr_eq(&compiler, &shared_compiler(&generic, None)));
+    assert!(!std::rc::Rc::ptr_eq(&compiler, &shared_compiler(&TargetDescription::host(), None)));
+
+    let first = generate_function("sin(x)*exp(x)", 0.5, 7);
+    let second = generate_function_with_options("sin(x)*exp(x)", 0.5, 7, &CompilationOptions::default());
+    let (direct, _) = Compiler::default().compile_function("sin(x)*exp(x)", 0.5, 7, &CompilationOptions::default());
+    for x in [0.2, 0.5, 0.8] {
+        assert_eq!(first.call(x).to_bits(), direct.call(x).to_bits());
+        assert_eq!(second.call(x).to_bits(), direct.call(x).to_bits());
+    }
+    assert!(std::rc::Rc::ptr_eq(&compiler, &shared_compiler(&generic, None)));
+}
+
+#[test]
+fn compiler_4(){
+    // the least recently used shared compiler makes room once a thread has used SHARED_COMPILER_LIMIT targets
+    let generic = TargetDescription::generic();
+    let first = shared_compiler(&generic, None);
+    let cpus = ["x86-64", "core2", "nehalem", "westmere", "sandybridge", "ivybridge", "haswell", "broadwell", "skylake"];
+    let targets: Vec<TargetDescription> = cpus.iter().map(|cpu| TargetDescription { cpu: cpu.to_string(), features: String::new() }).collect();
+    let kept = shared_compiler(&targets[0], None);
+    for target in &targets[1..SHARED_COMPILER_LIMIT - 1] {
+        shared_compiler(target, None);
+    }
+    // using it again makes it the most recently used one
+    assert!(std::rc::Rc::ptr_eq(&kept, &shared_compiler(&targets[0], None)));
+    shared_compiler(&targets[SHARED_COMPILER_LIMIT - 1], None);
+
+    assert!(!std::rc::Rc::ptr_eq(&first, &shared_compiler(&generic, None)));
+    assert!(std::rc::Rc::ptr_eq(&kept, &shared_compiler(&targets[0], None)));
+}
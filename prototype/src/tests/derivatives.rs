@@ -0,0 +1,70 @@
+use crate::{
+    components::{
+        compilation_options::{CompilationOptions, ApproximationMode},
+        polynomials::TsPoly
+    },
+    stages::compiler::Compiler
+};
+
+#[test]
+fn derivatives_0(){
+    // p = 1 + 2x + 3x^2 + 4x^3
+    let poly = TsPoly::from_vec(vec![1.0, 2.0, 3.0, 4.0], true);
+    assert_eq!(poly.normalized_derivative(1).coefs[..3], [2.0, 6.0, 12.0]);
+    assert_eq!(poly.normalized_derivative(2).coefs[..2], [3.0, 12.0]);
+    assert_eq!(poly.normalized_derivative(3).coefs[0], 4.0);
+    assert_eq!(poly.normalized_derivative(3).max_pow, 0);
+    assert_eq!(poly.normalized_derivative(5).coefs[0], 0.0);
+
+    let mut shifted = poly.clone();
+    shifted.center = 0.5;
+    let derivative = shifted.normalized_derivative(1);
+    assert_eq!(derivative.center, 0.5);
+    assert!(derivative.from_x);
+}
+
+#[test]
+fn derivatives_1(){
+    let compiler = Compiler::default();
+    let options = CompilationOptions { derivatives: 3, ..Default::default() };
+    let (module, _) = compiler.build_taylor_module("sin(x)*exp(x)", 0.5, 7, &options);
+    let dump = module.dump();
+
+    assert!(dump.contains("define void @fja_jet(double %x, double* %out)"));
+    for index in 0..=3 {
+        assert!(dump.contains(&format!("%out{} = getelementptr", index)));
+    }
+    assert!(!dump.contains("%out4"));
+    assert!(compiler.emit_object(&module).1 > 0);
+
+    let (module, _) = compiler.build_taylor_module("sin(x)*exp(x)", 0.5, 7, &CompilationOptions::default());
+    assert!(!module.dump().contains("fja_jet"));
+}
+
+#[test]
+fn derivatives_2(){
+    let compiler = Compiler::default();
+    for options in [
+        CompilationOptions { derivatives: 2, range_reduction: true, ..Default::default() },
+        CompilationOptions { derivatives: 2, approximation: ApproximationMode::Pade { denominator_degree: 2 }, ..Default::default() },
+    ] {
+        let (module, _) = compiler.build_taylor_module("ln(x)*cos(x)/sqrt(x)+x^2.5", 1.0, 8, &options);
+        assert!(module.verify().is_ok());
+        assert!(compiler.emit_object(&module).1 > 0);
+    }
+}
+
+#[test]
+#[should_panic]
+fn derivatives_3(){
+    // exponent depends on x
+    let options = CompilationOptions { derivatives: 1, ..Default::default() };
+    Compiler::default().build_taylor_module("sqrt(x)^x", 1.0, 8, &options);
+}
+
+#[test]
+#[should_panic]
+fn derivatives_4(){
+    // without derivatives there is no jet function to load
+    Compiler::default().compile_jet_function("sin(x)", 0.5, 8, &CompilationOptions::default());
+}
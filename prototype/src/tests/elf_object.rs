@@ -0,0 +1,163 @@
+use crate::{
+    components::{
+        elf_object::*,
+        executable_memory::ExecutableRegion
+    },
+    stages::{
+        compiler::Compiler,
+        linking::link_buffer
+    }
+};
+
+/// (name, type, flags, contents, link, info)
+type SectionSpec = (&'static str, u32, u64, Vec<u8>, u32, u32);
+
+/// Relocatable object with the given sections after the null section, names of sections and symbols share `.strtab`.
+pub(super) fn build_object(sections: Vec<SectionSpec>, symbols: &[(&str, u8, u16, u64)], first_global: u32) -> Vec<u8> {
+    let mut strtab = vec![0u8];
+    let mut add_name = |name: &str| {
+        let offset = strtab.len() as u32;
+        strtab.extend_from_slice(name.as_bytes());
+        strtab.push(0);
+        offset
+    };
+
+    let mut symtab = vec![0u8; 24];
+    for (name, info, section, value) in symbols {
+        symtab.extend_from_slice(&add_name(name).to_le_bytes());
+        symtab.extend_from_slice(&[*info, 0]);
+        symtab.extend_from_slice(&section.to_le_bytes());
+        symtab.extend_from_slice(&value.to_le_bytes());
+        symtab.extend_from_slice(&0u64.to_le_bytes());
+    }
+    let section_names: Vec<u32> = sections.iter().map(|section| add_name(section.0)).collect();
+    let strtab_name = add_name(".strtab");
+    let symtab_name = add_name(".symtab");
+
+    let strtab_index = sections.len() as u32 + 1;
+    let mut all = vec![(0, 0, 0, Vec::new(), 0, 0)];
+    all.extend(sections.into_iter().zip(section_names).map(|(section, name)| (name, section.1, section.2, section.3, section.4, section.5)));
+    all.push((strtab_name, SHT_STRTAB, 0, strtab, 0, 0));
+    all.push((symtab_name, SHT_SYMTAB, 0, symtab, strtab_index, first_global));
+
+    let mut file = vec![0u8; 0x40];
+    let mut headers = Vec::new();
+    for (name, kind, flags, contents, link, info) in &all {
+        while !file.len().is_multiple_of(16) {
+            file.push(0);
+        }
+        let mut header = vec![0u8; 0x40];
+        header[0..4].copy_from_slice(&name.to_le_bytes());
+        header[4..8].copy_from_slice(&kind.to_le_bytes());
+        header[8..16].copy_from_slice(&flags.to_le_bytes());
+        header[0x18..0x20].copy_from_slice(&(file.len() as u64).to_le_bytes());
+        header[0x20..0x28].copy_from_slice(&(contents.len() as u64).to_le_bytes());
+        header[0x28..0x2C].copy_from_slice(&link.to_le_bytes());
+        header[0x2C..0x30].copy_from_slice(&info.to_le_bytes());
+        header[0x30..0x38].copy_from_slice(&16u64.to_le_bytes());
+        if *kind == SHT_SYMTAB || *kind == SHT_RELA {
+            header[0x38..0x40].copy_from_slice(&24u64.to_le_bytes());
+        }
+        headers.extend(header);
+        file.extend_from_slice(contents);
+    }
+    while !file.len().is_multiple_of(16) {
+        file.push(0);
+    }
+
+    let section_table = file.len() as u64;
+    file[0..4].copy_from_slice(&[0x7F, b'E', b'L', b'F']);
+    file[4] = 2;
+    file[5] = 1;
+    file[6] = 1;
+    file[0x10..0x12].copy_from_slice(&1u16.to_le_bytes());
+    file[0x12..0x14].copy_from_slice(&62u16.to_le_bytes());
+    file[0x28..0x30].copy_from_slice(&section_table.to_le_bytes());
+    file[0x34..0x36].copy_from_slice(&0x40u16.to_le_bytes());
+    file[0x3A..0x3C].copy_from_slice(&0x40u16.to_le_bytes());
+    file[0x3C..0x3E].copy_from_slice(&(all.len() as u16).to_le_bytes());
+    file[0x3E..0x40].copy_from_slice(&(strtab_index as u16).to_le_bytes());
+    file.extend(headers);
+    file
+}
+
+pub(super) fn rela(entries: &[(u64, u64, u32, i64)]) -> Vec<u8> {
+    entries.iter().flat_map(|(offset, symbol, kind, addend)| {
+        [offset.to_le_bytes(), ((symbol << 32) | *kind as u64).to_le_bytes(), addend.to_le_bytes()].concat()
+    }).collect()
+}
+
+/// fja(x) = helper(x) + c[1], helper(x) = x + c[0], each function in its own .text.* section, c in .rodata.cst16.
+fn two_text_sections_object() -> Vec<u8> {
+    // addsd xmm0, [rip+c]; ret
+    let helper = vec![0xF2, 0x0F, 0x58, 0x05, 0, 0, 0, 0, 0xC3];
+    // call helper; addsd xmm0, [rip+c+8]; ret
+    let fja = vec![0xE8, 0, 0, 0, 0, 0xF2, 0x0F, 0x58, 0x05, 0, 0, 0, 0, 0xC3];
+    let constants = [1.5f64.to_le_bytes(), 2.25f64.to_le_bytes()].concat();
+
+    build_object(
+        vec![
+            (".text.helper", 1, SHF_ALLOC | SHF_EXECINSTR, helper, 0, 0),
+            (".text.fja", 1, SHF_ALLOC | SHF_EXECINSTR, fja, 0, 0),
+            (".rodata.cst16", 1, SHF_ALLOC | 0x10, constants, 0, 0),
+            (".rela.text.helper", SHT_RELA, 0x40, rela(&[(4, 1, R_X86_64_PC32, -4)]), 7, 1),
+            (".rela.text.fja", SHT_RELA, 0x40, rela(&[(1, 2, R_X86_64_PLT32, -4), (9, 1, R_X86_64_PC32, 4)]), 7, 2)
+        ],
+        // .rodata.cst16 section symbol, helper and fja
+        &[("", STT_SECTION, 3, 0), ("helper", 0x12, 1, 0), ("fja", 0x12, 2, 0)],
+        2
+    )
+}
+
+#[test]
+fn elf_object_0(){
+    let compiler = Compiler::default();
+    let (object_file, _) = compiler.emit_object(&compiler.build_exact_module("sin(x)*2.5"));
+    let object = ElfObject::parse(&object_file);
+
+    let fja = object.defined_symbol("fja").unwrap();
+    assert!(object.sections[fja.section].is_executable());
+    assert!(object.symbols.iter().any(|symbol| symbol.name == "sin" && !symbol.is_defined()));
+    assert!(object.relocation_sections.iter().any(|relocations| relocations.target == fja.section));
+    assert!(object.defined_symbol("sin").is_none());
+}
+
+#[test]
+fn elf_object_1(){
+    let object_file = two_text_sections_object();
+    let object = ElfObject::parse(&object_file);
+    assert_eq!(object.section_index(".rodata.cst16"), Some(3));
+    assert_eq!(object.relocation_sections.len(), 2);
+    assert_eq!(object.relocation_sections[1].relocations[1], Relocation { offset: 9, symbol: 1, kind: R_X86_64_PC32, addend: 4 });
+
+    let mut buffer = object_file.clone();
+    let mut region = ExecutableRegion::allocate(buffer.len());
+    let function = link_buffer(&mut buffer, region.as_ptr());
+    region.write(0, &buffer);
+    region.make_executable();
+    assert_eq!(function(1.0), 4.75);
+}
+
+#[test]
+#[should_panic]
+fn elf_object_2(){
+    let object_file = two_text_sections_object();
+    ElfObject::parse(&object_file[..object_file.len() - 8]);
+}
+
+#[test]
+#[should_panic]
+fn elf_object_3(){
+    let mut object_file = two_text_sections_object();
+    object_file[1] = b'X';
+    ElfObject::parse(&object_file);
+}
+
+#[test]
+#[should_panic]
+fn elf_object_4(){
+    // e_shoff near the end of the address space, the end of the section header table overflows
+    let mut object_file = two_text_sections_object();
+    object_file[0x28..0x30].copy_from_slice(&(u64::MAX - 0x10).to_le_bytes());
+    ElfObject::parse(&object_file);
+}
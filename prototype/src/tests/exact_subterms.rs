@@ -0,0 +1,79 @@
+use crate::{
+    components::{
+        object_type_definitions::Func,
+        compilation_options::CompilationOptions,
+        taylor_optimizer::collapse_exact_subterms
+    },
+    stages::{
+        function_lexing::{lex_function, convert_infix_to_postfix},
+        ir_compile::generate_ir,
+        compiler::Compiler,
+        binary_compile::generate_function
+    }
+};
+
+#[test]
+fn exact_subterms_0(){
+    let mut sequence = lex_function("sin(x)+exact(ln(x+1)*exact(x))");
+    convert_infix_to_postfix(&mut sequence);
+    collapse_exact_subterms(&mut sequence);
+
+    let exact = vec![Func::X, Func::Const(1.0), Func::Add, Func::Ln, Func::X, Func::Mul];
+    assert_eq!(sequence, vec![Func::X, Func::Sin, Func::Exact(exact), Func::Add]);
+    assert_eq!(sequence[2].to_string(), "exact(ln(x+1)*x)");
+}
+
+#[test]
+fn exact_subterms_1(){
+    let compiler = Compiler::default();
+    let (module, _) = compiler.build_taylor_module("sin(x)*exact(ln(x))", 0.7, 9, &CompilationOptions::default());
+    let dump = module.dump();
+    assert!(dump.contains("call double @ln(double %x)"));
+    assert!(!dump.contains("@sin"));
+
+    // functions the optimizer doesn't approximate are calls as well
+    let (module, _) = compiler.build_taylor_module("exact(tg(x)+sqrt(x))-cos(x)", 0.7, 9, &CompilationOptions::default());
+    let dump = module.dump();
+    assert!(dump.contains("@tan"));
+    assert!(dump.contains("@sqrt"));
+    assert!(!dump.contains("@cos"));
+
+    let options = CompilationOptions { derivatives: 2, ..Default::default() };
+    let (module, _) = compiler.build_taylor_module("sin(x)*exact(actg(x))", 0.7, 9, &options);
+    assert!(module.dump().contains("define void @fja_jet(double %x, double* %out)"));
+    assert!(compiler.emit_object(&module).1 > 0);
+}
+
+#[test]
+fn exact_subterms_2(){
+    // the whole function is already exact, so the marker changes nothing but the register names
+    let ir = generate_ir("exact(sin(x))*sqrt(x)+sin(x)");
+    assert_eq!(ir.matches("call double @sin").count(), 1);
+    assert_eq!(ir.matches("call double @sqrt").count(), 1);
+    assert!(!ir.contains("llvm.sqrt"));
+}
+
+#[test]
+fn exact_subterms_3(){
+    // functions without a Taylor generator are evaluated exactly in Taylor mode without an exact(...) marker
+    let exact = [
+        ("tg(x)", f64::tan as fn(f64) -> f64), ("ctg(x)", |x| 1.0/x.tan()), ("atg(x)", f64::atan),
+        ("actg(x)", |x| std::f64::consts::FRAC_PI_2 - x.atan()), ("asin(x)", f64::asin), ("acos(x)", f64::acos)
+    ];
+    for (formula, expected) in exact {
+        let function = generate_function(formula, 0.5, 10);
+        for x in [0.2, 0.5, 0.7] {
+            assert!((function.call(x) - expected(x)).abs() <= 2.0*f64::EPSILON*expected(x).abs(), "{} at {}", formula, x);
+        }
+    }
+
+    let function = generate_function("sin(x)*tg(x)+asin(x/2)+sqrt(1/x)", 0.5, 14);
+    for x in [0.3f64, 0.5, 0.6] {
+        let expected = x.sin()*x.tan() + (x/2.0).asin() + (1.0/x).sqrt();
+        assert!((function.call(x) - expected).abs() <= 1e-9, "{} != {}", function.call(x), expected);
+    }
+    let mut sequence = lex_function("sin(x)*tg(x)");
+    convert_infix_to_postfix(&mut sequence);
+    collapse_exact_subterms(&mut sequence);
+    assert_eq!(sequence, vec![Func::X, Func::Sin, Func::Exact(vec![Func::X, Func::Tg]), Func::Mul]);
+}
@@ -0,0 +1,55 @@
+use crate::{
+    components::{
+        executable_memory::ExecutableRegion,
+        compilation_options::CompilationOptions
+    },
+    stages::{
+        compiler::Compiler,
+        binary_compile::generate_function
+    }
+};
+
+#[test]
+fn executable_memory_0(){
+    // mov rax, 42; ret
+    let code = [0x48, 0xC7, 0xC0, 0x2A, 0x00, 0x00, 0x00, 0xC3];
+    let mut region = ExecutableRegion::allocate(code.len());
+    assert!(region.len() >= code.len());
+    assert!(!region.is_executable());
+    region.write(0, &code);
+    region.make_executable();
+    assert!(region.is_executable());
+
+    let function: extern "C" fn() -> u64 = unsafe { std::mem::transmute(region.as_ptr().as_ptr()) };
+    assert_eq!(function(), 42);
+}
+
+#[test]
+#[should_panic]
+fn executable_memory_1(){
+    let mut region = ExecutableRegion::with_code(&[0xC3]);
+    region.write(0, &[0x90]);
+}
+
+#[test]
+fn executable_memory_2(){
+    // every function gets its own region, so earlier functions stay valid
+    let compiler = Compiler::default();
+    let (taylor, _) = compiler.compile_function("sin(x)*e^(x)", 0.5, 12, &CompilationOptions::default());
+    let exact = compiler.compile_exact_function("sin(x)+ln(x)");
+    let batch = compiler.compile_exact_batch_function("x*x+1");
+
+    for x in [0.3, 0.5, 0.7] {
+        assert!((taylor.call(x) - x.sin()*x.exp()).abs() < 1e-9);
+        assert!((exact.call(x) - (x.sin() + x.ln())).abs() < 1e-15);
+    }
+    let mut ys = [0.0; 3];
+    batch.evaluate(&[1.0, 2.0, 3.0], &mut ys);
+    assert_eq!(ys, [2.0, 5.0, 10.0]);
+
+    // functions outlive their compiler
+    drop(compiler);
+    assert!((taylor.call(0.4) - 0.4f64.sin()*0.4f64.exp()).abs() < 1e-9);
+    let function = generate_function("e^(x)", 0.0, 12);
+    assert!((function.call(0.1) - 0.1f64.exp()).abs() < 1e-12);
+}
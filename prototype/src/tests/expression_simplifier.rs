@@ -0,0 +1,57 @@
+use crate::{
+    components::{
+        expression_simplifier::simplify_postfix,
+        symbolic_differentiation::postfix_to_tree
+    },
+    stages::{
+        function_lexing::{lex_function, convert_infix_to_postfix},
+        ir_compile::generate_ir
+    }
+};
+
+fn simplified(function: &str) -> String {
+    let mut sequence = lex_function(function);
+    convert_infix_to_postfix(&mut sequence);
+    simplify_postfix(&mut sequence);
+    postfix_to_tree(&sequence).to_string()
+}
+
+#[test]
+fn expression_simplifier_0(){
+    assert_eq!(simplified("e^(x)/e^(x)"), "1");
+    assert_eq!(simplified("sin(x)*sin(x)+cos(x)*cos(x)"), "1");
+    assert_eq!(simplified("cos(2*x)^2+sin(2*x)^2"), "1");
+    assert_eq!(simplified("x-x+x^1"), "x");
+    assert_eq!(simplified("ln(x)*0+cos(2*3)"), format!("{}", 6.0f64.cos()));
+    assert_eq!(simplified("e^(ln(x+1))"), "x+1");
+    assert_eq!(simplified("ln(x)+ln(x)"), "2*ln(x)");
+    assert_eq!(simplified("2+3*x"), "2+3*x");
+    assert_eq!(simplified("sin(x)*cos(x)+cos(x)*cos(x)"), "sin(x)*cos(x)+cos(x)*cos(x)");
+}
+
+#[test]
+fn expression_simplifier_1(){
+    for function in ["sin(x)*ln(x)+x*1", "e^(x*x)/sqrt(x)-0", "tg(2*x)^1-ctg(x)", "(x+1)^0.5*cos(x)^3+2*(3*x)"] {
+        let mut sequence = lex_function(function);
+        convert_infix_to_postfix(&mut sequence);
+        let tree = postfix_to_tree(&sequence);
+        let simplified = tree.simplified();
+        for x in [0.3, 0.7, 1.1] {
+            assert!((tree.evaluate(x) - simplified.evaluate(x)).abs() < 1e-12, "{} simplified to {}", function, simplified);
+        }
+    }
+}
+
+#[test]
+fn expression_simplifier_2(){
+    let ir = generate_ir("sin(x)*sin(x)+cos(x)");
+    assert_eq!(ir.matches("@sin(").count(), 2); // declaration and a single call
+    assert_eq!(ir.matches("@cos(").count(), 2);
+
+    let ir = generate_ir("e^(x+1)*ln(e^(x+1)+2)");
+    assert_eq!(ir.matches("fadd double %x, 1").count(), 1);
+    assert_eq!(ir.matches("@exp(").count(), 2);
+
+    let ir = generate_ir("sin(x)*sin(x)+cos(x)*cos(x)");
+    assert!(!ir.contains("call"));
+}
@@ -0,0 +1,69 @@
+use crate::{
+    components::{
+        compilation_options::{CompilationOptions, EvaluationScheme, FloatMode},
+        taylor_optimizer::optimize_postfix_using_taylor
+    },
+    stages::{
+        compiler::Compiler,
+        taylor_ir_compile::build_module_from_taylor_sequence
+    }
+};
+use super::lexing_and_postfix::postfix;
+
+const MODES: [FloatMode; 3] = [FloatMode::Strict, FloatMode::Contract, FloatMode::Fast];
+
+/// Instructions left after `default<O3>`, and the compiled function at `points`.
+fn optimized_with(function: &str, approximated: bool, options: &CompilationOptions, points: &[f64]) -> (usize, Vec<f64>) {
+    let mut sequence = postfix(function);
+    if approximated {
+        optimize_postfix_using_taylor(&mut sequence, 0.5, 12);
+    }
+    let compiler = Compiler::default();
+    let module = build_module_from_taylor_sequence(&sequence, options);
+    compiler.optimize(&module, "default<O3>");
+    let compiled = compiler.load(compiler.emit_object(&module));
+    (module.instruction_count(), points.iter().map(|x| compiled.call(*x)).collect())
+}
+
+fn with_mode(float_mode: FloatMode) -> CompilationOptions {
+    CompilationOptions { float_mode, ..Default::default() }
+}
+
+#[test]
+fn float_mode_0(){
+    // (x+1)+2 may only become x+3 with reassociation, which rounds differently at 2^53
+    let big = 2f64.powi(53);
+    let points = [0.1, big];
+    let [strict, contract, fast] = MODES.map(|mode| optimized_with("x+1+2", false, &with_mode(mode), &points));
+    assert_eq!(strict.0, contract.0);
+    assert!(fast.0 < contract.0, "{} {}", fast.0, contract.0);
+    assert_eq!(strict.1, [0.1 + 1.0 + 2.0, big + 2.0]);
+    assert_eq!(fast.1, [0.1 + 3.0, big + 4.0]);
+}
+
+#[test]
+fn float_mode_1(){
+    // x-x is 0 only without NaNs and infinities
+    let points = [0.7, f64::INFINITY];
+    let [strict, contract, fast] = MODES.map(|mode| optimized_with("x-x", false, &with_mode(mode), &points));
+    assert_eq!(strict.0, contract.0);
+    assert!(fast.0 < contract.0, "{} {}", fast.0, contract.0);
+    assert!(strict.1[0] == 0.0 && strict.1[1].is_nan() && contract.1[1].is_nan());
+    assert_eq!(fast.1, [0.0, 0.0]);
+}
+
+#[test]
+fn float_mode_2(){
+    let points = [0.1, 0.5, 0.9];
+    for evaluation_scheme in [EvaluationScheme::SharedPowers, EvaluationScheme::Horner, EvaluationScheme::Estrin] {
+        let [strict, contract, fast] = MODES.map(|float_mode| {
+            let options = CompilationOptions { float_mode, evaluation_scheme, ..Default::default() };
+            optimized_with("sin(x)*e^(x)+cos(x)*e^(x)", true, &options, &points)
+        });
+        // multiply-adds fused into llvm.fmuladd
+        assert!(contract.0 < strict.0, "{:?} {} {}", evaluation_scheme, contract.0, strict.0);
+        for (other, expected) in contract.1.iter().chain(&fast.1).zip(strict.1.iter().cycle()) {
+            assert!((other - expected).abs() <= 1e-13*expected.abs(), "{:?} {} {}", evaluation_scheme, other, expected);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{
+    components::{
+        compilation_options::{CompilationOptions, EvaluationScheme},
+        ir_builder::IrModule,
+        taylor_optimizer::optimize_postfix_using_taylor
+    },
+    stages::{
+        function_lexing::{lex_function, convert_infix_to_postfix},
+        ir_compile::build_ir_module,
+        taylor_ir_compile::build_module_from_taylor_sequence
+    }
+};
+
+#[test]
+fn builder_0(){
+    let module = build_ir_module("(x-2)/sin(x)");
+    assert!(module.verify().is_ok());
+    let dump = module.dump();
+    assert!(dump.contains("define double @fja(double %x)"));
+    assert!(dump.contains("declare double @sin(double)"));
+    // operands of non commutative operations keep their order
+    assert!(dump.contains("fsub double %x, 2.000000e+00"));
+    assert!(dump.contains("fdiv double %t2, %t4"), "{}", dump);
+}
+
+#[test]
+fn builder_1(){
+    let module = build_ir_module("ctg(x)+actg(x)*ln(x)");
+    assert!(module.verify().is_ok());
+    let dump = module.dump();
+    assert!(dump.contains("declare double @tan(double)"));
+    assert!(dump.contains("declare double @atan(double)"));
+    assert!(dump.contains("declare double @ln(double)"));
+}
+
+#[test]
+fn builder_2(){
+    for evaluation_scheme in [EvaluationScheme::SharedPowers, EvaluationScheme::Horner, EvaluationScheme::Estrin] {
+        for fused_multiply_add in [false, true] {
+            let mut sequence = lex_function("sin(x)*exp(x)+sqrt(x)");
+            convert_infix_to_postfix(&mut sequence);
+            optimize_postfix_using_taylor(&mut sequence, 0.5, 7);
+
+            let options = CompilationOptions { evaluation_scheme, fused_multiply_add, ..Default::default() };
+            let module = build_module_from_taylor_sequence(&sequence, &options);
+            assert!(module.verify().is_ok(), "{}", module.dump());
+            assert_eq!(module.dump().contains("@llvm.fma.f64"), fused_multiply_add);
+        }
+    }
+}
+
+#[test]
+fn builder_3(){
+    let module = IrModule::parse("define double @fja(double %x){\n%t = fmul double %x, %x\nret double %t\n}");
+    assert!(module.verify().is_ok());
+    assert!(module.dump().contains("fmul double %x, %x"));
+}
@@ -1,8 +1,18 @@
-use crate::stages::function_lexing::{
-    lex_function,
-    convert_infix_to_postfix
+use crate::{
+    components::object_type_definitions::Func,
+    stages::function_lexing::{
+        lex_function,
+        convert_infix_to_postfix
+    }
 };
 
+/// Formula lexed and converted to postfix, the starting point of the optimizer fixtures.
+pub(super) fn postfix(function: &str) -> Vec<Func> {
+    let mut sequence = lex_function(function);
+    convert_infix_to_postfix(&mut sequence);
+    sequence
+}
+
 #[test]
 fn gen_1(){
     let function = String::from("sin(7.56*x)*e^(x+1)-tg(x-8)/cos(x)");
@@ -0,0 +1,209 @@
+use crate::{
+    components::{
+        compilation_options::CompilationOptions,
+        elf_object::ElfObject,
+        shared_object::write_shared_object,
+        symbol_resolver::register_external_function
+    },
+    stages::{
+        compiler::Compiler,
+        library_export::{export_function, export_module, ExportFormat}
+    }
+};
+use std::{
+    ffi::CString,
+    fs,
+    path::{Path, PathBuf},
+    process::Command
+};
+
+/// Empty directory of the test under the system temporary directory, also used by the compilation cache tests.
+pub(super) fn test_directory(test: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("fja_{}_{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Opened with RTLD_LOCAL and never closed, the tests only look up a few symbols.
+fn open_library(path: &Path) -> *mut libc::c_void {
+    let path = CString::new(path.to_str().unwrap()).unwrap();
+    let library = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    assert!(!library.is_null(), "{:?}", unsafe { std::ffi::CStr::from_ptr(libc::dlerror()) });
+    library
+}
+
+fn symbol(library: *mut libc::c_void, name: &str) -> *mut libc::c_void {
+    let name = CString::new(name).unwrap();
+    let address = unsafe { libc::dlsym(library, name.as_ptr()) };
+    assert!(!address.is_null());
+    address
+}
+
+#[test]
+fn library_export_0(){
+    let directory = test_directory("library_export_0");
+    let output = directory.join("libkernel.so");
+    let options = CompilationOptions { derivatives: 2, ..Default::default() };
+    let formula = "sin(x)*e^(x)+ln(x)";
+    export_function(formula, 0.5, 12, &options, "kernel", ExportFormat::SharedLibrary, &output);
+
+    let compiler = Compiler::default();
+    let (function, _) = compiler.compile_function(formula, 0.5, 12, &options);
+    let (jet, _) = compiler.compile_jet_function(formula, 0.5, 12, &options);
+
+    let library = open_library(&output);
+    let kernel = unsafe { std::mem::transmute::<*mut libc::c_void, extern "C" fn(f64) -> f64>(symbol(library, "kernel")) };
+    let kernel_batch = unsafe { std::mem::transmute::<*mut libc::c_void, extern "C" fn(*const f64, *mut f64, usize)>(symbol(library, "kernel_batch")) };
+    let kernel_jet = unsafe { std::mem::transmute::<*mut libc::c_void, extern "C" fn(f64, *mut f64)>(symbol(library, "kernel_jet")) };
+
+    let xs = [0.3, 0.5, 0.7, 0.9];
+    let mut ys = [0.0; 4];
+    kernel_batch(xs.as_ptr(), ys.as_mut_ptr(), xs.len());
+    for (x, y) in xs.iter().zip(ys) {
+        assert_eq!(kernel(*x).to_bits(), function.call(*x).to_bits());
+        assert_eq!(y.to_bits(), function.call(*x).to_bits());
+
+        let mut derivatives = [0.0; 3];
+        kernel_jet(*x, derivatives.as_mut_ptr());
+        assert_eq!(derivatives.to_vec(), jet.evaluate(*x));
+    }
+
+    let header = fs::read_to_string(directory.join("libkernel.h")).unwrap();
+    assert!(header.contains("double kernel(double x);"));
+    assert!(header.contains("void kernel_batch(const double* KERNEL_RESTRICT xs, double* KERNEL_RESTRICT ys, size_t n);"));
+    assert!(header.contains("#define KERNEL_RESTRICT restrict") && header.contains("must not overlap"));
+    assert!(header.contains("void kernel_jet(double x, double* out);"));
+    assert!(header.contains(formula) && header.contains("k = 0, ..., 2"));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn library_export_1(){
+    // the shared object writer used when there's no system linker, libm is reached through GOT entries filled by the loader
+    let directory = test_directory("library_export_1");
+    let compiler = Compiler::default();
+    let formula = "ln(x)*cos(x)+exact(atg(x))";
+    let object_path = directory.join("exact.o");
+    export_module(&compiler, &compiler.build_exact_module(formula), "exact_kernel", ExportFormat::Object, &object_path);
+
+    let output = directory.join("libexact.so");
+    fs::write(&output, write_shared_object(&fs::read(&object_path).unwrap(), "libexact.so", &["libm.so.6"])).unwrap();
+    let library = open_library(&output);
+    let exact_kernel = unsafe { std::mem::transmute::<*mut libc::c_void, extern "C" fn(f64) -> f64>(symbol(library, "exact_kernel")) };
+    let exact_kernel_batch = unsafe { std::mem::transmute::<*mut libc::c_void, extern "C" fn(*const f64, *mut f64, usize)>(symbol(library, "exact_kernel_batch")) };
+
+    let exact = compiler.compile_exact_function(formula);
+    let xs = [0.25, 0.5, 2.0];
+    let mut ys = [0.0; 3];
+    exact_kernel_batch(xs.as_ptr(), ys.as_mut_ptr(), xs.len());
+    for (x, y) in xs.iter().zip(ys) {
+        assert_eq!(exact_kernel(*x).to_bits(), exact.call(*x).to_bits());
+        assert_eq!(y.to_bits(), exact.call(*x).to_bits());
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn library_export_2(){
+    if Command::new("cc").arg("--version").output().is_err() {
+        return;
+    }
+    let directory = test_directory("library_export_2");
+    let options = CompilationOptions::default();
+    export_function("sqrt(x)/(1+x*x)", 1.0, 10, &options, "rational", ExportFormat::StaticArchive, &directory.join("librational.a"));
+
+    // the batch call checks that the restrict qualified declaration compiles as C
+    let program = "#include <stdio.h>\n#include \"librational.h\"\nint main(void) { double xs[1] = {1.25}, ys[1]; rational_batch(xs, ys, 1); printf(\"%.17g\\n\", ys[0]); return 0; }\n";
+    fs::write(directory.join("main.c"), program).unwrap();
+    let compiled = Command::new("cc").current_dir(&directory)
+        .args(["main.c", "-o", "main", "-L.", "-lrational", "-lm"])
+        .output().unwrap();
+    assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+
+    let printed = Command::new(directory.join("main")).output().unwrap();
+    let value: f64 = String::from_utf8_lossy(&printed.stdout).trim().parse().unwrap();
+    let (function, _) = Compiler::default().compile_function("sqrt(x)/(1+x*x)", 1.0, 10, &options);
+    assert_eq!(value.to_bits(), function.call(1.25).to_bits());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+extern "C" fn triple(x: f64) -> f64 {
+    3.0*x
+}
+
+#[test]
+fn library_export_3(){
+    register_external_function("triple", triple);
+    let directory = test_directory("library_export_3");
+    let output = directory.join("kernel.o");
+    let compiler = Compiler::default();
+    export_module(&compiler, &compiler.build_exact_module("triple(x)+ln(x)"), "tripled_log", ExportFormat::Object, &output);
+
+    let object_file = fs::read(&output).unwrap();
+    let object = ElfObject::parse(&object_file);
+    assert!(object.defined_symbol("tripled_log").is_some() && object.defined_symbol("tripled_log_batch").is_some());
+    assert!(object.symbols.iter().all(|symbol| symbol.name != "fja" && symbol.name != "ln"));
+    assert!(object.symbols.iter().any(|symbol| symbol.name == "log" && !symbol.is_defined()));
+
+    let header = fs::read_to_string(directory.join("kernel.h")).unwrap();
+    assert!(header.contains("#ifndef TRIPLED_LOG_H") && header.contains("extern \"C\""));
+    assert!(header.contains("double triple(double);"));
+    assert!(!header.contains("_jet"));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+#[should_panic]
+fn library_export_4(){
+    let compiler = Compiler::default();
+    export_module(&compiler, &compiler.build_exact_module("sin(x)"), "2fast", ExportFormat::Object, &test_directory("library_export_4").join("kernel.o"));
+}
+
+#[test]
+fn library_export_5(){
+    // every ar member header is 60 bytes ending in "`\n", also when the symbol name is longer than the name field
+    let directory = test_directory("library_export_5");
+    let output = directory.join("libintegrand.a");
+    export_function("sin(x)*e^(x)", 0.5, 10, &CompilationOptions::default(), "integrand_kernel_v2", ExportFormat::StaticArchive, &output);
+
+    let archive = fs::read(&output).unwrap();
+    assert_eq!(&archive[..8], b"!<arch>\n");
+    let mut members = Vec::new();
+    let mut offset = 8;
+    while offset < archive.len() {
+        let header = &archive[offset..offset + 60];
+        assert_eq!(&header[58..], b"`\n", "member header at {}", offset);
+        let name = String::from_utf8_lossy(&header[..16]).trim_end().to_string();
+        let size: usize = String::from_utf8_lossy(&header[48..58]).trim_end().parse().unwrap();
+        members.push((name, archive[offset + 60..offset + 60 + size].to_vec()));
+        offset = (offset + 60 + size).next_multiple_of(2);
+    }
+    assert_eq!(members.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["/", "fja.o/"]);
+    let object = ElfObject::parse(&members[1].1);
+    assert!(object.defined_symbol("integrand_kernel_v2").is_some() && object.defined_symbol("integrand_kernel_v2_batch").is_some());
+    let index = String::from_utf8_lossy(&members[0].1);
+    assert!(index.contains("integrand_kernel_v2\0") && index.contains("integrand_kernel_v2_batch\0"));
+
+    if Command::new("cc").arg("--version").output().is_ok() {
+        let program = "#include <stdio.h>\n#include \"libintegrand.h\"\nint main(void) { printf(\"%.17g\\n\", integrand_kernel_v2(0.25)); return 0; }\n";
+        fs::write(directory.join("main.c"), program).unwrap();
+        let compiled = Command::new("cc").current_dir(&directory)
+            .args(["main.c", "-o", "main", "-L.", "-lintegrand", "-lm"])
+            .output().unwrap();
+        assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+        let printed = Command::new(directory.join("main")).output().unwrap();
+        let value: f64 = String::from_utf8_lossy(&printed.stdout).trim().parse().unwrap();
+        let (function, _) = Compiler::default().compile_function("sin(x)*e^(x)", 0.5, 10, &CompilationOptions::default());
+        assert_eq!(value.to_bits(), function.call(0.25).to_bits());
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+#[should_panic]
+fn library_export_6(){
+    // `ln` is called as `log` in the library, exporting as `log` would make the function call itself
+    let compiler = Compiler::default();
+    export_module(&compiler, &compiler.build_exact_module("ln(x)+1"), "log", ExportFormat::Object, &test_directory("library_export_6").join("kernel.o"));
+}
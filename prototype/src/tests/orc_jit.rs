@@ -0,0 +1,60 @@
+use crate::{
+    components::{
+        compilation_options::{CompilationOptions, ExecutionBackend},
+        symbol_resolver::register_external_function
+    },
+    stages::compiler::Compiler
+};
+
+extern "C" fn half(x: f64) -> f64 {
+    0.5*x
+}
+
+#[test]
+fn orc_jit_0(){
+    // both backends link the same object files, so they compute the same bits
+    let linker = Compiler::default();
+    let jit = Compiler::default().with_backend(ExecutionBackend::OrcJit);
+    assert_eq!(jit.backend(), ExecutionBackend::OrcJit);
+
+    let options = CompilationOptions { derivatives: 2, ..Default::default() };
+    for formula in ["sin(x)*e^(x)+ln(x)", "sqrt(x)/(1+x*x)", "exact(atg(x))*cos(x)"] {
+        let (linked, _) = linker.compile_function(formula, 0.5, 12, &options);
+        let (jitted, _) = jit.compile_function(formula, 0.5, 12, &options);
+        let exact = linker.compile_exact_function(formula);
+        let exact_jitted = jit.compile_exact_function(formula);
+        assert!(jitted.is_jit_compiled() && jitted.region().is_none());
+        assert!(!linked.is_jit_compiled());
+
+        for x in [0.3, 0.5, 0.7] {
+            assert_eq!(linked.call(x).to_bits(), jitted.call(x).to_bits(), "{}", formula);
+            assert_eq!(exact.call(x).to_bits(), exact_jitted.call(x).to_bits(), "{}", formula);
+        }
+    }
+}
+
+#[test]
+fn orc_jit_1(){
+    register_external_function("half", half);
+    let jit = Compiler::default().with_backend(ExecutionBackend::OrcJit);
+    let options = CompilationOptions { derivatives: 3, ..Default::default() };
+
+    let xs = [0.25, 0.5, 1.0, 2.0];
+    let mut ys = [0.0; 4];
+    jit.compile_exact_batch_function("half(x)+cbrt(x)").evaluate(&xs, &mut ys);
+    for (x, y) in xs.iter().zip(ys) {
+        assert_eq!(y, 0.5*x + x.cbrt());
+    }
+
+    let (jet, _) = jit.compile_jet_function("sin(x)", 0.0, 16, &options);
+    let derivatives = jet.evaluate(0.5);
+    for (derivative, expected) in derivatives.iter().zip([0.5f64.sin(), 0.5f64.cos(), -0.5f64.sin(), -0.5f64.cos()]) {
+        assert!((derivative - expected).abs() < 1e-9);
+    }
+
+    // every function has its own JIT instance, dropping one leaves the others working
+    let first = jit.compile_exact_function("x+1");
+    let second = jit.compile_exact_function("x+2");
+    drop(first);
+    assert_eq!(second.call(1.0), 3.0);
+}
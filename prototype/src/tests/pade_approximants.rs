@@ -0,0 +1,62 @@
+use crate::components::{
+    object_type_definitions::Func,
+    polynomials::TsPoly,
+    pade_approximants::apply_pade_approximation
+};
+
+fn assert_coefs_eq(actual: &[f64], expected: &[f64]) {
+    for (index, value) in expected.iter().enumerate() {
+        assert!((actual[index] - value).abs() < 1e-12, "coefitient {}: {} != {}", index, actual[index], value);
+    }
+}
+
+#[test]
+fn recenter_0(){
+    let poly = TsPoly::from_vec(vec![0.0, 0.0, 1.0], true);
+    assert_coefs_eq(&poly.recentered(1.0).coefs, &[1.0, 2.0, 1.0]);
+}
+
+#[test]
+fn recenter_1(){
+    let poly = TsPoly::from_vec(vec![5.0, -3.0, 0.0, 2.0], true);
+    let shifted = poly.recentered(-2.0);
+    // 5 - 3(t-2) + 2(t-2)^3 = -5 + 21t - 12t^2 + 2t^3
+    assert_coefs_eq(&shifted.coefs, &[-5.0, 21.0, -12.0, 2.0]);
+}
+
+#[test]
+fn pade_0(){
+    let exp_poly = TsPoly::generate_exp(0.0, 4, true);
+    let rational = exp_poly.pade(2, 2, 0.0);
+    assert_coefs_eq(&rational.numerator.coefs, &[1.0, 0.5, 1.0/12.0]);
+    assert_coefs_eq(&rational.denominator.coefs, &[1.0, -0.5, 1.0/12.0]);
+}
+
+#[test]
+fn pade_1(){
+    // 1/(1+x^2) has poles at +-i, so the Taylor series diverges at x = 2, but [4/4] recovers the function exactly
+    let series = TsPoly::from_vec(vec![1.0, 0.0, -1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 1.0], true);
+    let rational = series.pade(4, 4, 0.0);
+    assert!((rational.evaluate(2.0) - 0.2).abs() < 1e-12);
+}
+
+#[test]
+fn pade_2(){
+    let exp_poly = TsPoly::generate_exp(1.0, 6, true);
+    let rational = exp_poly.pade(3, 3, 1.0);
+    assert!((rational.evaluate(1.5) - f64::exp(1.5)).abs() < 1e-6);
+}
+
+#[test]
+fn pade_3(){
+    let mut sequence = vec![Func::Poly(TsPoly::from_vec(vec![1.0, 1.0], true))];
+    apply_pade_approximation(&mut sequence, 0.0, 3, 2);
+    assert_eq!(sequence, vec![Func::Poly(TsPoly::from_vec(vec![1.0, 1.0], true))]);
+}
+
+#[test]
+#[should_panic]
+fn pade_panic_0(){
+    let mut sequence = vec![Func::Poly(TsPoly::generate_exp(0.0, 4, true))];
+    apply_pade_approximation(&mut sequence, 0.0, 4, 5);
+}
@@ -0,0 +1,52 @@
+use crate::{
+    components::{
+        compilation_options::{CompilationOptions, EvaluationScheme, PassPipeline},
+        taylor_optimizer::optimize_postfix_using_taylor
+    },
+    stages::{
+        binary_compile::{generate_binary_from_module, optimize_module},
+        taylor_ir_compile::build_module_from_taylor_sequence
+    }
+};
+use super::lexing_and_postfix::postfix;
+
+fn module_for(function: &str, options: &CompilationOptions) -> crate::components::ir_builder::IrModule {
+    let mut sequence = postfix(function);
+    optimize_postfix_using_taylor(&mut sequence, 0.5, 9);
+    build_module_from_taylor_sequence(&sequence, options)
+}
+
+#[test]
+fn pipeline_0(){
+    assert_eq!(PassPipeline::Disabled.description(), None);
+    assert_eq!(PassPipeline::Default(3).description().unwrap(), "default<O3>");
+    assert_eq!(PassPipeline::Custom(String::from("instcombine,gvn")).description().unwrap(), "instcombine,gvn");
+}
+
+#[test]
+fn pipeline_1(){
+    let options = CompilationOptions { evaluation_scheme: EvaluationScheme::Horner, ..Default::default() };
+    let module = module_for("sin(x)*exp(x)+sin(x)*exp(x)", &options);
+    let report = optimize_module(&module, &PassPipeline::Default(3).description().unwrap());
+
+    assert!(report.instructions_before > 0);
+    assert!(report.instructions_after <= report.instructions_before, "{}", report);
+    assert_eq!(report.instructions_after, module.instruction_count());
+    assert!(module.verify().is_ok());
+    assert!(generate_binary_from_module(&module).1 > 0);
+}
+
+#[test]
+fn pipeline_2(){
+    let module = module_for("sin(x)/(exp(x)+2)", &CompilationOptions::default());
+    let report = optimize_module(&module, "instcombine,reassociate,gvn");
+    assert_eq!(report.pipeline, "instcombine,reassociate,gvn");
+    assert!(module.verify().is_ok());
+}
+
+#[test]
+#[should_panic]
+fn pipeline_panic_0(){
+    let module = module_for("sin(x)", &CompilationOptions::default());
+    optimize_module(&module, "not-a-pass");
+}
@@ -0,0 +1,91 @@
+use crate::{
+    components::{
+        object_type_definitions::Func,
+        compilation_options::{CompilationOptions, EvaluationScheme},
+        taylor_optimizer::optimize_postfix_using_taylor,
+        pade_approximants::apply_pade_approximation
+    },
+    stages::{
+        compiler::Compiler,
+        linking::CompiledFunction,
+        taylor_ir_compile::build_module_from_taylor_sequence
+    }
+};
+use super::lexing_and_postfix::postfix;
+
+const SCHEMES: [EvaluationScheme; 3] = [EvaluationScheme::SharedPowers, EvaluationScheme::Horner, EvaluationScheme::Estrin];
+const POINTS: [f64; 5] = [-0.4, 0.1, 0.5, 0.8, 1.3];
+
+fn optimized(function: &str, pade_denominator: Option<usize>) -> Vec<Func> {
+    let mut sequence = postfix(function);
+    optimize_postfix_using_taylor(&mut sequence, 0.5, 7);
+    if let Some(denominator_degree) = pade_denominator {
+        apply_pade_approximation(&mut sequence, 0.5, 7, denominator_degree);
+    }
+    sequence
+}
+
+fn compile_with(sequence: &[Func], options: &CompilationOptions) -> CompiledFunction {
+    let compiler = Compiler::default();
+    compiler.load(compiler.emit_object(&build_module_from_taylor_sequence(sequence, options)))
+}
+
+/// The optimized sequence evaluated in Rust, the reference every scheme has to agree with.
+fn reference(sequence: &[Func], x: f64) -> f64 {
+    let mut stack = Vec::<f64>::new();
+    for token in sequence {
+        let value = match token {
+            Func::Poly(poly) => poly.evaluate(x),
+            Func::Rational(rational) => rational.evaluate(x),
+            Func::Const(value) => *value,
+            Func::X => x,
+            operator => {
+                let (rhs, lhs) = (stack.pop().unwrap(), stack.pop().unwrap());
+                match operator {
+                    Func::Add => lhs + rhs,
+                    Func::Sub => lhs - rhs,
+                    Func::Mul => lhs*rhs,
+                    Func::Div => lhs/rhs,
+                    _ => panic!("unexpected {:?} in {:?}", operator, sequence)
+                }
+            }
+        };
+        stack.push(value);
+    }
+    stack.pop().unwrap()
+}
+
+fn assert_close(value: f64, expected: f64, what: &str) {
+    assert!((value - expected).abs() <= 1e-13*expected.abs().max(1.0), "{}: {} != {}", what, value, expected);
+}
+
+fn check_schemes(function: &str, pade_denominator: Option<usize>) {
+    let sequence = optimized(function, pade_denominator);
+    let shared_powers = compile_with(&sequence, &CompilationOptions::default());
+    for evaluation_scheme in SCHEMES {
+        for fused_multiply_add in [false, true] {
+            let options = CompilationOptions { evaluation_scheme, fused_multiply_add, ..Default::default() };
+            let compiled = compile_with(&sequence, &options);
+            for x in POINTS {
+                let what = format!("{} {:?} fma={} x={}", function, evaluation_scheme, fused_multiply_add, x);
+                assert_close(compiled.call(x), shared_powers.call(x), &what);
+                assert_close(compiled.call(x), reference(&sequence, x), &what);
+            }
+        }
+    }
+}
+
+#[test]
+fn schemes_0(){
+    check_schemes("sin(x)*exp(x)+2", None);
+}
+
+#[test]
+fn schemes_1(){
+    check_schemes("sin(x)/cos(x)", None);
+}
+
+#[test]
+fn schemes_2(){
+    check_schemes("exp(x)", Some(3));
+}
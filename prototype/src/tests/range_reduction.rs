@@ -0,0 +1,138 @@
+use crate::{
+    components::{
+        object_type_definitions::Func,
+        compilation_options::{CompilationOptions, EvaluationScheme},
+        range_reduction::{apply_range_reduction, ReducedFunc, TRIG_REDUCTION_LIMIT},
+        taylor_optimizer::optimize_postfix_using_taylor
+    },
+    stages::{
+        compiler::Compiler,
+        taylor_ir_compile::build_module_from_taylor_sequence
+    }
+};
+use super::lexing_and_postfix::postfix;
+
+fn reduced(function: &str, poly_degree: usize) -> Vec<Func> {
+    let mut sequence = postfix(function);
+    apply_range_reduction(&mut sequence, poly_degree);
+    optimize_postfix_using_taylor(&mut sequence, 0.0, poly_degree);
+    sequence
+}
+
+fn assert_relative(actual: f64, expected: f64, tolerance: f64) {
+    assert!((actual - expected).abs() <= tolerance*expected.abs().max(1.0), "{} != {}", actual, expected);
+}
+
+#[test]
+fn reduction_0(){
+    let sin = ReducedFunc::new(&Func::Sin, 13).unwrap();
+    let cos = ReducedFunc::new(&Func::Cos, 13).unwrap();
+    for x in [-1000.3, -7.0, -2.0, 0.0, 0.5, 2.5, 4.0, 1e5+0.25] {
+        assert_relative(sin.evaluate(x), f64::sin(x), 1e-11);
+        assert_relative(cos.evaluate(x), f64::cos(x), 1e-11);
+    }
+}
+
+#[test]
+fn reduction_1(){
+    let exp = ReducedFunc::new(&Func::Exp, 11).unwrap();
+    for x in [-700.0, -30.5, -1.0, 0.0, 0.3, 12.0, 300.0] {
+        assert_relative(exp.evaluate(x), f64::exp(x), 1e-12);
+    }
+    assert_eq!(exp.evaluate(1000.0), f64::INFINITY);
+}
+
+#[test]
+fn reduction_2(){
+    let ln = ReducedFunc::new(&Func::Ln, 15).unwrap();
+    for x in [1e-300, 0.001, 0.7, 1.0, 1.5, 2.0, 1e10, 1e300] {
+        assert_relative(ln.evaluate(x), f64::ln(x), 1e-13);
+    }
+}
+
+#[test]
+fn reduction_3(){
+    // Constant arguments are still evaluated statically, x dependent ones are kept for the IR
+    let sequence = reduced("sin(2)+exp(x)", 9);
+    assert!(matches!(sequence[0], Func::Const(_)));
+    assert!(matches!(sequence[1], Func::X));
+    assert!(matches!(sequence[2], Func::Reduced(ReducedFunc::Exp { .. })));
+}
+
+#[test]
+fn reduction_ir_0(){
+    for evaluation_scheme in [EvaluationScheme::SharedPowers, EvaluationScheme::Horner, EvaluationScheme::Estrin] {
+        for fused_multiply_add in [false, true] {
+            let options = CompilationOptions { evaluation_scheme, fused_multiply_add, range_reduction: true, ..Default::default() };
+            let sequence = reduced("sin(x)*cos(x^2)+exp(x)/ln(x)", 13);
+            let compiler = Compiler::default();
+            let compiled = compiler.load(compiler.emit_object(&build_module_from_taylor_sequence(&sequence, &options)));
+            for x in [0.3, 2.0, 17.5, 150.0] {
+                assert_relative(compiled.call(x), x.sin()*(x*x).cos() + x.exp()/x.ln(), 1e-10);
+            }
+        }
+    }
+}
+
+fn compile_reduced(function: &str, poly_degree: usize) -> impl Fn(f64) -> f64 {
+    let options = CompilationOptions { range_reduction: true, ..Default::default() };
+    let (compiled, _) = Compiler::default().compile_function(function, 0.0, poly_degree, &options);
+    move |x| compiled.call(x)
+}
+
+#[test]
+fn reduction_ir_1(){
+    let ln = compile_reduced("ln(x)", 15);
+    let reference = ReducedFunc::new(&Func::Ln, 15).unwrap();
+    for x in [-2.0, -0.0, f64::NEG_INFINITY, f64::NAN] {
+        assert!(ln(x).is_nan() == (x != 0.0) && reference.evaluate(x).is_nan() == (x != 0.0), "ln({})", x);
+    }
+    for x in [0.0, -0.0] {
+        assert_eq!(ln(x), f64::NEG_INFINITY);
+        assert_eq!(reference.evaluate(x), f64::NEG_INFINITY);
+    }
+    assert_eq!(ln(f64::INFINITY), f64::INFINITY);
+    for x in [1e-310, 5e-324, f64::MIN_POSITIVE, 2.5e-300, 1e300] {
+        assert_relative(ln(x), x.ln(), 1e-13);
+        assert_relative(reference.evaluate(x), x.ln(), 1e-13);
+    }
+}
+
+#[test]
+fn reduction_ir_2(){
+    let exp = compile_reduced("exp(x)", 13);
+    let reference = ReducedFunc::new(&Func::Exp, 13).unwrap();
+    for x in [-700.0, 1.0, 700.0, 709.5, 709.78] {
+        assert_relative(exp(x), x.exp(), 1e-12);
+        assert_relative(reference.evaluate(x), x.exp(), 1e-12);
+    }
+    // subnormal results are rounded once, to the nearest multiple of the smallest subnormal
+    let smallest = f64::from_bits(1);
+    for x in [-709.0, -720.0, -740.0, -744.0] {
+        assert!((exp(x) - x.exp()).abs() <= smallest, "exp({}) = {} != {}", x, exp(x), x.exp());
+        assert!((reference.evaluate(x) - x.exp()).abs() <= smallest);
+    }
+    for (x, expected) in [(709.79, f64::INFINITY), (1e300, f64::INFINITY), (f64::INFINITY, f64::INFINITY), (-750.0, 0.0), (-1e300, 0.0), (f64::NEG_INFINITY, 0.0)] {
+        assert_eq!(exp(x), expected, "exp({})", x);
+        assert_eq!(reference.evaluate(x), expected);
+    }
+    assert!(exp(f64::NAN).is_nan() && reference.evaluate(f64::NAN).is_nan());
+}
+
+#[test]
+fn reduction_ir_3(){
+    let sin = compile_reduced("sin(x)", 15);
+    let cos = compile_reduced("cos(x)", 15);
+    for x in [-1.5e6, -1000.3, 0.7, 1e6+0.3, TRIG_REDUCTION_LIMIT] {
+        assert_relative(sin(x), x.sin(), 1e-10);
+        assert_relative(cos(x), x.cos(), 1e-10);
+    }
+    // outside of the reduction limit the libm functions are called
+    for x in [TRIG_REDUCTION_LIMIT*1.5, 1e12, -1e17, 1e300] {
+        assert_eq!(sin(x).to_bits(), x.sin().to_bits());
+        assert_eq!(cos(x).to_bits(), x.cos().to_bits());
+    }
+    for x in [f64::INFINITY, f64::NAN] {
+        assert!(sin(x).is_nan() && cos(x).is_nan());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    components::{
+        elf_object::*,
+        executable_memory::ExecutableRegion,
+        symbol_resolver::global_resolver
+    },
+    stages::{
+        compiler::Compiler,
+        linking::{link_buffer, linked_size, FunctionType}
+    }
+};
+use super::elf_object::{build_object, rela};
+
+fn load(object_file: &[u8]) -> (FunctionType, ExecutableRegion) {
+    load_into(object_file, ExecutableRegion::allocate)
+}
+
+fn load_into(object_file: &[u8], allocate: impl FnOnce(usize) -> ExecutableRegion) -> (FunctionType, ExecutableRegion) {
+    let mut buffer = object_file.to_vec();
+    buffer.resize(linked_size(object_file).max(object_file.len()), 0);
+    let mut region = allocate(buffer.len());
+    let function = link_buffer(&mut buffer, region.as_ptr());
+    region.write(0, &buffer);
+    region.make_executable();
+    (function, region)
+}
+
+#[test]
+fn relocations_0(){
+    // jmp [rip+sin@GOTPCREL]
+    let text = vec![0xFF, 0x25, 0, 0, 0, 0];
+    let object_file = build_object(
+        vec![
+            (".text", 1, SHF_ALLOC | SHF_EXECINSTR, text, 0, 0),
+            (".rela.text", SHT_RELA, 0x40, rela(&[(2, 2, R_X86_64_GOTPCRELX, -4)]), 4, 1)
+        ],
+        &[("fja", 0x12, 1, 0), ("sin", 0x10, 0, 0)],
+        1
+    );
+    assert_eq!(linked_size(&object_file), ElfObject::parse(&object_file).file_size().next_multiple_of(8) + 8);
+
+    let (function, _code) = load(&object_file);
+    assert_eq!(function(0.5), 0.5f64.sin());
+}
+
+#[test]
+fn relocations_1(){
+    // mov rax, [rip+pointer]; addsd xmm0, [rax]; ret, the pointer to the constant is an absolute R_X86_64_64
+    let text = vec![0x48, 0x8B, 0x05, 0, 0, 0, 0, 0xF2, 0x0F, 0x58, 0x00, 0xC3];
+    let object_file = build_object(
+        vec![
+            (".text.fja", 1, SHF_ALLOC | SHF_EXECINSTR, text, 0, 0),
+            (".data.rel.ro", 1, SHF_ALLOC | 0x1, vec![0; 8], 0, 0),
+            (".rodata.cst8", 1, SHF_ALLOC | 0x10, 0.5f64.to_le_bytes().to_vec(), 0, 0),
+            (".rela.text.fja", SHT_RELA, 0x40, rela(&[(3, 1, R_X86_64_PC32, -4)]), 7, 1),
+            (".rela.data.rel.ro", SHT_RELA, 0x40, rela(&[(0, 2, R_X86_64_64, 0)]), 7, 2)
+        ],
+        &[("", STT_SECTION, 2, 0), ("", STT_SECTION, 3, 0), ("fja", 0x12, 1, 0)],
+        3
+    );
+
+    let (function, _code) = load(&object_file);
+    assert_eq!(function(1.0), 1.5);
+}
+
+#[test]
+fn relocations_2(){
+    // mov rax, answer; cvtsi2sd xmm0, rax; ret
+    let text = vec![0x48, 0xC7, 0xC0, 0, 0, 0, 0, 0xF2, 0x48, 0x0F, 0x2A, 0xC0, 0xC3];
+    let object_file = build_object(
+        vec![
+            (".text", 1, SHF_ALLOC | SHF_EXECINSTR, text, 0, 0),
+            (".rela.text", SHT_RELA, 0x40, rela(&[(3, 2, R_X86_64_32S, 2)]), 4, 1)
+        ],
+        &[("fja", 0x12, 1, 0), ("answer", 0x10, SHN_ABS as u16, 40)],
+        1
+    );
+
+    let (function, _code) = load(&object_file);
+    assert_eq!(function(0.0), 42.0);
+}
+
+#[test]
+#[should_panic]
+fn relocations_3(){
+    let object_file = build_object(
+        vec![
+            (".text", 1, SHF_ALLOC | SHF_EXECINSTR, vec![0x90; 8], 0, 0),
+            // R_X86_64_TPOFF32
+            (".rela.text", SHT_RELA, 0x40, rela(&[(0, 1, 23, 0)]), 4, 1)
+        ],
+        &[("fja", 0x12, 1, 0)],
+        1
+    );
+    load(&object_file);
+}
+
+/// Region at least 16 GB below libm, out of rel32 reach.
+fn far_region(size: usize) -> ExecutableRegion {
+    let libm = global_resolver().address("sin").unwrap();
+    let region = ExecutableRegion::allocate_near(size, libm - (16 << 30)).unwrap();
+    assert!((region.as_ptr().as_ptr() as usize).abs_diff(libm) > 1 << 31);
+    region
+}
+
+#[test]
+fn relocations_4(){
+    // jmp sin
+    let text = vec![0xE9, 0, 0, 0, 0];
+    let object_file = build_object(
+        vec![
+            (".text", 1, SHF_ALLOC | SHF_EXECINSTR, text, 0, 0),
+            (".rela.text", SHT_RELA, 0x40, rela(&[(1, 2, R_X86_64_PLT32, -4)]), 4, 1)
+        ],
+        &[("fja", 0x12, 1, 0), ("sin", 0x10, 0, 0)],
+        1
+    );
+    assert_eq!(linked_size(&object_file), ElfObject::parse(&object_file).file_size().next_multiple_of(8) + 16);
+
+    let (near, _near_code) = load(&object_file);
+    let (far, _far_code) = load_into(&object_file, far_region);
+    assert_eq!(near(0.5), 0.5f64.sin());
+    assert_eq!(far(0.5), 0.5f64.sin());
+}
+
+#[test]
+fn relocations_5(){
+    // every call of a compiled function goes through a trampoline
+    let compiler = Compiler::default();
+    let (object_file, len) = compiler.emit_object(&compiler.build_exact_module("sin(x)*e^(x)+ln(x)"));
+    let (function, _code) = load_into(&object_file[..len], far_region);
+    for x in [0.5f64, 1.0, 3.0] {
+        assert_eq!(function(x), x.sin()*x.exp() + x.ln());
+    }
+}
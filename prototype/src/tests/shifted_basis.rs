@@ -0,0 +1,55 @@
+use crate::{
+    components::{
+        object_type_definitions::Func,
+        polynomials::TsPoly,
+        compilation_options::{CompilationOptions, EvaluationScheme, PolynomialBasis},
+        taylor_optimizer::optimize_postfix_using_taylor_in_basis
+    },
+    stages::{
+        binary_compile::generate_binary_from_module,
+        taylor_ir_compile::build_module_from_taylor_sequence
+    }
+};
+use super::lexing_and_postfix::postfix;
+
+fn optimized(function: &str, precision_center: f64, poly_degree: usize, basis: PolynomialBasis) -> Vec<Func> {
+    let mut sequence = postfix(function);
+    optimize_postfix_using_taylor_in_basis(&mut sequence, precision_center, poly_degree, basis);
+    sequence
+}
+
+#[test]
+fn shifted_0(){
+    let series = TsPoly::generate_sin(40.0, 9, true);
+    assert_eq!(series.center, 40.0);
+    assert!((series.evaluate(40.3) - 40.3_f64.sin()).abs() < 1e-10);
+    assert!((series.into_monomial_basis().evaluate(40.3) - 40.3_f64.sin()).abs() < 1e-3);
+}
+
+#[test]
+fn shifted_1(){
+    let sequence = optimized("sin(x)*x-x^2+3", 30.0, 9, PolynomialBasis::Shifted);
+    let Func::Poly(poly) = &sequence[0] else { panic!("expected a polynomial, found {:?}", sequence) };
+    assert_eq!(sequence.len(), 1);
+    assert_eq!(poly.center, 30.0);
+    let expected = |x: f64| x.sin()*x - x*x + 3.0;
+    assert!((poly.evaluate(30.2) - expected(30.2)).abs() < 1e-9);
+}
+
+#[test]
+fn shifted_2(){
+    let sequence = optimized("exp(x)+x", 2.0, 7, PolynomialBasis::Monomial);
+    let Func::Poly(poly) = &sequence[0] else { panic!("expected a polynomial, found {:?}", sequence) };
+    assert_eq!(poly.center, 0.0);
+    assert!((poly.evaluate(2.1) - (2.1_f64.exp()+2.1)).abs() < 1e-7);
+}
+
+#[test]
+fn shifted_ir_0(){
+    for evaluation_scheme in [EvaluationScheme::SharedPowers, EvaluationScheme::Horner, EvaluationScheme::Estrin] {
+        let options = CompilationOptions { evaluation_scheme, basis: PolynomialBasis::Shifted, ..Default::default() };
+        let sequence = optimized("sin(x)/cos(x)+ln(x)", 25.0, 7, options.basis);
+        let (_buffer, buffer_len) = generate_binary_from_module(&build_module_from_taylor_sequence(&sequence, &options));
+        assert!(buffer_len > 0);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    components::{
+        object_type_definitions::Func,
+        symbol_resolver::{SymbolResolver, global_resolver, register_external_function},
+        expression_simplifier::simplify_postfix,
+        symbolic_differentiation::postfix_to_tree,
+        compilation_options::CompilationOptions
+    },
+    stages::{
+        compiler::Compiler,
+        function_lexing::{lex_function, convert_infix_to_postfix, is_builtin_name}
+    }
+};
+
+/// Series of J0, accurate to double precision for |x| < 4.
+extern "C" fn bessel_j0(x: f64) -> f64 {
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for k in 1..30 {
+        term *= -(x*x)/(4.0*(k*k) as f64);
+        sum += term;
+    }
+    sum
+}
+
+extern "C" fn twice(x: f64) -> f64 {
+    2.0*x
+}
+
+#[test]
+fn symbol_resolver_0(){
+    let libm = SymbolResolver::with_libm();
+    assert!(libm.unary_function("sin").is_some());
+    assert_eq!(libm.unary_function("ln").unwrap()(1.0f64.exp()), 1.0);
+    assert!(libm.contains("pow") && libm.unary_function("pow").is_none());
+
+    let mut resolver = SymbolResolver::new();
+    assert!(!resolver.contains("sin"));
+    resolver.register("twice", twice);
+    assert_eq!(resolver.address("twice"), Some(twice as *const () as usize));
+    assert_eq!(resolver.unary_function("twice").unwrap()(4.0), 8.0);
+}
+
+#[test]
+fn symbol_resolver_1(){
+    register_external_function("bessel_j0", bessel_j0);
+    assert!(global_resolver().contains("bessel_j0"));
+
+    let mut sequence = lex_function("bessel_j0(x)*2+cbrt(x)");
+    assert_eq!(sequence[..4], [Func::External(String::from("bessel_j0")), Func::Ob, Func::X, Func::Cb]);
+    assert!(sequence.contains(&Func::External(String::from("cbrt"))));
+    // builtin names stay builtin
+    assert_eq!(lex_function("sin(x)")[0], Func::Sin);
+
+    convert_infix_to_postfix(&mut sequence);
+    assert_eq!(postfix_to_tree(&sequence).to_string(), "bessel_j0(x)*2+cbrt(x)");
+
+    let mut constant = lex_function("cbrt(8)+x");
+    convert_infix_to_postfix(&mut constant);
+    simplify_postfix(&mut constant);
+    assert_eq!(postfix_to_tree(&constant).to_string(), "2+x");
+}
+
+#[test]
+fn symbol_resolver_2(){
+    register_external_function("bessel_j0", bessel_j0);
+    let compiler = Compiler::default();
+
+    let exact = compiler.compile_exact_function("bessel_j0(x)+cbrt(x)");
+    let (taylor, _) = compiler.compile_function("sin(x)*bessel_j0(2*x)", 0.5, 14, &CompilationOptions::default());
+    for x in [0.25f64, 0.5, 1.0] {
+        assert_eq!(exact.call(x), bessel_j0(x) + x.cbrt());
+        assert!((taylor.call(x) - x.sin()*bessel_j0(2.0*x)).abs() < 1e-6);
+    }
+}
+
+#[test]
+#[should_panic]
+fn symbol_resolver_3(){
+    SymbolResolver::new().register("2x", twice);
+}
+
+/// `fja` forwarding its argument (and constants for the other arguments) to `name`.
+fn forwarding_ir(name: &str, arity: usize) -> String {
+    let arguments = ["double %x", "double 0.75", "double -1.5"][..arity].join(", ");
+    let parameters = vec!["double"; arity].join(", ");
+    format!("declare double @{name}({parameters})\n\ndefine double @fja(double %x) {{\n  %r = call double @{name}({arguments})\n  ret double %r\n}}\n")
+}
+
+/// Correctly rounded values of the functions Rust's f64 doesn't have, computed with 200 bit precision (mpmath).
+/// acosh is listed here too, at arguments above 1 where it's defined.
+const SPECIAL_VALUES: [(&str, [(f64, f64); 3]); 7] = [
+    ("erf", [(0.125, 0.1403162048013338), (0.5, 0.5204998778130465), (0.875, 0.7840750610598597)]),
+    ("erfc", [(0.125, 0.8596837951986662), (0.5, 0.4795001221869535), (0.875, 0.21592493894014034)]),
+    ("tgamma", [(0.125, 7.533941598797612), (0.5, 1.772453850905516), (0.875, 1.089652357422897)]),
+    ("lgamma", [(0.125, 2.0194183575537963), (0.5, 0.5723649429247001), (0.875, 0.08585870722533433)]),
+    ("j0", [(0.125, 0.9960975630419852), (0.5, 0.9384698072408129), (0.875, 0.8175603610005849)]),
+    ("j1", [(0.125, 0.062378009134494684), (0.5, 0.2422684576748739), (0.875, 0.3969444806508056)]),
+    ("acosh", [(1.125, 0.4949329230945269), (1.5, 0.9624236501192069), (1.875, 1.2415784233077212)])
+];
+
+#[test]
+fn symbol_resolver_4(){
+    // generated code calls every libm function with the C calling convention, so it gets the same bits as a direct call,
+    // and the values match ones computed without libm
+    let resolver = SymbolResolver::with_libm();
+    let compiler = Compiler::default();
+    let reference = |name: &str, x: f64| -> Option<f64> {
+        Some(match name {
+            "sin" => x.sin(), "cos" => x.cos(), "tan" => x.tan(),
+            "asin" => x.asin(), "acos" => x.acos(), "atan" => x.atan(),
+            "sinh" => x.sinh(), "cosh" => x.cosh(), "tanh" => x.tanh(),
+            "asinh" => x.asinh(), "atanh" => x.atanh(),
+            "exp" => x.exp(), "exp2" => x.exp2(), "expm1" => x.exp_m1(),
+            "ln" => x.ln(), "log2" => x.log2(), "log10" => x.log10(), "log1p" => x.ln_1p(),
+            "sqrt" => x.sqrt(), "cbrt" => x.cbrt(), "rint" => x.round_ties_even(), "fabs" => x.abs(),
+            "pow" => x.powf(0.75), "fma" => x.mul_add(0.75, -1.5),
+            _ => return None
+        })
+    };
+
+    let mut names: Vec<&str> = resolver.names().collect();
+    names.sort();
+    assert!(names.len() >= 24);
+    for name in names {
+        let function = compiler.compile_ir(&forwarding_ir(name, resolver.arity(name).unwrap()));
+        let special = SPECIAL_VALUES.iter().find(|(special, _)| *special == name).map(|(_, values)| values);
+        let points = special.map_or([0.125, 0.5, 0.875], |values| values.map(|(x, _)| x));
+        for (index, x) in points.into_iter().enumerate() {
+            if let Some(direct) = resolver.unary_function(name) {
+                assert_eq!(function.call(x).to_bits(), direct(x).to_bits(), "{}({})", name, x);
+            }
+            let expected = match special {
+                Some(values) => values[index].1,
+                None => reference(name, x).unwrap_or_else(|| panic!("no reference value for {}", name))
+            };
+            assert!((function.call(x) - expected).abs() <= 4.0*f64::EPSILON*expected.abs(), "{}({}) = {} != {}", name, x, function.call(x), expected);
+        }
+    }
+}
+
+#[test]
+fn symbol_resolver_5(){
+    // names the lexer or libm already use can't be taken over, they would make the Taylor and the exact path disagree
+    for name in ["sin", "ln", "tan", "sqrt", "log", "atan", "e", "exact", "erf"] {
+        let registered = std::panic::catch_unwind(|| SymbolResolver::new().register(name, twice));
+        assert!(registered.is_err(), "'{}' was registered", name);
+    }
+    let mut resolver = SymbolResolver::new();
+    resolver.register("sine_table", twice);
+    assert!(resolver.contains("sine_table"));
+
+    assert!(is_builtin_name("e") && is_builtin_name("atan") && is_builtin_name("exact"));
+    assert!(!is_builtin_name("tan") && !is_builtin_name("e^") && !is_builtin_name("sine_table"));
+}
@@ -0,0 +1,62 @@
+use crate::{
+    components::symbolic_differentiation::{symbolic_derivative, postfix_to_tree, tree_to_postfix, expand_derivatives},
+    stages::{
+        function_lexing::{lex_function, convert_infix_to_postfix},
+        ir_compile::generate_ir,
+        compiler::Compiler
+    }
+};
+
+fn postfix(function: &str) -> Vec<crate::components::object_type_definitions::Func> {
+    let mut sequence = lex_function(function);
+    convert_infix_to_postfix(&mut sequence);
+    sequence
+}
+
+#[test]
+fn symbolic_differentiation_0(){
+    assert_eq!(symbolic_derivative("x^3").to_string(), "3*x^2");
+    assert_eq!(symbolic_derivative("2*x+7").to_string(), "2");
+    assert_eq!(symbolic_derivative("cos(x)").to_string(), "0-sin(x)");
+    assert_eq!(symbolic_derivative("sin(x)*ln(x)").to_string(), "cos(x)*ln(x)+sin(x)*1/x");
+    assert_eq!(symbolic_derivative("sin(3)*x").to_string(), format!("{}", 3.0f64.sin()));
+
+    let sequence = postfix("sin(7.56*x)*e^(x+1)-tg(x-8)/cos(x)");
+    assert_eq!(tree_to_postfix(&postfix_to_tree(&sequence)), sequence);
+
+    let mut nested = postfix("diff(diff(sin(x)))+1");
+    expand_derivatives(&mut nested);
+    assert_eq!(postfix_to_tree(&nested).to_string(), "0-sin(x)+1");
+}
+
+#[test]
+fn symbolic_differentiation_1(){
+    let functions = [
+        "sin(x)*ln(x)", "e^(x*x)/sqrt(x)", "tg(2*x)-ctg(x)", "asin(x/2)+acos(x/3)*atg(x)", "actg(x)", "x^x", "2^sin(x)", "(x+1)^0.5*cos(x)^3"
+    ];
+    for function in functions {
+        let tree = postfix_to_tree(&postfix(function));
+        let derivative = symbolic_derivative(function);
+        let reparsed = postfix_to_tree(&postfix(&derivative.to_string()));
+        for x in [0.3, 0.7, 1.1] {
+            let step = 1e-6;
+            let numerical = (tree.evaluate(x+step) - tree.evaluate(x-step))/(2.0*step);
+            let exact = derivative.evaluate(x);
+            assert!((exact - numerical).abs() < 1e-6*exact.abs().max(1.0), "{} at {}: {} != {}", function, x, exact, numerical);
+            assert!((reparsed.evaluate(x) - exact).abs() < 1e-12*exact.abs().max(1.0), "{} printed as {}", function, derivative);
+        }
+    }
+}
+
+#[test]
+fn symbolic_differentiation_2(){
+    let ir = generate_ir("diff(sin(x)*ln(x))");
+    assert!(ir.contains("@cos"));
+    assert!(ir.contains("@ln"));
+    assert!(ir.contains("@sin"));
+    assert!(!ir.contains("diff"));
+
+    let compiler = Compiler::default();
+    let (module, _) = compiler.build_taylor_module("diff(sin(x)*e^(x))", 0.5, 7, &Default::default());
+    assert!(module.dump().contains("define double @fja(double %x)"));
+}
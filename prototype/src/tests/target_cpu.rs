@@ -0,0 +1,68 @@
+use crate::{
+    components::{
+        compilation_options::{CompilationOptions, TargetCpu},
+        taylor_optimizer::optimize_postfix_using_taylor
+    },
+    stages::{
+        binary_compile::{generate_binary_for_target, TargetDescription},
+        compiler::Compiler,
+        taylor_ir_compile::build_module_from_taylor_sequence
+    }
+};
+use super::lexing_and_postfix::postfix;
+
+fn compile_for(target: &TargetDescription) -> (String, usize) {
+    let mut sequence = postfix("sin(x)*exp(x)");
+    optimize_postfix_using_taylor(&mut sequence, 0.5, 7);
+
+    let module = build_module_from_taylor_sequence(&sequence, &CompilationOptions::default());
+    module.set_target_attributes(&target.cpu, &target.features);
+    let (_, buffer_len) = generate_binary_for_target(&module, target);
+    (module.dump(), buffer_len)
+}
+
+#[test]
+fn target_cpu_0(){
+    let target = TargetDescription::resolve(&TargetCpu::Explicit { cpu: String::from("x86-64"), features: String::from("+sse2,-avx,+fma") });
+    assert_eq!(target.required_features(), vec!["sse2", "fma"]);
+    assert!(TargetDescription::generic().required_features().is_empty());
+    assert!(TargetDescription::generic().runs_on_host());
+}
+
+#[test]
+fn target_cpu_1(){
+    let host = TargetDescription::host();
+    assert!(!host.cpu.is_empty());
+    assert!(host.runs_on_host());
+
+    let (dump, buffer_len) = compile_for(&host);
+    assert!(buffer_len > 0);
+    assert!(dump.contains(&format!("\"target-cpu\"=\"{}\"", host.cpu)));
+}
+
+#[test]
+fn target_cpu_2(){
+    let (dump, buffer_len) = compile_for(&TargetDescription::generic());
+    assert!(buffer_len > 0);
+    assert!(!dump.contains("target-features"));
+
+    assert_eq!(TargetDescription::resolve(&TargetCpu::Host), TargetDescription::host());
+}
+
+#[test]
+fn target_cpu_3(){
+    // code for a feature this machine doesn't have can be emitted, but not loaded
+    let Some(missing) = TargetDescription::host().features.split(',').find_map(|feature| feature.strip_prefix('-')).map(String::from) else {
+        return;
+    };
+    let target = TargetDescription { cpu: String::from("x86-64"), features: format!("+sse2,+{}", missing) };
+    assert_eq!(target.missing_on_host(), vec![missing.clone()]);
+    assert!(!target.runs_on_host());
+
+    let compiler = Compiler::for_target(target);
+    let (module, _) = compiler.build_taylor_module("sin(x)*exp(x)", 0.5, 7, &CompilationOptions::default());
+    let binary = compiler.emit_object(&module);
+    assert!(binary.1 > 0);
+    let loaded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| compiler.load(binary)));
+    assert!(loaded.is_err(), "code needing {} was loaded", missing);
+}
@@ -1,3 +0,0 @@
-fn main() {
-    println!("cargo:rustc-link-arg=-Tprototype/linker.ld");
-}
\ No newline at end of file
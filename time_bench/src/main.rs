@@ -1,40 +1,77 @@
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::_rdtsc;
+use std::time::Instant;
 use prototype::{
-    components::auxilary_functions::parse_plot_input_file,
-    stages::{binary_compile::generate_function, custom_ir_compile::generate_custom_function, ir_compile::generate_ir}
+    components::{
+        auxilary_functions::parse_plot_input_file,
+        compilation_options::{CompilationOptions, EvaluationScheme, PassPipeline, ExecutionBackend}
+    },
+    stages::{
+        compiler::Compiler,
+        linking::CompiledFunction
+    }
 };
 
-fn main() {
-    println!("\nStarted time benchmark");
-    let plot_conf = parse_plot_input_file("./test_config.toml");
-    let x: f64 = plot_conf.precision_center+0.1;
-    
-    let fja = generate_function(&plot_conf.function, plot_conf.precision_center, plot_conf.poly_power);
-
+fn average_cycles(fja: &CompiledFunction, x: f64, samples: usize) -> f64 {
     let mut avg = 0.0;
 
-    for _ in 0..plot_conf.samples {
+    for _ in 0..samples {
         let ruler = unsafe{_rdtsc()};
-        let _temp_x = fja(x);
+        let _temp_x = fja.call(x);
         avg += (unsafe{_rdtsc()} - ruler) as f64;
     }
 
-    println!("\nMy approach => average {:.4} cycles", avg/(plot_conf.samples as f64));
+    avg/(samples as f64)
+}
+
+/// Compiles the function with both backends, reports their compile latency and the largest difference of their results.
+fn compare_backends(function: &str, precision_center: f64, poly_power: usize, samples: usize) {
+    let options = CompilationOptions::default();
+    let mut compiled = Vec::new();
+    for backend in [ExecutionBackend::Linker, ExecutionBackend::OrcJit] {
+        let compiler = Compiler::default().with_backend(backend);
+        let start = Instant::now();
+        let (fja, _) = compiler.compile_function(function, precision_center, poly_power, &options);
+        println!("{:?} backend => compiled in {:.3} ms, average {:.4} cycles", backend, start.elapsed().as_secs_f64()*1000.0, average_cycles(&fja, precision_center+0.1, samples));
+        compiled.push(fja);
+    }
 
+    let max_difference = (0..1000)
+        .map(|i| precision_center - 0.5 + i as f64/1000.0)
+        .map(|x| (compiled[0].call(x) - compiled[1].call(x)).abs())
+        .fold(0.0, f64::max);
+    println!("Largest difference between the backends => {:e}", max_difference);
+}
+
+fn main() {
+    println!("\nStarted time benchmark");
+    let plot_conf = parse_plot_input_file("./test_config.toml");
     let x: f64 = plot_conf.precision_center+0.1;
-    
-    let fja = generate_custom_function(generate_ir(&plot_conf.function));
-    
-    // let mut times: Vec<u64> = vec![0; samples];
-    avg = 0.0;
+    let compiler = Compiler::default();
 
-    for _ in 0..plot_conf.samples {
-        let ruler = unsafe{_rdtsc()};
-        let _temp_x = fja(x);
-        avg += (unsafe{_rdtsc()} - ruler) as f64;
+    let mut variants = Vec::new();
+    for evaluation_scheme in [EvaluationScheme::SharedPowers, EvaluationScheme::Horner, EvaluationScheme::Estrin] {
+        for fused_multiply_add in [false, true] {
+            let options = CompilationOptions { evaluation_scheme, fused_multiply_add, ..Default::default() };
+            let (fja, _) = compiler.compile_function(&plot_conf.function, plot_conf.precision_center, plot_conf.poly_power, &options);
+            variants.push((evaluation_scheme, fused_multiply_add, fja));
+        }
+    }
+
+    for (evaluation_scheme, fused_multiply_add, fja) in &variants {
+        println!("\nMy approach ({:?}, fma: {}) => average {:.4} cycles", evaluation_scheme, fused_multiply_add, average_cycles(fja, x, plot_conf.samples));
+    }
+
+    let options = CompilationOptions { pass_pipeline: PassPipeline::Default(3), ..Default::default() };
+    let (fja, report) = compiler.compile_function(&plot_conf.function, plot_conf.precision_center, plot_conf.poly_power, &options);
+    if let Some(pipeline) = report.pipeline {
+        println!("\nOptimization pipeline {}", pipeline);
     }
-    
+    println!("My approach (default<O3>) => average {:.4} cycles", average_cycles(&fja, x, plot_conf.samples));
+
+    let fja = compiler.compile_exact_function(&plot_conf.function);
+
+    println!("glibc => average {:.4} cycles\n", average_cycles(&fja, x, plot_conf.samples));
 
-    println!("glibc => average {:.4} cycles\n", avg/(plot_conf.samples as f64));
-}
\ No newline at end of file
+    compare_backends(&plot_conf.function, plot_conf.precision_center, plot_conf.poly_power, plot_conf.samples);
+}